@@ -0,0 +1,144 @@
+/// The kind of item an [`AccessFlags`] belongs to. Some bits are only valid for one kind, and 0x40/0x80 mean different things for fields and methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessFlagsContext {
+    Class,
+    Field,
+    Method,
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#access-flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AccessFlags(pub u32);
+
+/// Every flag with its smali keyword and the contexts it is valid in, in the order baksmali prints them.
+const FLAGS: &[(u32, &str, &[AccessFlagsContext])] = {
+    use AccessFlagsContext::{Class, Field, Method};
+    &[
+        (AccessFlags::PUBLIC, "public", &[Class, Field, Method]),
+        (AccessFlags::PRIVATE, "private", &[Class, Field, Method]),
+        (AccessFlags::PROTECTED, "protected", &[Class, Field, Method]),
+        (AccessFlags::STATIC, "static", &[Class, Field, Method]),
+        (AccessFlags::FINAL, "final", &[Class, Field, Method]),
+        (AccessFlags::SYNCHRONIZED, "synchronized", &[Method]),
+        (AccessFlags::VOLATILE, "volatile", &[Field]),
+        (AccessFlags::BRIDGE, "bridge", &[Method]),
+        (AccessFlags::TRANSIENT, "transient", &[Field]),
+        (AccessFlags::VARARGS, "varargs", &[Method]),
+        (AccessFlags::NATIVE, "native", &[Method]),
+        (AccessFlags::INTERFACE, "interface", &[Class]),
+        (AccessFlags::ABSTRACT, "abstract", &[Class, Method]),
+        (AccessFlags::STRICT, "strictfp", &[Method]),
+        (AccessFlags::SYNTHETIC, "synthetic", &[Class, Field, Method]),
+        (AccessFlags::ANNOTATION, "annotation", &[Class]),
+        (AccessFlags::ENUM, "enum", &[Class, Field]),
+        (AccessFlags::CONSTRUCTOR, "constructor", &[Method]),
+        (
+            AccessFlags::DECLARED_SYNCHRONIZED,
+            "declared-synchronized",
+            &[Method],
+        ),
+    ]
+};
+
+impl AccessFlags {
+    /// visible everywhere
+    pub const PUBLIC: u32 = 0x1;
+    /// only visible to the defining class
+    pub const PRIVATE: u32 = 0x2;
+    /// visible to the package and subclasses
+    pub const PROTECTED: u32 = 0x4;
+    /// not constructed with an outer `this` reference (classes), global to the defining class (fields), or does not take a `this` argument (methods)
+    pub const STATIC: u32 = 0x8;
+    /// not subclassable (classes), immutable after construction (fields), or not overridable (methods)
+    pub const FINAL: u32 = 0x10;
+    /// associated lock automatically acquired around the call to this method
+    pub const SYNCHRONIZED: u32 = 0x20;
+    /// special access rules to help with thread safety (fields only)
+    pub const VOLATILE: u32 = 0x40;
+    /// bridge method, added automatically by the compiler as a type-safe bridge (methods only)
+    pub const BRIDGE: u32 = 0x40;
+    /// not to be saved by default serialization (fields only)
+    pub const TRANSIENT: u32 = 0x80;
+    /// last argument should be treated as a "rest" argument by the compiler (methods only)
+    pub const VARARGS: u32 = 0x80;
+    /// implemented in native code
+    pub const NATIVE: u32 = 0x100;
+    /// multiply-implementable abstract class
+    pub const INTERFACE: u32 = 0x200;
+    /// not directly instantiable (classes) or unimplemented by this class (methods)
+    pub const ABSTRACT: u32 = 0x400;
+    /// strict rules for floating-point arithmetic
+    pub const STRICT: u32 = 0x800;
+    /// not directly defined in source code
+    pub const SYNTHETIC: u32 = 0x1000;
+    /// declared as an annotation class
+    pub const ANNOTATION: u32 = 0x2000;
+    /// declared as an enumerated type (classes) or an enumerated value (fields)
+    pub const ENUM: u32 = 0x4000;
+    /// constructor method (class or instance initializer)
+    pub const CONSTRUCTOR: u32 = 0x10000;
+    /// declared `synchronized`. This doesn't affect execution (other than in reflection of this flag, per se).
+    pub const DECLARED_SYNCHRONIZED: u32 = 0x20000;
+
+    pub fn contains(self, flag: u32) -> bool {
+        self.0 & flag == flag
+    }
+
+    /// Returns the bits that have no meaning in `context`.
+    pub fn unknown_bits(self, context: AccessFlagsContext) -> u32 {
+        let known = FLAGS
+            .iter()
+            .filter(|(_, _, contexts)| contexts.contains(&context))
+            .fold(0, |known, (flag, _, _)| known | flag);
+        self.0 & !known
+    }
+
+    /// Renders the flags valid in `context` as space-separated smali keywords in baksmali order, e.g. `public static final`. Unknown bits are left out.
+    pub fn to_human_readable(self, context: AccessFlagsContext) -> String {
+        FLAGS
+            .iter()
+            .filter(|(flag, _, contexts)| self.contains(*flag) && contexts.contains(&context))
+            .map(|(_, name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl From<u32> for AccessFlags {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_dependent_bits() {
+        let flags = AccessFlags(AccessFlags::PUBLIC | 0x40 | 0x80);
+        assert_eq!(
+            flags.to_human_readable(AccessFlagsContext::Field),
+            "public volatile transient"
+        );
+        assert_eq!(
+            flags.to_human_readable(AccessFlagsContext::Method),
+            "public bridge varargs"
+        );
+        assert_eq!(flags.unknown_bits(AccessFlagsContext::Class), 0xC0);
+    }
+
+    #[test]
+    fn test_baksmali_order() {
+        let flags = AccessFlags(
+            AccessFlags::CONSTRUCTOR
+                | AccessFlags::STATIC
+                | AccessFlags::DECLARED_SYNCHRONIZED
+                | AccessFlags::PUBLIC,
+        );
+        assert_eq!(
+            flags.to_human_readable(AccessFlagsContext::Method),
+            "public static constructor declared-synchronized"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+
+use crate::{
+    errors::{Error, TableIdxError},
+    utils::{read_u32_le, Reader},
+};
+
+use super::{encoded_value::EncodedAnnotation, Dex};
+
+/// https://source.android.com/docs/core/runtime/dex-format#visibility
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    /// intended only to be visible at build time (e.g., during compilation of other code)
+    Build,
+    /// intended to visible at runtime
+    Runtime,
+    /// intended to visible at runtime, but only to the underlying system (and not to regular user code)
+    System,
+    /// A visibility not described by the specification
+    Unknown(u8),
+}
+
+impl From<u8> for Visibility {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::Build,
+            0x01 => Self::Runtime,
+            0x02 => Self::System,
+            unknown => Self::Unknown(unknown),
+        }
+    }
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Build => f.write_str("build"),
+            Self::Runtime => f.write_str("runtime"),
+            Self::System => f.write_str("system"),
+            Self::Unknown(value) => write!(f, "unknown({value:#04x})"),
+        }
+    }
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#annotation-item
+#[derive(Debug)]
+pub struct AnnotationItem {
+    /// intended visibility of this annotation
+    pub visibility: Visibility,
+    /// encoded annotation contents
+    pub annotation: EncodedAnnotation,
+}
+
+impl AnnotationItem {
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(buffer, "annotation_item");
+        let visibility = Visibility::from(reader.u8()?);
+        let annotation = EncodedAnnotation::try_parse_from_reader(&mut reader)?;
+
+        Ok(Self {
+            visibility,
+            annotation,
+        })
+    }
+
+    /// Renders the annotation as the lines of a smali `.annotation` block, without indentation.
+    pub fn to_human_readable(&self, dex: &Dex) -> Result<Vec<String>, TableIdxError> {
+        let type_idx = self.annotation.type_idx as usize;
+        let annotation_type = dex
+            .types
+            .get(type_idx)
+            .ok_or(TableIdxError::Type(type_idx))?;
+
+        let mut lines = vec![format!(".annotation {} {annotation_type}", self.visibility)];
+        for element in self.annotation.elements_to_human_readable(dex)? {
+            lines.extend(element.lines().map(|line| format!("    {line}")));
+        }
+        lines.push(String::from(".end annotation"));
+        Ok(lines)
+    }
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#annotation-set-item
+#[derive(Debug, Default)]
+pub struct AnnotationSetItem {
+    /// offsets from the start of the file to each `annotation_item`. The elements must be sorted in increasing order, by `type_idx`.
+    pub entries: Vec<u32>,
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#set-ref-list
+#[derive(Debug, Default)]
+pub struct AnnotationSetRefList {
+    /// offsets from the start of the file to the referenced `annotation_set_item`, or `0` if there are no annotations for this element
+    pub list: Vec<u32>,
+}
+
+/// Parses a `uint size` followed by `size` offsets, the shape shared by `annotation_set_item` and `annotation_set_ref_list`.
+fn read_offset_list(buffer: &[u8], structure: &'static str) -> Result<Vec<u32>, Error> {
+    let mut reader = Reader::new(buffer, structure);
+    let size = reader.u32()? as usize;
+    // Reading the whole list up front keeps a corrupt size from causing a huge allocation
+    let entries = reader.bytes(size.saturating_mul(4))?;
+    Ok(entries
+        .chunks_exact(4)
+        .map(|entry| read_u32_le(entry, 0))
+        .collect())
+}
+
+impl AnnotationSetItem {
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        let entries = read_offset_list(buffer, "annotation_set_item")?;
+        Ok(Self { entries })
+    }
+}
+
+impl AnnotationSetRefList {
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        let list = read_offset_list(buffer, "annotation_set_ref_list")?;
+        Ok(Self { list })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dex::{encoded_value::EncodedValue, fixture::DexBuilder},
+        diagnostics::Diagnostics,
+        errors::ErrorKind,
+    };
+
+    #[test]
+    fn test_annotation_item() {
+        let mut builder = DexBuilder::new();
+        let type_idx = builder.type_("Ldalvik/annotation/Signature;");
+        let name_idx = builder.string("value");
+        let file = builder.build();
+        let diagnostics = Diagnostics::new(true);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        // system visibility, one element `value` set to true
+        let buffer = [0x02, type_idx as u8, 0x01, name_idx as u8, 0x3F];
+        let annotation = AnnotationItem::try_parse_from_bytes_unsized(&buffer).unwrap();
+        assert_eq!(annotation.visibility, Visibility::System);
+        assert_eq!(
+            annotation.annotation.elements[0].value,
+            EncodedValue::Boolean(true)
+        );
+        assert_eq!(
+            annotation.to_human_readable(&dex).unwrap(),
+            [
+                ".annotation system Ldalvik/annotation/Signature;",
+                "    value = true",
+                ".end annotation",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_visibility_is_kept() {
+        let annotation = AnnotationItem::try_parse_from_bytes_unsized(&[0x07, 0x00, 0x00]).unwrap();
+        assert_eq!(annotation.visibility, Visibility::Unknown(0x07));
+        assert_eq!(annotation.visibility.to_string(), "unknown(0x07)");
+    }
+
+    #[test]
+    fn test_truncated_annotation_item() {
+        // the annotation declares two elements but holds one
+        let e = AnnotationItem::try_parse_from_bytes_unsized(&[0x01, 0x00, 0x02, 0x00, 0x1E])
+            .unwrap_err();
+        assert_eq!(e.structure, "annotation_item");
+        assert_eq!(e.offset, 5);
+        assert!(matches!(e.kind, ErrorKind::Leb128(_)));
+    }
+
+    #[rustfmt::skip]
+    const OFFSETS: [u8; 12] = [
+        0x02, 0x00, 0x00, 0x00, // size
+        0x10, 0x04, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_offset_lists() {
+        let set = AnnotationSetItem::try_parse_from_bytes_unsized(&OFFSETS).unwrap();
+        assert_eq!(set.entries, [0x410, 0]);
+        let ref_list = AnnotationSetRefList::try_parse_from_bytes_unsized(&OFFSETS).unwrap();
+        assert_eq!(ref_list.list, [0x410, 0]);
+    }
+
+    #[test]
+    fn test_truncated_offset_lists() {
+        let e = AnnotationSetItem::try_parse_from_bytes_unsized(&OFFSETS[..8]).unwrap_err();
+        assert_eq!(e.structure, "annotation_set_item");
+        assert_eq!(e.offset, 4);
+        assert!(matches!(
+            e.kind,
+            ErrorKind::UnexpectedEof {
+                needed: 8,
+                available: 4
+            }
+        ));
+
+        // A huge size is rejected without allocating for it
+        let e = AnnotationSetRefList::try_parse_from_bytes_unsized(&[0xFF; 8]).unwrap_err();
+        assert_eq!(e.structure, "annotation_set_ref_list");
+        assert!(matches!(e.kind, ErrorKind::UnexpectedEof { .. }));
+    }
+}
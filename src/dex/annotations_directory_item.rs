@@ -0,0 +1,174 @@
+use crate::{
+    errors::Error,
+    traits::parse::TryParseFromBytes,
+    utils::{read_u32_le, Reader},
+};
+
+/// https://source.android.com/docs/core/runtime/dex-format#field-annotation
+#[allow(unused)]
+#[derive(Debug)]
+pub struct FieldAnnotation {
+    /// index into the `field_ids` list for the identity of the field being annotated
+    pub field_idx: u32,
+    /// offset from the start of the file to the list of annotations for the field. The offset should be to a location in the `data` section. The format of the data is specified by "`annotation_set_item`" below.
+    pub annotations_off: u32,
+}
+
+impl TryParseFromBytes for FieldAnnotation {
+    const NAME: &'static str = "field_annotation";
+    const SIZE: usize = 8;
+
+    fn parse_from_bytes(buffer: &[u8]) -> Self {
+        let field_idx = read_u32_le(buffer, 0);
+        let annotations_off = read_u32_le(buffer, 4);
+        Self {
+            field_idx,
+            annotations_off,
+        }
+    }
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#method-annotation
+#[allow(unused)]
+#[derive(Debug)]
+pub struct MethodAnnotation {
+    /// index into the `method_ids` list for the identity of the method being annotated
+    pub method_idx: u32,
+    /// offset from the start of the file to the list of annotations for the method. The offset should be to a location in the `data` section. The format of the data is specified by "`annotation_set_item`" below.
+    pub annotations_off: u32,
+}
+
+impl TryParseFromBytes for MethodAnnotation {
+    const NAME: &'static str = "method_annotation";
+    const SIZE: usize = 8;
+
+    fn parse_from_bytes(buffer: &[u8]) -> Self {
+        let method_idx = read_u32_le(buffer, 0);
+        let annotations_off = read_u32_le(buffer, 4);
+        Self {
+            method_idx,
+            annotations_off,
+        }
+    }
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#parameter-annotation
+#[allow(unused)]
+#[derive(Debug)]
+pub struct ParameterAnnotation {
+    /// index into the `method_ids` list for the identity of the method whose parameters are being annotated
+    pub method_idx: u32,
+    /// offset from the start of the file to the list of annotations for the method parameters. The offset should be to a location in the `data` section. The format of the data is specified by "`annotation_set_ref_list`" below.
+    pub annotations_off: u32,
+}
+
+impl TryParseFromBytes for ParameterAnnotation {
+    const NAME: &'static str = "parameter_annotation";
+    const SIZE: usize = 8;
+
+    fn parse_from_bytes(buffer: &[u8]) -> Self {
+        let method_idx = read_u32_le(buffer, 0);
+        let annotations_off = read_u32_le(buffer, 4);
+        Self {
+            method_idx,
+            annotations_off,
+        }
+    }
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#annotations-directory
+#[allow(unused)]
+#[derive(Debug, Default)]
+pub struct AnnotationsDirectoryItem {
+    /// offset from the start of the file to the annotations made directly on the class, or `0` if the class has no direct annotations. The offset, if non-zero, should be to a location in the `data` section. The format of the data is specified by "`annotation_set_item`" below.
+    pub class_annotations_off: u32,
+    /// list of associated field annotations. The elements of the list must be sorted in increasing order, by `field_idx`.
+    pub field_annotations: Vec<FieldAnnotation>,
+    /// list of associated method annotations. The elements of the list must be sorted in increasing order, by `method_idx`.
+    pub method_annotations: Vec<MethodAnnotation>,
+    /// list of associated method parameter annotations. The elements of the list must be sorted in increasing order, by `method_idx`.
+    pub parameter_annotations: Vec<ParameterAnnotation>,
+}
+
+impl AnnotationsDirectoryItem {
+    fn read_annotations<T: TryParseFromBytes>(
+        reader: &mut Reader,
+        size: usize,
+    ) -> Result<Vec<T>, Error> {
+        let mut annotations = Vec::with_capacity(size.min(reader.remaining() / T::SIZE));
+        for _ in 0..size {
+            annotations.push(reader.parse()?);
+        }
+        Ok(annotations)
+    }
+
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(buffer, "annotations_directory_item");
+        let class_annotations_off = reader.u32()?;
+        let fields_size = reader.u32()? as usize;
+        let annotated_methods_size = reader.u32()? as usize;
+        let annotated_parameters_size = reader.u32()? as usize;
+
+        let field_annotations = Self::read_annotations(&mut reader, fields_size)?;
+        let method_annotations = Self::read_annotations(&mut reader, annotated_methods_size)?;
+        let parameter_annotations = Self::read_annotations(&mut reader, annotated_parameters_size)?;
+
+        Ok(Self {
+            class_annotations_off,
+            field_annotations,
+            method_annotations,
+            parameter_annotations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{DexParseError, ErrorKind};
+
+    #[rustfmt::skip]
+    const DIRECTORY: [u8; 40] = [
+        0x00, 0x04, 0x00, 0x00, // class_annotations_off
+        0x01, 0x00, 0x00, 0x00, // fields_size
+        0x00, 0x00, 0x00, 0x00, // annotated_methods_size
+        0x02, 0x00, 0x00, 0x00, // annotated_parameters_size
+        0x03, 0x00, 0x00, 0x00, 0x10, 0x04, 0x00, 0x00,
+        0x05, 0x00, 0x00, 0x00, 0x20, 0x04, 0x00, 0x00,
+        0x06, 0x00, 0x00, 0x00, 0x30, 0x04, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_annotations_directory_item() {
+        let directory = AnnotationsDirectoryItem::try_parse_from_bytes_unsized(&DIRECTORY).unwrap();
+        assert_eq!(directory.class_annotations_off, 0x400);
+        assert_eq!(directory.field_annotations.len(), 1);
+        assert_eq!(directory.field_annotations[0].field_idx, 3);
+        assert_eq!(directory.field_annotations[0].annotations_off, 0x410);
+        assert!(directory.method_annotations.is_empty());
+        let parameters: Vec<_> = directory
+            .parameter_annotations
+            .iter()
+            .map(|annotation| (annotation.method_idx, annotation.annotations_off))
+            .collect();
+        assert_eq!(parameters, [(5, 0x420), (6, 0x430)]);
+    }
+
+    #[test]
+    fn test_truncated_annotations_directory_item() {
+        let e =
+            AnnotationsDirectoryItem::try_parse_from_bytes_unsized(&DIRECTORY[..36]).unwrap_err();
+        // the error names the entry that is cut short
+        assert_eq!(e.structure, "parameter_annotation");
+        assert_eq!(e.offset, 32);
+        assert!(matches!(
+            e.kind,
+            ErrorKind::Parse(DexParseError::InvalidElementSize { actual: 4, .. })
+        ));
+
+        let e =
+            AnnotationsDirectoryItem::try_parse_from_bytes_unsized(&DIRECTORY[..10]).unwrap_err();
+        assert_eq!(e.structure, "annotations_directory_item");
+        assert_eq!(e.offset, 8);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    errors::{Error, ErrorKind, TableIdxError},
+    traits::parse::TryParseFromBytes,
+    utils::read_u32_le,
+};
+
+use super::{
+    encoded_value::{EncodedArray, EncodedValue},
+    Dex,
+};
+
+/// https://source.android.com/docs/core/runtime/dex-format#call-site-id-item
+#[allow(unused)]
+#[derive(Debug)]
+pub struct CallSiteIdItem {
+    /// offset from the start of the file to call site definition. The offset should be in the data section, and the data there should be in the format specified by "`call_site_item`" below.
+    pub call_site_off: u32,
+}
+
+impl TryParseFromBytes for CallSiteIdItem {
+    const NAME: &'static str = "call_site_id_item";
+    const SIZE: usize = 4;
+
+    fn parse_from_bytes(buffer: &[u8]) -> Self {
+        let call_site_off = read_u32_le(buffer, 0);
+        Self { call_site_off }
+    }
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#call-site-item
+#[allow(unused)]
+#[derive(Debug)]
+pub struct CallSiteItem {
+    /// index into the `method_handles` list for the bootstrap linker method
+    pub method_handle_idx: u32,
+    /// index into the `string_ids` list for the name of the dynamically linked method
+    pub method_name_idx: u32,
+    /// index into the `proto_ids` list for the type of the dynamically linked method
+    pub method_type_idx: u32,
+    /// additional constant arguments passed to the bootstrap linker method
+    pub extra_arguments: Vec<EncodedValue>,
+}
+
+impl CallSiteItem {
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        let mut values = EncodedArray::try_parse_from_bytes_unsized(buffer)
+            .map_err(|e| Error {
+                structure: "call_site_item",
+                ..e
+            })?
+            .values
+            .into_iter();
+
+        let (
+            Some(EncodedValue::MethodHandle(method_handle_idx)),
+            Some(EncodedValue::String(method_name_idx)),
+            Some(EncodedValue::MethodType(method_type_idx)),
+        ) = (values.next(), values.next(), values.next())
+        else {
+            return Err(Error::new(
+                "call_site_item",
+                0,
+                ErrorKind::Malformed(String::from(
+                    "Call site must start with a method handle, a method name and a method type",
+                )),
+            ));
+        };
+
+        Ok(Self {
+            method_handle_idx,
+            method_name_idx,
+            method_type_idx,
+            extra_arguments: values.collect(),
+        })
+    }
+
+    /// Renders the call site the way baksmali does, e.g. `call_site_0("run", ()Ljava/lang/Runnable;)@Lfoo;->bootstrap(...)Ljava/lang/invoke/CallSite;`
+    pub fn to_human_readable(
+        &self,
+        call_site_idx: usize,
+        dex: &Dex,
+    ) -> Result<String, TableIdxError> {
+        let mut arguments = vec![
+            dex.string_literal(self.method_name_idx as usize)?,
+            dex.proto_descriptor(self.method_type_idx as usize)?,
+        ];
+        for argument in &self.extra_arguments {
+            arguments.push(argument.to_human_readable(dex)?);
+        }
+
+        let bootstrap = dex
+            .method_handles
+            .get(self.method_handle_idx as usize)
+            .ok_or(TableIdxError::MethodHandle(self.method_handle_idx as usize))?
+            .target_to_human_readable(dex)?;
+
+        Ok(format!(
+            "call_site_{call_site_idx}({})@{bootstrap}",
+            arguments.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dex::{fixture::DexBuilder, method_handle_item::MethodHandleItem},
+        diagnostics::Diagnostics,
+        traits::parse::TryParseFromBytes,
+    };
+
+    #[test]
+    fn test_call_site_item() {
+        // method handle 1, name "string 2", method type 3 and one extra int argument
+        let buffer = [0x04, 0x16, 0x01, 0x17, 0x02, 0x15, 0x03, 0x04, 0x2A];
+        let call_site = CallSiteItem::try_parse_from_bytes_unsized(&buffer).unwrap();
+        assert_eq!(call_site.method_handle_idx, 1);
+        assert_eq!(call_site.method_name_idx, 2);
+        assert_eq!(call_site.method_type_idx, 3);
+        assert_eq!(call_site.extra_arguments, [EncodedValue::Int(42)]);
+    }
+
+    #[test]
+    fn test_call_site_without_bootstrap() {
+        // The method name and type are swapped
+        let buffer = [0x03, 0x16, 0x01, 0x15, 0x03, 0x17, 0x02];
+        let e = CallSiteItem::try_parse_from_bytes_unsized(&buffer).unwrap_err();
+        assert_eq!(e.structure, "call_site_item");
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+    }
+
+    #[test]
+    fn test_truncated_call_site() {
+        let buffer = [0x03, 0x16, 0x01, 0x17];
+        let e = CallSiteItem::try_parse_from_bytes_unsized(&buffer).unwrap_err();
+        assert_eq!((e.structure, e.offset), ("call_site_item", 4));
+    }
+
+    #[test]
+    fn test_method_name_is_escaped() {
+        let mut builder = DexBuilder::new();
+        let proto_idx = builder.proto("L", "Ljava/lang/invoke/CallSite;", &[]);
+        let method_idx = builder.method("LFoo;", proto_idx, "bootstrap");
+        let name_idx = builder.string("say \"hi\"\\\t");
+        let type_idx = builder.proto("V", "V", &[]);
+        let file = builder.build();
+        let diagnostics = Diagnostics::new(true);
+        let mut dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+        let mut handle = [0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        handle[4..6].copy_from_slice(&(method_idx as u16).to_le_bytes());
+        dex.method_handles
+            .push(MethodHandleItem::try_parse_from_bytes(&handle).unwrap());
+
+        // method handle 0, the quoted name and an empty method type
+        let buffer = [0x03, 0x16, 0x00, 0x17, name_idx as u8, 0x15, type_idx as u8];
+        let call_site = CallSiteItem::try_parse_from_bytes_unsized(&buffer).unwrap();
+        assert_eq!(
+            call_site.to_human_readable(0, &dex).unwrap(),
+            "call_site_0(\"say \\\"hi\\\"\\\\\\t\", ()V)@LFoo;->bootstrap()Ljava/lang/invoke/CallSite;"
+        );
+    }
+}
@@ -1,9 +1,10 @@
-use crate::utils::decode_uleb128;
+use crate::{errors::Error, utils::Reader};
 
 use super::encoded::{EncodedField, EncodedMethod};
 
 /// https://source.android.com/docs/core/runtime/dex-format#class-data-item
 #[allow(unused)]
+#[derive(Default)]
 pub struct ClassDataItem {
     /// the defined static fields, represented as a sequence of encoded elements. The fields must be sorted by `field_idx` in increasing order.
     pub static_fields: Vec<EncodedField>,
@@ -17,79 +18,43 @@ pub struct ClassDataItem {
 
 #[allow(unused)]
 impl ClassDataItem {
-    fn read_encoded_fields(
-        buffer: &[u8],
-        offset: &mut usize,
-        size: usize,
-    ) -> std::io::Result<Vec<EncodedField>> {
-        let mut encoded_fields = Vec::with_capacity(size);
+    fn read_encoded_fields(reader: &mut Reader, size: usize) -> Result<Vec<EncodedField>, Error> {
+        let mut encoded_fields = Vec::with_capacity(size.min(reader.remaining()));
         let mut prev = 0;
         for _ in 0..size {
-            let encoded_field =
-                EncodedField::try_parse_from_bytes_with_offset(buffer, prev, offset)?;
+            let encoded_field = EncodedField::try_parse_from_reader(reader, prev)?;
             prev = encoded_field.field_idx;
             encoded_fields.push(encoded_field);
         }
         Ok(encoded_fields)
     }
 
-    fn read_encoded_methods(
-        buffer: &[u8],
-        offset: &mut usize,
-        size: usize,
-    ) -> std::io::Result<Vec<EncodedMethod>> {
-        let mut encoded_methods = Vec::with_capacity(size);
+    fn read_encoded_methods(reader: &mut Reader, size: usize) -> Result<Vec<EncodedMethod>, Error> {
+        let mut encoded_methods = Vec::with_capacity(size.min(reader.remaining()));
         let mut prev = 0;
         for _ in 0..size {
-            let encoded_method =
-                EncodedMethod::try_parse_from_bytes_with_offset(buffer, prev, offset)?;
+            let encoded_method = EncodedMethod::try_parse_from_reader(reader, prev)?;
             prev = encoded_method.method_idx;
             encoded_methods.push(encoded_method);
         }
         Ok(encoded_methods)
     }
 
-    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> std::io::Result<Self> {
-        let mut offset = 0;
-        let (static_fields_size, bytes_used) =
-            decode_uleb128(&buffer[offset..]).ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to decode ULEB128 for static fields size",
-            ))?;
-        offset += bytes_used;
-
-        let (instance_fields_size, bytes_used) =
-            decode_uleb128(&buffer[offset..]).ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to decode ULEB128 for instance fields size",
-            ))?;
-        offset += bytes_used;
-
-        let (direct_methods_size, bytes_used) =
-            decode_uleb128(&buffer[offset..]).ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to decode ULEB128 for direct methods size",
-            ))?;
-        offset += bytes_used;
-
-        let (virtual_methods_size, bytes_used) =
-            decode_uleb128(&buffer[offset..]).ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to decode ULEB128 for virtual methods size",
-            ))?;
-        offset += bytes_used;
-
-        let static_fields =
-            Self::read_encoded_fields(buffer, &mut offset, static_fields_size as usize)?;
-
-        let instance_fields =
-            Self::read_encoded_fields(buffer, &mut offset, instance_fields_size as usize)?;
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        Self::try_parse_from_reader(&mut Reader::new(buffer, "class_data_item"))
+    }
 
-        let direct_methods =
-            Self::read_encoded_methods(buffer, &mut offset, direct_methods_size as usize)?;
+    /// Reads a `class_data_item`, leaving `reader` just past its last method.
+    pub fn try_parse_from_reader(reader: &mut Reader) -> Result<Self, Error> {
+        let static_fields_size = reader.uleb128("static_fields_size")?;
+        let instance_fields_size = reader.uleb128("instance_fields_size")?;
+        let direct_methods_size = reader.uleb128("direct_methods_size")?;
+        let virtual_methods_size = reader.uleb128("virtual_methods_size")?;
 
-        let virtual_methods =
-            Self::read_encoded_methods(buffer, &mut offset, virtual_methods_size as usize)?;
+        let static_fields = Self::read_encoded_fields(reader, static_fields_size as usize)?;
+        let instance_fields = Self::read_encoded_fields(reader, instance_fields_size as usize)?;
+        let direct_methods = Self::read_encoded_methods(reader, direct_methods_size as usize)?;
+        let virtual_methods = Self::read_encoded_methods(reader, virtual_methods_size as usize)?;
 
         Ok(ClassDataItem {
             static_fields,
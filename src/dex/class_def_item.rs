@@ -1,5 +1,7 @@
 use crate::{traits::parse::TryParseFromBytes, utils::read_u32_le};
 
+use super::access_flags::AccessFlags;
+
 /// https://source.android.com/docs/core/runtime/dex-format#class-def-item
 #[allow(unused)]
 #[derive(Debug)]
@@ -7,7 +9,7 @@ pub struct ClassDefItem {
     /// index into the `type_ids` list for this class. This must be a class type, and not an array or primitive type.
     pub class_idx: u32,
     /// access flags for the class (public, final, etc.). See "`access_flags` Definitions" for details.
-    pub access_flags: u32,
+    pub access_flags: AccessFlags,
     /// index into the `type_ids` list for the superclass, or the constant value `NO_INDEX` if this class has no superclass (i.e., it is a root class such as `Object`). If present, this must be a class type, and not an array or primitive type.
     pub superclass_idx: u32,
     /// offset from the start of the file to the list of interfaces, or `0` if there are none. This offset should be in the data section, and the data there should be in the format specified by "`type_list`" below. Each of the elements of the list must be a class type (not an array or primitive type), and there must not be any duplicates.
@@ -28,7 +30,7 @@ impl TryParseFromBytes for ClassDefItem {
 
     fn parse_from_bytes(buffer: &[u8]) -> Self {
         let class_idx = read_u32_le(buffer, 0);
-        let access_flags = read_u32_le(buffer, 4);
+        let access_flags = AccessFlags::from(read_u32_le(buffer, 4));
         let superclass_idx = read_u32_le(buffer, 8);
         let interfaces_off = read_u32_le(buffer, 12);
         let source_file_idx = read_u32_le(buffer, 16);
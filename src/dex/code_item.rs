@@ -1,10 +1,23 @@
-use crate::utils::{read_u16_le, read_u32_le};
+use std::collections::{BTreeMap, BTreeSet};
 
-use super::instruction::Instruction;
+use crate::{errors::Error, traits::parse::TryParseFromBytes, utils::Reader};
+
+use super::{
+    instruction::{
+        verify::{verify_instructions, TableSizes},
+        Instruction,
+    },
+    try_item::{try_parse_catch_handler_list, CatchHandler, TryItem},
+};
+
+/// Labels keyed by the address they are printed at and their prefix, e.g. `(12, "goto")` for `:goto_0`
+type Labels = BTreeMap<(u32, &'static str), String>;
 
 #[allow(unused)]
 #[derive(Debug)]
 pub struct CodeItem {
+    /// offset from the start of the file to this item, as referenced by `encoded_method::code_off`. Only known when parsed through `Dex::code_item`, `0` otherwise.
+    pub offset: u32,
     /// the number of registers used by this code
     pub registers_size: u16,
     /// the number of words of incoming arguments to the method that this code is for
@@ -18,52 +31,73 @@ pub struct CodeItem {
     /// size of the instructions list, in 16-bit code units
     pub insns_size: u32,
     pub insns: Vec<Instruction>,
+    /// address of each of `insns` in 16-bit code units, in ascending order
+    addresses: Vec<u32>,
+    /// array indicating where in the code exceptions are caught and how to handle them. Elements of the array must be non-overlapping in range and in order from low to high address.
+    pub tries: Vec<TryItem>,
+    /// bytes representing a list of lists of catch types and associated handler addresses. Each `try_item` has a byte-wise offset into this structure.
+    pub handlers: Vec<CatchHandler>,
 }
 
 impl CodeItem {
-    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> std::io::Result<Self> {
-        if buffer.len() < 16 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::UnexpectedEof,
-                "Buffer too small for CodeItem header",
-            ));
+    #[allow(unused)]
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        match Self::try_parse_from_bytes_lenient(buffer)? {
+            (_, Some(e)) => Err(e),
+            (code_item, None) => Ok(code_item),
         }
+    }
 
-        let registers_size = read_u16_le(buffer, 0);
-        let ins_size = read_u16_le(buffer, 2);
-        let outs_size = read_u16_le(buffer, 4);
-        let tries_size = read_u16_le(buffer, 6);
-        let debug_info_off = read_u32_le(buffer, 8);
-        let insns_size = read_u32_le(buffer, 12);
-
-        let insns_bytes = insns_size as usize * 2;
+    /// Like [`Self::try_parse_from_bytes_unsized`], except that an instruction that fails to decode does not fail the whole item. `insns` then holds the instructions before it, and the error is returned alongside.
+    pub fn try_parse_from_bytes_lenient(buffer: &[u8]) -> Result<(Self, Option<Error>), Error> {
+        let mut reader = Reader::new(buffer, "code_item");
+        let registers_size = reader.u16()?;
+        let ins_size = reader.u16()?;
+        let outs_size = reader.u16()?;
+        let tries_size = reader.u16()?;
+        let debug_info_off = reader.u32()?;
+        let insns_size = reader.u32()?;
 
-        if buffer.len() < 16 + insns_bytes {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::UnexpectedEof,
-                "Buffer too small for CodeItem instructions",
-            ));
-        }
+        let insns_start = reader.offset;
+        let insns_bytes = reader.bytes((insns_size as usize).saturating_mul(2))?;
 
-        let mut insns = Vec::with_capacity(insns_size as usize);
+        let mut insns = Vec::new();
+        let mut addresses = Vec::new();
         let mut total_size = 0;
-        while total_size < insns_bytes {
-            let offset = 16 + total_size;
-            let insn = match Instruction::try_decode(&buffer[offset..]) {
+        let mut decode_error = None;
+        while total_size < insns_bytes.len() {
+            let insn = match Instruction::try_decode(&insns_bytes[total_size..]) {
                 Ok(insn) => insn,
                 Err(e) => {
-                    println!(
-                        "!!!!!!!! Failed to decode instruction at offset {}: {}",
-                        offset, e
-                    );
+                    decode_error = Some(Error::new("code_item", insns_start + total_size, e));
                     break;
                 }
             };
+            addresses.push(total_size as u32 / 2);
             total_size += insn.size_bytes();
             insns.push(insn);
         }
 
-        Ok(CodeItem {
+        // The tries are 4-byte aligned, so an odd number of code units is followed by two bytes of padding
+        if tries_size != 0 && insns_size % 2 == 1 {
+            reader.u16()?;
+        }
+
+        let mut tries = Vec::with_capacity(tries_size as usize);
+        for _ in 0..tries_size {
+            tries.push(reader.parse::<TryItem>()?);
+        }
+
+        let handlers = if tries_size != 0 {
+            let handlers_start = reader.offset;
+            try_parse_catch_handler_list(buffer.get(handlers_start..).unwrap_or_default())
+                .map_err(|e| e.offset_by(handlers_start))?
+        } else {
+            Vec::new()
+        };
+
+        let code_item = CodeItem {
+            offset: 0,
             registers_size,
             ins_size,
             outs_size,
@@ -71,6 +105,310 @@ impl CodeItem {
             debug_info_off,
             insns_size,
             insns,
-        })
+            addresses,
+            tries,
+            handlers,
+        };
+        Ok((code_item, decode_error))
+    }
+
+    /// Iterates over the instructions along with their address in 16-bit code units.
+    pub fn instructions(&self) -> impl Iterator<Item = (u32, &Instruction)> {
+        self.addresses.iter().copied().zip(&self.insns)
+    }
+
+    /// Returns the instruction starting at `address`, or `None` if no instruction starts there.
+    pub fn instruction_at(&self, address: u32) -> Option<&Instruction> {
+        let idx = self.addresses.binary_search(&address).ok()?;
+        self.insns.get(idx)
+    }
+
+    /// Returns the offset from the start of the file to the code unit at `address`, or `None` if `address` is past the end of `insns`.
+    pub fn file_offset(&self, address: u32) -> Option<u32> {
+        (address < self.insns_size).then(|| self.offset + 16 + address * 2)
+    }
+
+    /// Returns the offset from the start of the file to the `try_item` at index `idx` of `tries`.
+    pub fn try_item_offset(&self, idx: usize) -> usize {
+        // The tries follow the insns, padded to 4-byte alignment
+        let insns_end = 16 + self.insns_size as usize * 2;
+        self.offset as usize + insns_end.next_multiple_of(4) + idx * TryItem::SIZE
+    }
+
+    /// Checks the bytecode against the constraints of ART's method verifier, see [`verify_instructions`]. Violations are reported at their offset from the start of the file.
+    pub fn verify(&self, tables: &TableSizes) -> Vec<Error> {
+        let instructions: Vec<_> = self.instructions().collect();
+        verify_instructions(&instructions, self.registers_size, tables)
+            .into_iter()
+            .map(|(address, e)| {
+                let offset = self.file_offset(address).unwrap_or(self.offset);
+                Error::new("code_item", offset as usize, e)
+            })
+            .collect()
+    }
+
+    /// Assigns baksmali-style labels to every branch target and payload, keyed by target address and label prefix, along with the address of the switch that references each switch payload.
+    ///
+    /// Labels of one prefix are numbered in address order, so `:goto_0` is the first `goto` target in the code rather than the target of the first `goto`.
+    fn labels(&self) -> (Labels, BTreeMap<u32, u32>) {
+        let mut targets: BTreeMap<&'static str, BTreeSet<u32>> = BTreeMap::new();
+        let mut switches = BTreeMap::new();
+
+        for (address, insn) in self.instructions() {
+            let target = match insn {
+                Instruction::Goto { offset } => Some(("goto", i32::from(*offset))),
+                Instruction::Goto16 { offset } => Some(("goto", i32::from(*offset))),
+                Instruction::Goto32 { offset } => Some(("goto", *offset)),
+                Instruction::IfEq { offset, .. }
+                | Instruction::IfNe { offset, .. }
+                | Instruction::IfLt { offset, .. }
+                | Instruction::IfGe { offset, .. }
+                | Instruction::IfGt { offset, .. }
+                | Instruction::IfLe { offset, .. }
+                | Instruction::IfEqz { offset, .. }
+                | Instruction::IfNez { offset, .. }
+                | Instruction::IfLtz { offset, .. }
+                | Instruction::IfGez { offset, .. }
+                | Instruction::IfGtz { offset, .. }
+                | Instruction::IfLez { offset, .. } => Some(("cond", i32::from(*offset))),
+                Instruction::PackedSwitch { offset, .. } => Some(("pswitch_data", *offset)),
+                Instruction::SparseSwitch { offset, .. } => Some(("sswitch_data", *offset)),
+                Instruction::FillArrayData { offset, .. } => Some(("array", *offset)),
+                _ => None,
+            };
+            if let Some((prefix, offset)) = target {
+                let target = address.wrapping_add_signed(offset);
+                targets.entry(prefix).or_default().insert(target);
+                if matches!(prefix, "pswitch_data" | "sswitch_data") {
+                    switches.entry(target).or_insert(address);
+                }
+            }
+        }
+
+        // Switch targets are relative to the switch instruction, not to the payload
+        for (payload_address, switch_address) in &switches {
+            let (prefix, offsets) = match self.instruction_at(*payload_address) {
+                Some(Instruction::PackedSwitchPayload { targets, .. }) => ("pswitch", targets),
+                Some(Instruction::SparseSwitchPayload { targets, .. }) => ("sswitch", targets),
+                _ => continue,
+            };
+            let entry = targets.entry(prefix).or_default();
+            entry.extend(
+                offsets
+                    .iter()
+                    .map(|offset| switch_address.wrapping_add_signed(*offset)),
+            );
+        }
+
+        let mut labels = BTreeMap::new();
+        for (prefix, addresses) in targets {
+            for (i, address) in addresses.into_iter().enumerate() {
+                labels.insert((address, prefix), format!(":{prefix}_{i}"));
+            }
+        }
+        (labels, switches)
+    }
+
+    /// Builds what instructions of this code need to render their operands. With `parameter_registers`, the last `ins_size` registers are named `p0..pN` the way smali does.
+    pub fn render_context(&self, parameter_registers: bool) -> RenderContext {
+        let (labels, switches) = self.labels();
+        RenderContext {
+            labels,
+            switches,
+            first_parameter_register: parameter_registers
+                .then(|| self.registers_size.saturating_sub(self.ins_size)),
+        }
+    }
+
+    /// Resolves the `encoded_catch_handler` referenced by `try_item`.
+    pub fn catch_handler(&self, try_item: &TryItem) -> Option<&CatchHandler> {
+        self.handlers
+            .iter()
+            .find(|handler| handler.offset == try_item.handler_off)
+    }
+}
+
+/// Per-method state used by `Instruction::to_human_readable`.
+#[derive(Debug, Default)]
+pub struct RenderContext {
+    /// labels of the branch targets and payloads in this code, keyed by target address and label prefix
+    labels: Labels,
+    /// address of the switch instruction referencing each switch payload, keyed by payload address
+    switches: BTreeMap<u32, u32>,
+    /// the register holding the first incoming argument (`this` for instance methods), or `None` to name every register `vN`
+    first_parameter_register: Option<u16>,
+}
+
+impl RenderContext {
+    /// The labels to print right before the instruction at `address`, ordered by prefix as baksmali does.
+    pub fn labels_at(&self, address: u32) -> impl Iterator<Item = &str> {
+        self.labels
+            .range((address, "")..)
+            .take_while(move |((label_address, _), _)| *label_address == address)
+            .map(|(_, label)| label.as_str())
+    }
+
+    /// Names the target `offset` code units away from `base` with its `prefix` label, falling back to the raw offset if the pass found no such target.
+    pub fn label(&self, prefix: &'static str, base: u32, offset: i32) -> String {
+        match self.labels.get(&(base.wrapping_add_signed(offset), prefix)) {
+            Some(label) => label.clone(),
+            None => format!("{offset}"),
+        }
+    }
+
+    /// The address of the switch instruction that references the payload at `payload_address`.
+    pub fn switch_address(&self, payload_address: u32) -> Option<u32> {
+        self.switches.get(&payload_address).copied()
+    }
+
+    /// Names `register` as smali does: `pN` for the incoming arguments if enabled, `vN` otherwise.
+    pub fn register(&self, register: u16) -> String {
+        match self.first_parameter_register {
+            Some(first) if register >= first => format!("p{}", register - first),
+            _ => format!("v{register}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruction_addresses() {
+        #[rustfmt::skip]
+        let buffer = [
+            1, 0, 0, 0, 0, 0, 0, 0, // registers_size, ins_size, outs_size, tries_size
+            0, 0, 0, 0, 4, 0, 0, 0, // debug_info_off, insns_size
+            0x13, 0x00, 0x2a, 0x00, // const/16 v0, 42
+            0x00, 0x00, // nop
+            0x0e, 0x00, // return-void
+        ];
+        let mut code_item = CodeItem::try_parse_from_bytes_unsized(&buffer).unwrap();
+        code_item.offset = 0x100;
+
+        let addresses: Vec<u32> = code_item
+            .instructions()
+            .map(|(address, _)| address)
+            .collect();
+        assert_eq!(addresses, [0, 2, 3]);
+        assert!(matches!(
+            code_item.instruction_at(2),
+            Some(Instruction::Nop)
+        ));
+        assert!(code_item.instruction_at(1).is_none());
+        assert_eq!(code_item.file_offset(3), Some(0x116));
+        assert_eq!(code_item.file_offset(4), None);
+    }
+
+    #[test]
+    fn test_truncated_insns() {
+        #[rustfmt::skip]
+        let buffer = [
+            1, 0, 0, 0, 0, 0, 0, 0, // registers_size, ins_size, outs_size, tries_size
+            0, 0, 0, 0, 4, 0, 0, 0, // debug_info_off, insns_size
+            0x0e, 0x00, // return-void, then nothing where three more code units were declared
+        ];
+        let e = CodeItem::try_parse_from_bytes_unsized(&buffer)
+            .unwrap_err()
+            .offset_by(0x100)
+            .with_context("LFoo;->bar()V");
+        assert_eq!(e.structure, "code_item");
+        assert_eq!(e.offset, 0x110);
+        assert_eq!(
+            e.to_string(),
+            "Failed to parse code_item at offset 0x110 in LFoo;->bar()V: Unexpected end of data, needed 8 bytes but only 2 are left"
+        );
+    }
+
+    #[test]
+    fn test_lenient_keeps_decoded_prefix() {
+        #[rustfmt::skip]
+        let buffer = [
+            1, 0, 0, 0, 0, 0, 0, 0, // registers_size, ins_size, outs_size, tries_size
+            0, 0, 0, 0, 2, 0, 0, 0, // debug_info_off, insns_size
+            0x0e, 0x00, // return-void
+            0x3e, 0x00, // unused opcode
+        ];
+        let (code_item, e) = CodeItem::try_parse_from_bytes_lenient(&buffer).unwrap();
+        assert_eq!(code_item.insns, [Instruction::ReturnVoid]);
+        assert_eq!(e.unwrap().offset, 18);
+        assert!(CodeItem::try_parse_from_bytes_unsized(&buffer).is_err());
+    }
+
+    #[test]
+    fn test_tries_and_handlers() {
+        #[rustfmt::skip]
+        let buffer = [
+            1, 0, 0, 0, 0, 0, 2, 0, // registers_size, ins_size, outs_size, tries_size
+            0, 0, 0, 0, 1, 0, 0, 0, // debug_info_off, insns_size
+            0x0e, 0x00, // return-void
+            0, 0, // padding
+            0, 0, 0, 0, 1, 0, 1, 0, // start_addr, insn_count, handler_off
+            0, 0, 0, 0, 1, 0, 9, 0, // a handler_off that starts no handler
+            0x01, // encoded_catch_handler_list size
+            0x7F, 0x02, 0x00, 0x00, // catch type 2 at 0, then a catch-all at 0
+        ];
+        let mut code_item = CodeItem::try_parse_from_bytes_unsized(&buffer).unwrap();
+        code_item.offset = 0x100;
+
+        let handler = code_item.catch_handler(&code_item.tries[0]).unwrap();
+        assert_eq!(handler.handlers[0].type_idx, 2);
+        assert_eq!(handler.catch_all_addr, Some(0));
+        assert!(code_item.catch_handler(&code_item.tries[1]).is_none());
+        assert_eq!(code_item.try_item_offset(1), 0x100 + 28);
+    }
+
+    #[test]
+    fn test_parameter_register_names() {
+        #[rustfmt::skip]
+        let buffer = [
+            4, 0, 2, 0, 0, 0, 0, 0, // registers_size, ins_size, outs_size, tries_size
+            0, 0, 0, 0, 1, 0, 0, 0, // debug_info_off, insns_size
+            0x0e, 0x00, // return-void
+        ];
+        let code_item = CodeItem::try_parse_from_bytes_unsized(&buffer).unwrap();
+
+        // The arguments are passed in the last `ins_size` registers
+        let context = code_item.render_context(true);
+        let names: Vec<_> = (0..4).map(|register| context.register(register)).collect();
+        assert_eq!(names, ["v0", "v1", "p0", "p1"]);
+
+        let context = code_item.render_context(false);
+        assert_eq!(context.register(3), "v3");
+    }
+
+    #[test]
+    fn test_labels_are_numbered_by_address() {
+        #[rustfmt::skip]
+        let buffer = [
+            1, 0, 0, 0, 0, 0, 0, 0, // registers_size, ins_size, outs_size, tries_size
+            0, 0, 0, 0, 16, 0, 0, 0, // debug_info_off, insns_size
+            0x28, 0x07, // 0: goto +7
+            0x28, 0x03, // 1: goto +3
+            0x38, 0x00, 0x05, 0x00, // 2: if-eqz v0, +5
+            0x2b, 0x00, 0x04, 0x00, 0x00, 0x00, // 4: packed-switch v0, +4
+            0x0e, 0x00, // 7: return-void
+            0x00, 0x01, 0x02, 0x00, // 8: packed-switch-payload, 2 targets
+            0x00, 0x00, 0x00, 0x00, // first_key
+            0x03, 0x00, 0x00, 0x00, 0xfc, 0xff, 0xff, 0xff, // +3 and -4 from the switch
+        ];
+        let code_item = CodeItem::try_parse_from_bytes_unsized(&buffer).unwrap();
+        let context = code_item.render_context(false);
+
+        // The first goto jumps to the later of the two targets, so it gets the second label
+        assert_eq!(context.label("goto", 0, 7), ":goto_1");
+        assert_eq!(context.label("goto", 1, 3), ":goto_0");
+        assert_eq!(context.label("cond", 2, 5), ":cond_0");
+        assert_eq!(context.label("pswitch_data", 4, 4), ":pswitch_data_0");
+        assert_eq!(context.label("goto", 0, 99), "99");
+        assert_eq!(context.switch_address(8), Some(4));
+
+        let labels = |address| context.labels_at(address).collect::<Vec<_>>();
+        assert_eq!(labels(0), [":pswitch_0"]);
+        assert_eq!(labels(4), [":goto_0"]);
+        assert_eq!(labels(7), [":cond_0", ":goto_1", ":pswitch_1"]);
+        assert_eq!(labels(8), [":pswitch_data_0"]);
+        assert!(labels(1).is_empty());
     }
 }
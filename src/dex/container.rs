@@ -0,0 +1,123 @@
+use crate::{diagnostics::Diagnostics, errors::Error};
+
+use super::Dex;
+
+/// Iterates over the dex files of a container, in file order.
+///
+/// Files up to version 040 hold a single dex. From version 041 several headers may share one container: each header's `file_size` is the distance to the next header, and every other offset is relative to the start of the container.
+pub struct DexContainer<'a> {
+    raw: &'a [u8],
+    diagnostics: &'a Diagnostics,
+    /// offset of the next header to parse, or `None` once the container is exhausted
+    next_header_offset: Option<usize>,
+}
+
+impl<'a> DexContainer<'a> {
+    pub fn new(raw: &'a [u8], diagnostics: &'a Diagnostics) -> Self {
+        Self {
+            raw,
+            diagnostics,
+            next_header_offset: Some(0),
+        }
+    }
+}
+
+impl<'a> Iterator for DexContainer<'a> {
+    type Item = Result<Dex<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let header_offset = self.next_header_offset.take()?;
+
+        let dex = match Dex::try_parse_from_bytes_at(self.raw, header_offset, self.diagnostics) {
+            Ok(dex) => dex,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let header = &dex.header_item;
+        if header.is_container() {
+            let next = header_offset + header.file_size as usize;
+            let container_size = (header.container_size as usize).min(self.raw.len());
+            // A zero `file_size` would never advance, so treat it as the last header
+            if next > header_offset && next < container_size {
+                self.next_header_offset = Some(next);
+            }
+        }
+
+        Some(Ok(dex))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dex::fixture::{container, seal, DexBuilder},
+        errors::{ErrorKind, IntegrityError},
+    };
+
+    /// Two dex files sharing a container, along with the offset of the second header.
+    fn two_dexes() -> (Vec<u8>, usize) {
+        let mut first = DexBuilder::v41(0);
+        first.type_("LFirst;");
+        let second_base = first.build().len();
+        let mut second = DexBuilder::v41(second_base as u32);
+        second.type_("LSecond;");
+        (container(&[first, second]), second_base)
+    }
+
+    #[test]
+    fn test_two_dexes() {
+        let (file, second_base) = two_dexes();
+        let diagnostics = Diagnostics::new(true);
+        let dexes = DexContainer::new(&file, &diagnostics)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(dexes.len(), 2);
+        assert_eq!(dexes[0].header_item.header_offset, 0);
+        assert_eq!(dexes[1].header_item.header_offset as usize, second_base);
+        assert_eq!(dexes[0].types.get(0).unwrap(), "LFirst;");
+        assert_eq!(dexes[1].types.get(0).unwrap(), "LSecond;");
+        for dex in &dexes {
+            assert_eq!(dex.header_item.container_size as usize, file.len());
+            assert!(dex.verify_integrity().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_bogus_header_offset() {
+        let (mut file, second_base) = two_dexes();
+        file[second_base + 116..second_base + 120].copy_from_slice(&0x10u32.to_le_bytes());
+        seal(&mut file[second_base..]);
+
+        let diagnostics = Diagnostics::new(true);
+        let mut dexes = DexContainer::new(&file, &diagnostics);
+        assert!(dexes.next().unwrap().is_ok());
+        let Some(Err(e)) = dexes.next() else {
+            panic!("the second header should be rejected");
+        };
+        assert_eq!(e.structure, "header_item");
+        assert_eq!(e.offset, second_base + 0x74);
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+    }
+
+    #[test]
+    fn test_file_past_the_container() {
+        let mut file = DexBuilder::v41(0).build();
+        let file_size = file.len();
+        let container_size = file_size as u32 - 4;
+        file[112..116].copy_from_slice(&container_size.to_le_bytes());
+        seal(&mut file);
+
+        let diagnostics = Diagnostics::new(true);
+        let dexes = DexContainer::new(&file, &diagnostics)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(dexes.len(), 1);
+        assert!(matches!(
+            dexes[0].verify_integrity()[..],
+            [IntegrityError::Truncated { expected, actual }]
+                if expected == file_size && actual == file_size - 4
+        ));
+    }
+}
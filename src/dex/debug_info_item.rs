@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::{Error, TableIdxError},
+    utils::Reader,
+};
+
+use super::{code_item::RenderContext, Dex};
+
+/// terminates a debug info sequence for a `code_item`
+const DBG_END_SEQUENCE: u8 = 0x00;
+/// advances the address register without emitting a positions entry
+const DBG_ADVANCE_PC: u8 = 0x01;
+/// advances the line register without emitting a positions entry
+const DBG_ADVANCE_LINE: u8 = 0x02;
+/// introduces a local variable at the current address
+const DBG_START_LOCAL: u8 = 0x03;
+/// introduces a local with a type signature at the current address
+const DBG_START_LOCAL_EXTENDED: u8 = 0x04;
+/// marks a currently-live local variable as out of scope at the current address
+const DBG_END_LOCAL: u8 = 0x05;
+/// re-introduces a local variable at the current address
+const DBG_RESTART_LOCAL: u8 = 0x06;
+/// sets the `prologue_end` state machine register
+const DBG_SET_PROLOGUE_END: u8 = 0x07;
+/// sets the `epilogue_begin` state machine register
+const DBG_SET_EPILOGUE_BEGIN: u8 = 0x08;
+/// indicates that all subsequent line number entries make reference to this source file name
+const DBG_SET_FILE: u8 = 0x09;
+/// the first special opcode, which both advances the line and address registers and emits a position entry
+const DBG_FIRST_SPECIAL: u8 = 0x0A;
+/// the smallest line number increment a special opcode can encode
+const DBG_LINE_BASE: i64 = -4;
+/// the number of line increments a special opcode can encode
+const DBG_LINE_RANGE: u8 = 15;
+
+/// A local variable as described by the debug info.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Local {
+    /// register that will contain the local
+    pub register: u16,
+    /// index into the `string_ids` list for the name of the local, or `None` if unknown
+    pub name_idx: Option<u32>,
+    /// index into the `type_ids` list for the type of the local, or `None` if unknown
+    pub type_idx: Option<u32>,
+    /// index into the `string_ids` list for the generic type signature of the local, or `None` if there is none
+    pub signature_idx: Option<u32>,
+}
+
+impl Local {
+    fn unknown(register: u16) -> Self {
+        Self {
+            register,
+            name_idx: None,
+            type_idx: None,
+            signature_idx: None,
+        }
+    }
+
+    /// Renders the name and type of the local as `"name":Type`, or `None` if neither is known. The name is escaped like a string literal.
+    fn name_and_type_to_human_readable(&self, dex: &Dex) -> Result<Option<String>, TableIdxError> {
+        if self.name_idx.is_none() && self.type_idx.is_none() {
+            return Ok(None);
+        }
+
+        let name = match self.name_idx {
+            Some(idx) => dex.string_literal(idx as usize)?,
+            None => String::from("null"),
+        };
+        let local_type = match self.type_idx {
+            Some(idx) => dex
+                .types
+                .get(idx as usize)
+                .ok_or(TableIdxError::Type(idx as usize))?
+                .to_string(),
+            None => String::from("V"),
+        };
+        Ok(Some(format!("{name}:{local_type}")))
+    }
+}
+
+/// An entry emitted by the debug info state machine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugEventKind {
+    /// a positions table entry mapping the address to a source line
+    Line(u32),
+    /// the local becomes live
+    StartLocal(Local),
+    /// the local goes out of scope
+    EndLocal(Local),
+    /// a previously ended local becomes live again
+    RestartLocal(Local),
+    /// the address is the end of the method prologue, a good place for a breakpoint on method entry
+    PrologueEnd,
+    /// the address is the start of the method epilogue, a good place for a breakpoint before method exit
+    EpilogueBegin,
+    /// subsequent entries refer to the source file with this `string_ids` index, or to an unknown file if `None`
+    SetFile(Option<u32>),
+}
+
+impl DebugEventKind {
+    /// Renders the event as a smali directive, e.g. `.line 12` or `.local v0, "name":Ljava/lang/String;`. Registers are named by `context`.
+    pub fn to_human_readable(
+        &self,
+        dex: &Dex,
+        context: &RenderContext,
+    ) -> Result<String, TableIdxError> {
+        let repr = match self {
+            Self::Line(line) => format!(".line {line}"),
+            Self::StartLocal(local) => {
+                let mut out = format!(".local {}", context.register(local.register));
+                if let Some(name_and_type) = local.name_and_type_to_human_readable(dex)? {
+                    out.push_str(", ");
+                    out.push_str(&name_and_type);
+                }
+                if let Some(idx) = local.signature_idx {
+                    out.push_str(", ");
+                    out.push_str(&dex.string_literal(idx as usize)?);
+                }
+                out
+            }
+            Self::EndLocal(local) | Self::RestartLocal(local) => {
+                let directive = if let Self::EndLocal(_) = self {
+                    ".end local"
+                } else {
+                    ".restart local"
+                };
+                let mut out = format!("{directive} {}", context.register(local.register));
+                if let Some(name_and_type) = local.name_and_type_to_human_readable(dex)? {
+                    out.push_str(&format!("    # {name_and_type}"));
+                }
+                out
+            }
+            Self::PrologueEnd => String::from(".prologue"),
+            Self::EpilogueBegin => String::from(".epilogue"),
+            Self::SetFile(Some(idx)) => format!(".source {}", dex.string_literal(*idx as usize)?),
+            Self::SetFile(None) => String::from(".source \"\""),
+        };
+        Ok(repr)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugEvent {
+    /// address of the event, in 16-bit code units from the start of `insns`
+    pub address: u32,
+    pub kind: DebugEventKind,
+}
+
+/// The live range of a local variable, in 16-bit code units.
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalRange {
+    pub local: Local,
+    /// address at which the local becomes live
+    pub start_address: u32,
+    /// address at which the local goes out of scope, or `None` if it stays live until the end of the method
+    pub end_address: Option<u32>,
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#debug-info-item
+///
+/// The bytecode is interpreted when parsing, so this holds the entries emitted by the state machine in address order.
+#[allow(unused)]
+#[derive(Debug, Default)]
+pub struct DebugInfoItem {
+    /// the initial value for the state machine's `line` register. Does not represent an actual positions entry.
+    pub line_start: u32,
+    /// index into the `string_ids` list for the name of each incoming parameter, or `None` if the parameter is unnamed
+    pub parameter_names: Vec<Option<u32>>,
+    pub events: Vec<DebugEvent>,
+}
+
+impl DebugInfoItem {
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        Self::try_parse_from_reader(&mut Reader::new(buffer, "debug_info_item"))
+    }
+
+    /// Reads and interprets a `debug_info_item`, leaving `reader` just past its `DBG_END_SEQUENCE`.
+    pub fn try_parse_from_reader(reader: &mut Reader) -> Result<Self, Error> {
+        let line_start = reader.uleb128("line_start")? as u32;
+        let parameters_size = reader.uleb128("parameters_size")?;
+        let mut parameter_names = Vec::new();
+        for _ in 0..parameters_size {
+            parameter_names.push(reader.uleb128p1("parameter_names")?);
+        }
+
+        let mut address: u32 = 0;
+        let mut line = line_start;
+        let mut live_locals: HashMap<u16, Local> = HashMap::new();
+        let mut last_locals: HashMap<u16, Local> = HashMap::new();
+        let mut events = Vec::new();
+
+        loop {
+            let opcode = reader.u8()?;
+            let kind = match opcode {
+                DBG_END_SEQUENCE => break,
+                DBG_ADVANCE_PC => {
+                    address = address.wrapping_add(reader.uleb128("addr_diff")? as u32);
+                    continue;
+                }
+                DBG_ADVANCE_LINE => {
+                    line = line.wrapping_add_signed(reader.sleb128("line_diff")? as i32);
+                    continue;
+                }
+                DBG_START_LOCAL | DBG_START_LOCAL_EXTENDED => {
+                    let register = reader.uleb128("register_num")? as u16;
+                    let name_idx = reader.uleb128p1("name_idx")?;
+                    let type_idx = reader.uleb128p1("type_idx")?;
+                    let signature_idx = if opcode == DBG_START_LOCAL_EXTENDED {
+                        reader.uleb128p1("sig_idx")?
+                    } else {
+                        None
+                    };
+                    let local = Local {
+                        register,
+                        name_idx,
+                        type_idx,
+                        signature_idx,
+                    };
+                    live_locals.insert(register, local.clone());
+                    last_locals.insert(register, local.clone());
+                    DebugEventKind::StartLocal(local)
+                }
+                DBG_END_LOCAL => {
+                    let register = reader.uleb128("register_num")? as u16;
+                    let local = live_locals
+                        .remove(&register)
+                        .or_else(|| last_locals.get(&register).cloned())
+                        .unwrap_or_else(|| Local::unknown(register));
+                    DebugEventKind::EndLocal(local)
+                }
+                DBG_RESTART_LOCAL => {
+                    let register = reader.uleb128("register_num")? as u16;
+                    let local = last_locals
+                        .get(&register)
+                        .cloned()
+                        .unwrap_or_else(|| Local::unknown(register));
+                    live_locals.insert(register, local.clone());
+                    DebugEventKind::RestartLocal(local)
+                }
+                DBG_SET_PROLOGUE_END => DebugEventKind::PrologueEnd,
+                DBG_SET_EPILOGUE_BEGIN => DebugEventKind::EpilogueBegin,
+                DBG_SET_FILE => DebugEventKind::SetFile(reader.uleb128p1("name_idx")?),
+                special => {
+                    let adjusted_opcode = special - DBG_FIRST_SPECIAL;
+                    line = line.wrapping_add_signed(
+                        (DBG_LINE_BASE + (adjusted_opcode % DBG_LINE_RANGE) as i64) as i32,
+                    );
+                    address = address.wrapping_add((adjusted_opcode / DBG_LINE_RANGE) as u32);
+                    DebugEventKind::Line(line)
+                }
+            };
+            events.push(DebugEvent { address, kind });
+        }
+
+        Ok(Self {
+            line_start,
+            parameter_names,
+            events,
+        })
+    }
+
+    /// Returns the positions table as `(address, line)` pairs in address order.
+    #[allow(unused)]
+    pub fn lines(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.events.iter().filter_map(|event| match event.kind {
+            DebugEventKind::Line(line) => Some((event.address, line)),
+            _ => None,
+        })
+    }
+
+    /// Returns the live range of every local variable, in the order in which they become live.
+    #[allow(unused)]
+    pub fn local_ranges(&self) -> Vec<LocalRange> {
+        let mut ranges: Vec<LocalRange> = Vec::new();
+        let mut open: HashMap<u16, usize> = HashMap::new();
+
+        for event in &self.events {
+            match &event.kind {
+                DebugEventKind::StartLocal(local) | DebugEventKind::RestartLocal(local) => {
+                    if let Some(idx) = open.remove(&local.register) {
+                        ranges[idx].end_address = Some(event.address);
+                    }
+                    open.insert(local.register, ranges.len());
+                    ranges.push(LocalRange {
+                        local: local.clone(),
+                        start_address: event.address,
+                        end_address: None,
+                    });
+                }
+                DebugEventKind::EndLocal(local) => {
+                    if let Some(idx) = open.remove(&local.register) {
+                        ranges[idx].end_address = Some(event.address);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dex::fixture::DexBuilder, diagnostics::Diagnostics, errors::ErrorKind};
+
+    /// Builds a dex holding the strings and types the debug info below refers to, returning it with the `uleb128p1` encoding of each index.
+    fn fixture() -> (Vec<u8>, [u8; 4]) {
+        let mut builder = DexBuilder::new();
+        let name = builder.string("count");
+        let local_type = builder.type_("I");
+        let source_file = builder.string("Foo.java");
+        let quoted = builder.string("say \"hi\"");
+        let file = builder.build();
+        let p1 = |idx: u32| idx as u8 + 1;
+        (
+            file,
+            [p1(name), p1(local_type), p1(source_file), p1(quoted)],
+        )
+    }
+
+    fn parse(buffer: &[u8]) -> DebugInfoItem {
+        DebugInfoItem::try_parse_from_bytes_unsized(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_header() {
+        // line_start 10, an unnamed parameter and one named by string 5
+        let item = parse(&[0x0A, 0x02, 0x00, 0x06, DBG_END_SEQUENCE]);
+        assert_eq!(item.line_start, 10);
+        assert_eq!(item.parameter_names, [None, Some(5)]);
+        assert!(item.events.is_empty());
+    }
+
+    #[test]
+    fn test_special_opcodes() {
+        #[rustfmt::skip]
+        let item = parse(&[
+            0x0A, 0x00, // line_start 10, no parameters
+            0x0F, // line + 1, address + 0
+            0x2C, // line + 0, address + 2
+            0x0A, // line - 4, address + 0
+            0xFF, // line + 1, address + 16
+            DBG_END_SEQUENCE,
+        ]);
+        assert_eq!(
+            item.lines().collect::<Vec<_>>(),
+            [(0, 11), (2, 11), (2, 7), (18, 8)]
+        );
+    }
+
+    #[test]
+    fn test_advance_pc_and_line() {
+        #[rustfmt::skip]
+        let item = parse(&[
+            0x0A, 0x00, // line_start 10, no parameters
+            DBG_ADVANCE_PC, 0x80, 0x01, // address + 128
+            DBG_ADVANCE_LINE, 0x7E, // line - 2
+            DBG_SET_PROLOGUE_END,
+            0x0E, // line + 0, address + 0
+            DBG_ADVANCE_LINE, 0x05, // line + 5
+            DBG_SET_EPILOGUE_BEGIN,
+            0x0E,
+            DBG_END_SEQUENCE,
+        ]);
+        let events: Vec<_> = item
+            .events
+            .iter()
+            .map(|event| (event.address, event.kind.clone()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (128, DebugEventKind::PrologueEnd),
+                (128, DebugEventKind::Line(8)),
+                (128, DebugEventKind::EpilogueBegin),
+                (128, DebugEventKind::Line(13)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locals() {
+        let (file, [name, local_type, _, signature]) = fixture();
+        let diagnostics = Diagnostics::new(true);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        #[rustfmt::skip]
+        let item = parse(&[
+            0x01, 0x00, // line_start 1, no parameters
+            DBG_START_LOCAL_EXTENDED, 0x02, name, local_type, signature, // v2 at 0
+            DBG_ADVANCE_PC, 0x03,
+            DBG_END_LOCAL, 0x02, // at 3
+            DBG_ADVANCE_PC, 0x02,
+            DBG_RESTART_LOCAL, 0x02, // at 5, restoring name and type
+            DBG_RESTART_LOCAL, 0x07, // a register never started
+            DBG_ADVANCE_PC, 0x01,
+            DBG_START_LOCAL, 0x02, 0x00, 0x00, // at 6, replacing v2 with an unnamed local
+            DBG_END_SEQUENCE,
+        ]);
+
+        let context = RenderContext::default();
+        let rendered: Vec<_> = item
+            .events
+            .iter()
+            .map(|event| event.kind.to_human_readable(&dex, &context).unwrap())
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                ".local v2, \"count\":I, \"say \\\"hi\\\"\"",
+                ".end local v2    # \"count\":I",
+                ".restart local v2    # \"count\":I",
+                ".restart local v7",
+                ".local v2",
+            ]
+        );
+
+        let ranges: Vec<_> = item
+            .local_ranges()
+            .iter()
+            .map(|range| (range.local.register, range.start_address, range.end_address))
+            .collect();
+        assert_eq!(
+            ranges,
+            [(2, 0, Some(3)), (2, 5, Some(6)), (7, 5, None), (2, 6, None)]
+        );
+    }
+
+    #[test]
+    fn test_set_file() {
+        let (file, [_, _, source_file, quoted]) = fixture();
+        let diagnostics = Diagnostics::new(true);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        #[rustfmt::skip]
+        let item = parse(&[
+            0x01, 0x00,
+            DBG_SET_FILE, source_file,
+            DBG_SET_FILE, 0x00, // unknown file
+            DBG_SET_FILE, quoted,
+            DBG_END_SEQUENCE,
+        ]);
+        let context = RenderContext::default();
+        let rendered: Vec<_> = item
+            .events
+            .iter()
+            .map(|event| event.kind.to_human_readable(&dex, &context).unwrap())
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                ".source \"Foo.java\"",
+                ".source \"\"",
+                ".source \"say \\\"hi\\\"\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_debug_info() {
+        // the sequence is never terminated
+        let e = DebugInfoItem::try_parse_from_bytes_unsized(&[0x01, 0x00, 0x0E]).unwrap_err();
+        assert_eq!(e.structure, "debug_info_item");
+        assert_eq!(e.offset, 3);
+        assert!(matches!(e.kind, ErrorKind::UnexpectedEof { .. }));
+
+        // the register of DBG_START_LOCAL is cut short
+        let e = DebugInfoItem::try_parse_from_bytes_unsized(&[0x01, 0x00, DBG_START_LOCAL, 0x80])
+            .unwrap_err();
+        assert_eq!(e.offset, 3);
+        assert!(matches!(e.kind, ErrorKind::Leb128("register_num")));
+    }
+}
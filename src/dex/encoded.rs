@@ -1,4 +1,21 @@
-use crate::utils::decode_uleb128;
+use crate::{
+    errors::{Error, ErrorKind},
+    utils::Reader,
+};
+
+use super::access_flags::AccessFlags;
+
+/// Converts the `access_flags` of an `encoded_field` or `encoded_method` read at `offset`, which the spec limits to 32 bits.
+fn access_flags(structure: &'static str, offset: usize, value: u64) -> Result<AccessFlags, Error> {
+    let value = u32::try_from(value).map_err(|_| {
+        Error::new(
+            structure,
+            offset,
+            ErrorKind::Malformed(format!("access_flags {value:#x} does not fit in 32 bits")),
+        )
+    })?;
+    Ok(AccessFlags::from(value))
+}
 
 /// https://source.android.com/docs/core/runtime/dex-format#encoded-field-format
 #[allow(unused)]
@@ -7,31 +24,27 @@ pub struct EncodedField {
     /// index into the `field_ids` list for the identity of this field (includes the name and descriptor).
     pub field_idx: u64,
     /// access flags for the field (`public`, `final`, etc.). See "`access_flags` Definitions" for details.
-    pub access_flags: u64,
+    pub access_flags: AccessFlags,
 }
 
 impl EncodedField {
-    pub fn try_parse_from_bytes_with_offset(
-        buffer: &[u8],
-        prev: u64,
-        offset: &mut usize,
-    ) -> std::io::Result<Self> {
-        let (field_idx_diff, bytes_used) =
-            decode_uleb128(&buffer[*offset..]).ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to decode ULEB128 for field index difference",
-            ))?;
-        *offset += bytes_used;
-
-        let (access_flags, bytes_used) =
-            decode_uleb128(&buffer[*offset..]).ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to decode ULEB128 for access flags",
-            ))?;
-        *offset += bytes_used;
+    /// Reads the next field of a `class_data_item`. `prev` is the index of the previous field in the list, as `field_idx` is encoded as a difference.
+    pub fn try_parse_from_reader(reader: &mut Reader, prev: u64) -> Result<Self, Error> {
+        let offset = reader.offset;
+        let field_idx_diff = reader.uleb128("field_idx_diff")?;
+        let access_flags = access_flags("encoded_field", offset, reader.uleb128("access_flags")?)?;
+        let field_idx = prev.checked_add(field_idx_diff).ok_or_else(|| {
+            Error::new(
+                "encoded_field",
+                offset,
+                ErrorKind::Malformed(format!(
+                    "field_idx_diff {field_idx_diff} overflows the previous index {prev}"
+                )),
+            )
+        })?;
 
         Ok(EncodedField {
-            field_idx: prev + field_idx_diff,
+            field_idx,
             access_flags,
         })
     }
@@ -44,42 +57,119 @@ pub struct EncodedMethod {
     /// index into the `method_ids` list for the identity of this method (includes the name and descriptor).
     pub method_idx: u64,
     /// access flags for the method (`public`, `final`, etc.). See "`access_flags` Definitions" for details.
-    pub access_flags: u64,
+    pub access_flags: AccessFlags,
     /// offset from the start of the file to the code structure for this method, or `0` if this method is either `abstract` or `native`. The offset should be to a location in the data section. The format of the data is specified by "`code_item`" below.
-    pub code_off: u64,
+    pub code_off: u32,
 }
 
 impl EncodedMethod {
-    pub fn try_parse_from_bytes_with_offset(
-        buffer: &[u8],
-        prev: u64,
-        offset: &mut usize,
-    ) -> std::io::Result<Self> {
-        let (method_idx_diff, bytes_used) =
-            decode_uleb128(&buffer[*offset..]).ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to decode ULEB128 for method index difference",
-            ))?;
-        *offset += bytes_used;
-
-        let (access_flags, bytes_used) =
-            decode_uleb128(&buffer[*offset..]).ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to decode ULEB128 for access flags",
-            ))?;
-        *offset += bytes_used;
-
-        let (code_off, bytes_used) =
-            decode_uleb128(&buffer[*offset..]).ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to decode ULEB128 for code offset",
-            ))?;
-        *offset += bytes_used;
+    /// Reads the next method of a `class_data_item`. `prev` is the index of the previous method in the list, as `method_idx` is encoded as a difference.
+    pub fn try_parse_from_reader(reader: &mut Reader, prev: u64) -> Result<Self, Error> {
+        let offset = reader.offset;
+        let method_idx_diff = reader.uleb128("method_idx_diff")?;
+        let access_flags = access_flags("encoded_method", offset, reader.uleb128("access_flags")?)?;
+        let code_off = reader.uleb128("code_off")?;
+        let method_idx = prev.checked_add(method_idx_diff).ok_or_else(|| {
+            Error::new(
+                "encoded_method",
+                offset,
+                ErrorKind::Malformed(format!(
+                    "method_idx_diff {method_idx_diff} overflows the previous index {prev}"
+                )),
+            )
+        })?;
+        let code_off = u32::try_from(code_off).map_err(|_| {
+            Error::new(
+                "encoded_method",
+                offset,
+                ErrorKind::Malformed(format!("code_off {code_off:#x} does not fit in 32 bits")),
+            )
+        })?;
 
         Ok(EncodedMethod {
-            method_idx: prev + method_idx_diff,
+            method_idx,
             access_flags,
             code_off,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ULEB128 encoding of `u64::MAX`
+    const MAX_ULEB128: [u8; 10] = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+
+    #[test]
+    fn test_indices_are_differences() {
+        let buffer = [0x02, 0x01, 0x03, 0x08];
+        let mut reader = Reader::new(&buffer, "class_data_item");
+        let field = EncodedField::try_parse_from_reader(&mut reader, 5).unwrap();
+        assert_eq!(field.field_idx, 7);
+        let e = EncodedMethod::try_parse_from_reader(&mut reader, 0).unwrap_err();
+        // the method is cut short after its access flags
+        assert!(matches!(e.kind, ErrorKind::Leb128("code_off")));
+    }
+
+    #[test]
+    fn test_overflowing_field_idx_diff() {
+        let mut buffer = vec![0x00];
+        buffer.extend(MAX_ULEB128);
+        buffer.push(0x01);
+        let mut reader = Reader::new(&buffer, "class_data_item");
+        reader.u8().unwrap();
+
+        let e = EncodedField::try_parse_from_reader(&mut reader, 1).unwrap_err();
+        assert_eq!(e.structure, "encoded_field");
+        assert_eq!(e.offset, 1);
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+    }
+
+    #[test]
+    fn test_overflowing_method_idx_diff() {
+        let mut buffer = MAX_ULEB128.to_vec();
+        buffer.extend([0x01, 0x10]);
+        let mut reader = Reader::new(&buffer, "class_data_item");
+
+        let e = EncodedMethod::try_parse_from_reader(&mut reader, 1).unwrap_err();
+        assert_eq!(e.structure, "encoded_method");
+        assert_eq!(e.offset, 0);
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+
+        // The largest index is still reachable
+        let mut reader = Reader::new(&buffer, "class_data_item");
+        let method = EncodedMethod::try_parse_from_reader(&mut reader, 0).unwrap();
+        assert_eq!(method.method_idx, u64::MAX);
+        assert_eq!(method.code_off, 0x10);
+    }
+
+    #[test]
+    fn test_code_off_past_32_bits() {
+        // method_idx_diff 0, access_flags 1, code_off 0x100000000
+        let buffer = [0x00, 0x01, 0x80, 0x80, 0x80, 0x80, 0x10];
+        let mut reader = Reader::new(&buffer, "class_data_item");
+
+        let e = EncodedMethod::try_parse_from_reader(&mut reader, 0).unwrap_err();
+        assert_eq!(e.structure, "encoded_method");
+        assert_eq!(e.offset, 0);
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+    }
+
+    #[test]
+    fn test_access_flags_past_32_bits() {
+        // field_idx_diff 0, access_flags 0x100000001
+        let buffer = [0x00, 0x81, 0x80, 0x80, 0x80, 0x10];
+        let mut reader = Reader::new(&buffer, "class_data_item");
+        let e = EncodedField::try_parse_from_reader(&mut reader, 0).unwrap_err();
+        assert_eq!((e.structure, e.offset), ("encoded_field", 0));
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+
+        // method_idx_diff 0, access_flags 0x100000001, code_off 0
+        let buffer = [0x00, 0x81, 0x80, 0x80, 0x80, 0x10, 0x00];
+        let mut reader = Reader::new(&buffer, "class_data_item");
+        let e = EncodedMethod::try_parse_from_reader(&mut reader, 0).unwrap_err();
+        assert_eq!((e.structure, e.offset), ("encoded_method", 0));
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+    }
+}
@@ -0,0 +1,461 @@
+use crate::{
+    errors::{Error, ErrorKind, TableIdxError},
+    utils::{hex_literal, Reader},
+};
+
+use super::Dex;
+
+/// https://source.android.com/docs/core/runtime/dex-format#encoding
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncodedValue {
+    /// signed one-byte integer value
+    Byte(i8),
+    /// signed two-byte integer value
+    Short(i16),
+    /// unsigned two-byte integer value
+    Char(u16),
+    /// signed four-byte integer value
+    Int(i32),
+    /// signed eight-byte integer value
+    Long(i64),
+    /// four-byte IEEE754 32-bit floating point value
+    Float(f32),
+    /// eight-byte IEEE754 64-bit floating point value
+    Double(f64),
+    /// index into the `proto_ids` list
+    MethodType(u32),
+    /// index into the `method_handles` list
+    MethodHandle(u32),
+    /// index into the `string_ids` list
+    String(u32),
+    /// index into the `type_ids` list
+    Type(u32),
+    /// index into the `field_ids` list
+    Field(u32),
+    /// index into the `method_ids` list
+    Method(u32),
+    /// index into the `field_ids` list for an enumerated type constant
+    Enum(u32),
+    /// an array of values
+    Array(EncodedArray),
+    /// a sub-annotation
+    Annotation(EncodedAnnotation),
+    /// `null` reference value
+    Null,
+    /// one-bit value
+    Boolean(bool),
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#encoded-array
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EncodedArray {
+    /// a series of `size` encoded_value byte sequences
+    pub values: Vec<EncodedValue>,
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#encoded-annotation
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedAnnotation {
+    /// type of the annotation. This must be a class (not array or primitive) type.
+    pub type_idx: u64,
+    /// elements of the annotation, represented directly in-line (not as offsets). Elements must be sorted in increasing order by `string_id` index.
+    pub elements: Vec<AnnotationElement>,
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#annotation-element
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationElement {
+    /// element name, represented as an index into the `string_ids` section. The string must conform to the syntax for MemberName.
+    pub name_idx: u64,
+    /// element value
+    pub value: EncodedValue,
+}
+
+/// How deeply arrays and sub-annotations may be nested inside each other. Real files stay far below this; crafted ones could otherwise exhaust the stack.
+const MAX_DEPTH: usize = 256;
+
+/// Reads a little-endian integer of `bytes.len()` bytes, sign-extending it to 64 bits.
+fn sign_extend(bytes: &[u8]) -> i64 {
+    let shift = 64 - bytes.len() * 8;
+    ((zero_extend(bytes) << shift) as i64) >> shift
+}
+
+/// Reads a little-endian integer of `bytes.len()` bytes, zero-extending it to 64 bits.
+fn zero_extend(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0u64, |acc, byte| acc << 8 | *byte as u64)
+}
+
+/// Reads the high-order `bytes.len()` bytes of a `width`-byte little-endian value, zero-extending it to the right.
+fn zero_extend_right(bytes: &[u8], width: usize) -> u64 {
+    zero_extend(bytes) << (width.saturating_sub(bytes.len()) * 8)
+}
+
+impl EncodedValue {
+    #[allow(unused)]
+    pub fn try_parse_from_reader(reader: &mut Reader) -> Result<Self, Error> {
+        Self::try_parse_nested(reader, 0)
+    }
+
+    /// Parses a value found inside `depth` enclosing arrays or sub-annotations.
+    fn try_parse_nested(reader: &mut Reader, depth: usize) -> Result<Self, Error> {
+        if depth > MAX_DEPTH {
+            return Err(reader.error(ErrorKind::Malformed(format!(
+                "encoded_value is nested more than {MAX_DEPTH} levels deep"
+            ))));
+        }
+        let header = reader.u8()?;
+
+        let value_arg = (header >> 5) as usize;
+        let value_type = header & 0x1F;
+
+        let max_value_arg = match value_type {
+            0x00 => 0,
+            0x02 | 0x03 => 1,
+            0x04 | 0x10 | 0x15..=0x1B => 3,
+            0x06 | 0x11 => 7,
+            0x1C..=0x1F => 1,
+            _ => {
+                return Err(reader.error(ErrorKind::Malformed(format!(
+                    "Unknown encoded_value type: {value_type:#04x}"
+                ))))
+            }
+        };
+        if value_arg > max_value_arg || (matches!(value_type, 0x1C..=0x1E) && value_arg != 0) {
+            return Err(reader.error(ErrorKind::Malformed(format!(
+                "Invalid value_arg {value_arg} for encoded_value type {value_type:#04x}"
+            ))));
+        }
+
+        let value = match value_type {
+            0x1C => Self::Array(EncodedArray::try_parse_nested(reader, depth + 1)?),
+            0x1D => Self::Annotation(EncodedAnnotation::try_parse_nested(reader, depth + 1)?),
+            0x1E => Self::Null,
+            0x1F => Self::Boolean(value_arg == 1),
+            _ => {
+                let bytes = reader.bytes(value_arg + 1)?;
+
+                match value_type {
+                    0x00 => Self::Byte(bytes[0] as i8),
+                    0x02 => Self::Short(sign_extend(bytes) as i16),
+                    0x03 => Self::Char(zero_extend(bytes) as u16),
+                    0x04 => Self::Int(sign_extend(bytes) as i32),
+                    0x06 => Self::Long(sign_extend(bytes)),
+                    0x10 => Self::Float(f32::from_bits(zero_extend_right(bytes, 4) as u32)),
+                    0x11 => Self::Double(f64::from_bits(zero_extend_right(bytes, 8))),
+                    0x15 => Self::MethodType(zero_extend(bytes) as u32),
+                    0x16 => Self::MethodHandle(zero_extend(bytes) as u32),
+                    0x17 => Self::String(zero_extend(bytes) as u32),
+                    0x18 => Self::Type(zero_extend(bytes) as u32),
+                    0x19 => Self::Field(zero_extend(bytes) as u32),
+                    0x1A => Self::Method(zero_extend(bytes) as u32),
+                    0x1B => Self::Enum(zero_extend(bytes) as u32),
+                    _ => unreachable!("value_type was validated above"),
+                }
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Renders the value as a smali literal, resolving indices through `dex`.
+    pub fn to_human_readable(&self, dex: &Dex) -> Result<String, TableIdxError> {
+        let repr = match self {
+            Self::Byte(value) => hex_literal((*value).into(), "t"),
+            Self::Short(value) => hex_literal((*value).into(), "s"),
+            Self::Char(value) => match char::from_u32(*value as u32) {
+                Some(c) if !c.is_control() && c != '\'' && c != '\\' => format!("'{c}'"),
+                _ => format!("'\\u{value:04x}'"),
+            },
+            Self::Int(value) => hex_literal((*value).into(), ""),
+            Self::Long(value) => hex_literal(*value, "L"),
+            // smali spells infinities out, while NaN is written the way Rust prints it
+            Self::Float(value) => match *value {
+                f32::INFINITY => String::from("Infinityf"),
+                f32::NEG_INFINITY => String::from("-Infinityf"),
+                value => format!("{value:?}f"),
+            },
+            Self::Double(value) => match *value {
+                f64::INFINITY => String::from("Infinity"),
+                f64::NEG_INFINITY => String::from("-Infinity"),
+                value => format!("{value:?}"),
+            },
+            Self::MethodType(proto_idx) => dex.proto_descriptor(*proto_idx as usize)?,
+            Self::MethodHandle(method_handle_idx) => dex
+                .method_handles
+                .get(*method_handle_idx as usize)
+                .ok_or(TableIdxError::MethodHandle(*method_handle_idx as usize))?
+                .to_human_readable(dex)?,
+            Self::String(string_idx) => dex.string_literal(*string_idx as usize)?,
+            Self::Type(type_idx) => dex
+                .types
+                .get(*type_idx as usize)
+                .ok_or(TableIdxError::Type(*type_idx as usize))?
+                .to_string(),
+            Self::Field(field_idx) => dex
+                .field_ids
+                .get(*field_idx as usize)
+                .ok_or(TableIdxError::FieldId(*field_idx as usize))?
+                .to_human_readable(dex)?,
+            Self::Method(method_idx) => dex
+                .method_ids
+                .get(*method_idx as usize)
+                .ok_or(TableIdxError::MethodId(*method_idx as usize))?
+                .to_human_readable(dex)?,
+            Self::Enum(field_idx) => {
+                let field = dex
+                    .field_ids
+                    .get(*field_idx as usize)
+                    .ok_or(TableIdxError::FieldId(*field_idx as usize))?;
+                format!(".enum {}", field.to_human_readable(dex)?)
+            }
+            Self::Array(array) => array.to_human_readable(dex)?,
+            Self::Annotation(annotation) => annotation.to_human_readable(dex)?,
+            Self::Null => String::from("null"),
+            Self::Boolean(value) => format!("{value}"),
+        };
+        Ok(repr)
+    }
+}
+
+impl EncodedArray {
+    #[allow(unused)]
+    pub fn try_parse_from_reader(reader: &mut Reader) -> Result<Self, Error> {
+        Self::try_parse_nested(reader, 0)
+    }
+
+    fn try_parse_nested(reader: &mut Reader, depth: usize) -> Result<Self, Error> {
+        let size = reader.uleb128("encoded_array size")?;
+
+        let mut values = Vec::new();
+        for _ in 0..size {
+            values.push(EncodedValue::try_parse_nested(reader, depth)?);
+        }
+
+        Ok(Self { values })
+    }
+
+    /// Parses an `encoded_array_item`, which is an `encoded_array` on its own in the data section.
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        Self::try_parse_nested(&mut Reader::new(buffer, "encoded_array_item"), 0)
+    }
+
+    /// Renders the array as a smali array literal, e.g. `{ 1, 2, 3 }`.
+    pub fn to_human_readable(&self, dex: &Dex) -> Result<String, TableIdxError> {
+        if self.values.is_empty() {
+            return Ok(String::from("{}"));
+        }
+
+        let values = self
+            .values
+            .iter()
+            .map(|value| value.to_human_readable(dex))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("{{ {} }}", values.join(", ")))
+    }
+}
+
+impl EncodedAnnotation {
+    pub fn try_parse_from_reader(reader: &mut Reader) -> Result<Self, Error> {
+        Self::try_parse_nested(reader, 0)
+    }
+
+    fn try_parse_nested(reader: &mut Reader, depth: usize) -> Result<Self, Error> {
+        let type_idx = reader.uleb128("annotation type index")?;
+        let size = reader.uleb128("annotation size")?;
+
+        let mut elements = Vec::new();
+        for _ in 0..size {
+            let name_idx = reader.uleb128("annotation element name")?;
+            let value = EncodedValue::try_parse_nested(reader, depth)?;
+            elements.push(AnnotationElement { name_idx, value });
+        }
+
+        Ok(Self { type_idx, elements })
+    }
+
+    /// Renders the elements as `name = value`. A sub-annotation value spans several lines, with the ones after the first indented relative to the element.
+    pub fn elements_to_human_readable(&self, dex: &Dex) -> Result<Vec<String>, TableIdxError> {
+        self.elements
+            .iter()
+            .map(|element| {
+                let name = dex
+                    .strings
+                    .get(element.name_idx as usize)
+                    .ok_or(TableIdxError::String(element.name_idx as usize))?;
+                Ok(format!(
+                    "{name} = {}",
+                    element.value.to_human_readable(dex)?
+                ))
+            })
+            .collect()
+    }
+
+    /// Renders the annotation as a smali `.subannotation` block, one element per line.
+    pub fn to_human_readable(&self, dex: &Dex) -> Result<String, TableIdxError> {
+        let annotation_type = dex
+            .types
+            .get(self.type_idx as usize)
+            .ok_or(TableIdxError::Type(self.type_idx as usize))?;
+
+        let mut lines = vec![format!(".subannotation {annotation_type}")];
+        for element in self.elements_to_human_readable(dex)? {
+            lines.push(format!("    {}", element.replace('\n', "\n    ")));
+        }
+        lines.push(String::from(".end subannotation"));
+        Ok(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dex::fixture::DexBuilder, diagnostics::Diagnostics};
+
+    fn parse(buffer: &[u8]) -> EncodedValue {
+        let mut reader = Reader::new(buffer, "encoded_value");
+        let value = EncodedValue::try_parse_from_reader(&mut reader).unwrap();
+        assert_eq!(reader.offset, buffer.len());
+        value
+    }
+
+    #[test]
+    fn test_integers_are_sign_extended() {
+        assert_eq!(parse(&[0x00, 0xFF]), EncodedValue::Byte(-1));
+        assert_eq!(parse(&[0x02, 0x80]), EncodedValue::Short(-128));
+        assert_eq!(parse(&[0x24, 0x00, 0x80]), EncodedValue::Int(-32768));
+        assert_eq!(parse(&[0x04, 0x7F]), EncodedValue::Int(127));
+        assert_eq!(parse(&[0x26, 0xFE, 0xFF]), EncodedValue::Long(-2));
+    }
+
+    #[test]
+    fn test_chars_and_indices_are_zero_extended() {
+        assert_eq!(parse(&[0x03, 0xFF]), EncodedValue::Char(0xFF));
+        assert_eq!(parse(&[0x37, 0x34, 0x82]), EncodedValue::String(0x8234));
+        assert_eq!(parse(&[0x18, 0x80]), EncodedValue::Type(0x80));
+    }
+
+    #[test]
+    fn test_floats_are_zero_extended_to_the_right() {
+        assert_eq!(parse(&[0x30, 0x80, 0x3F]), EncodedValue::Float(1.0));
+        assert_eq!(parse(&[0x31, 0xF0, 0x3F]), EncodedValue::Double(1.0));
+    }
+
+    #[test]
+    fn test_null_boolean_and_array() {
+        assert_eq!(parse(&[0x1E]), EncodedValue::Null);
+        assert_eq!(parse(&[0x3F]), EncodedValue::Boolean(true));
+        assert_eq!(
+            parse(&[0x1C, 0x02, 0x1F, 0x04, 0x05]),
+            EncodedValue::Array(EncodedArray {
+                values: vec![EncodedValue::Boolean(false), EncodedValue::Int(5)]
+            })
+        );
+    }
+
+    #[test]
+    fn test_annotation() {
+        assert_eq!(
+            parse(&[0x1D, 0x03, 0x01, 0x07, 0x1E]),
+            EncodedValue::Annotation(EncodedAnnotation {
+                type_idx: 3,
+                elements: vec![AnnotationElement {
+                    name_idx: 7,
+                    value: EncodedValue::Null
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        // An encoded_array_item holding `depth` arrays nested inside each other around a null
+        let nested = |depth: usize| {
+            let mut buffer = vec![0x01];
+            buffer.extend([0x1C, 0x01].repeat(depth));
+            buffer.push(0x1E);
+            buffer
+        };
+        assert!(EncodedArray::try_parse_from_bytes_unsized(&nested(MAX_DEPTH)).is_ok());
+
+        let e = EncodedArray::try_parse_from_bytes_unsized(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!(e.structure, "encoded_array_item");
+        assert_eq!(e.offset, 1 + 2 * (MAX_DEPTH + 1));
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+    }
+
+    #[test]
+    fn test_literals_are_hex() {
+        let builder = DexBuilder::new();
+        let file = builder.build();
+        let diagnostics = Diagnostics::new(false);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        let render = |value: EncodedValue| value.to_human_readable(&dex).unwrap();
+        assert_eq!(render(EncodedValue::Byte(-1)), "-0x1t");
+        assert_eq!(render(EncodedValue::Short(0x100)), "0x100s");
+        assert_eq!(render(EncodedValue::Int(i32::MIN)), "-0x80000000");
+        assert_eq!(render(EncodedValue::Long(42)), "0x2aL");
+    }
+
+    #[test]
+    fn test_special_floats() {
+        let file = DexBuilder::new().build();
+        let diagnostics = Diagnostics::new(false);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        let render = |value: EncodedValue| value.to_human_readable(&dex).unwrap();
+        assert_eq!(render(EncodedValue::Float(-0.5)), "-0.5f");
+        assert_eq!(render(EncodedValue::Float(f32::NAN)), "NaNf");
+        assert_eq!(render(EncodedValue::Float(f32::INFINITY)), "Infinityf");
+        assert_eq!(render(EncodedValue::Float(f32::NEG_INFINITY)), "-Infinityf");
+        assert_eq!(render(EncodedValue::Double(1.0)), "1.0");
+        assert_eq!(render(EncodedValue::Double(f64::NAN)), "NaN");
+        assert_eq!(render(EncodedValue::Double(f64::INFINITY)), "Infinity");
+        assert_eq!(render(EncodedValue::Double(f64::NEG_INFINITY)), "-Infinity");
+        // the payload and sign of a NaN are not printed
+        let nan = EncodedValue::Double(-f64::from_bits(0x7FF0_0000_0000_0001));
+        assert_eq!(render(nan), "NaN");
+    }
+
+    #[test]
+    fn test_subannotation_block() {
+        let mut builder = DexBuilder::new();
+        let outer = builder.type_("LOuter;");
+        let inner = builder.type_("LInner;");
+        let name = builder.string("value");
+        let file = builder.build();
+        let diagnostics = Diagnostics::new(false);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        let element = |value| AnnotationElement {
+            name_idx: name.into(),
+            value,
+        };
+        let annotation = EncodedAnnotation {
+            type_idx: outer.into(),
+            elements: vec![element(EncodedValue::Annotation(EncodedAnnotation {
+                type_idx: inner.into(),
+                elements: vec![element(EncodedValue::Int(16))],
+            }))],
+        };
+        assert_eq!(
+            annotation.to_human_readable(&dex).unwrap(),
+            concat!(
+                ".subannotation LOuter;\n",
+                "    value = .subannotation LInner;\n",
+                "        value = 0x10\n",
+                "    .end subannotation\n",
+                ".end subannotation",
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_value_arg() {
+        let mut reader = Reader::new(&[0x20, 0x00, 0x00], "encoded_value");
+        assert!(EncodedValue::try_parse_from_reader(&mut reader).is_err());
+    }
+}
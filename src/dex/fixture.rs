@@ -0,0 +1,315 @@
+//! Builds small, well-formed dex files for tests.
+
+use crate::utils::adler32;
+
+/// where the data section of a built file starts, relative to its header
+const DATA_OFF: u32 = 0x400;
+
+/// Collects the items of a dex file and lays them out with the header, id sections, data and `map_list` in spec order.
+///
+/// Offsets handed out by [`Self::data`] and stored in items are relative to the start of the container, so a builder made with [`Self::v41`] can be placed after other dex files.
+#[derive(Debug)]
+pub struct DexBuilder {
+    version: &'static [u8; 3],
+    /// offset of the header in the container
+    base: u32,
+    strings: Vec<String>,
+    types: Vec<u32>,
+    protos: Vec<[u32; 3]>,
+    fields: Vec<(u16, u16, u32)>,
+    methods: Vec<(u16, u16, u32)>,
+    class_defs: Vec<[u32; 8]>,
+    data: Vec<u8>,
+    /// `map_list` entries for sections of the data section, as `(type_code, size, offset)`
+    data_sections: Vec<(u16, u32, u32)>,
+}
+
+impl DexBuilder {
+    pub fn new() -> Self {
+        Self {
+            version: b"035",
+            base: 0,
+            strings: Vec::new(),
+            types: Vec::new(),
+            protos: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            class_defs: Vec::new(),
+            data: Vec::new(),
+            data_sections: Vec::new(),
+        }
+    }
+
+    /// A version 041 dex file whose header will be placed at `base` in its container.
+    pub fn v41(base: u32) -> Self {
+        Self {
+            version: b"041",
+            base,
+            ..Self::new()
+        }
+    }
+
+    fn header_size(&self) -> u32 {
+        if self.version == b"041" {
+            0x78
+        } else {
+            0x70
+        }
+    }
+
+    /// Returns the index of the ASCII string `s`, adding it if needed.
+    pub fn string(&mut self, s: &str) -> u32 {
+        assert!(s.is_ascii() && s.len() < 0x80);
+        if let Some(idx) = self.strings.iter().position(|string| string == s) {
+            return idx as u32;
+        }
+        self.strings.push(s.to_string());
+        self.strings.len() as u32 - 1
+    }
+
+    /// Returns the index of the type with `descriptor`, adding it if needed.
+    pub fn type_(&mut self, descriptor: &str) -> u32 {
+        let descriptor_idx = self.string(descriptor);
+        if let Some(idx) = self.types.iter().position(|idx| *idx == descriptor_idx) {
+            return idx as u32;
+        }
+        self.types.push(descriptor_idx);
+        self.types.len() as u32 - 1
+    }
+
+    /// Adds a prototype, writing its parameters to a `type_list` in the data section.
+    pub fn proto(&mut self, shorty: &str, return_type: &str, parameters: &[&str]) -> u32 {
+        let shorty_idx = self.string(shorty);
+        let return_type_idx = self.type_(return_type);
+        let parameters_off = if parameters.is_empty() {
+            0
+        } else {
+            let mut type_list = (parameters.len() as u32).to_le_bytes().to_vec();
+            for parameter in parameters {
+                type_list.extend((self.type_(parameter) as u16).to_le_bytes());
+            }
+            self.data(&type_list)
+        };
+        self.protos
+            .push([shorty_idx, return_type_idx, parameters_off]);
+        self.protos.len() as u32 - 1
+    }
+
+    pub fn field(&mut self, class: &str, field_type: &str, name: &str) -> u32 {
+        let item = (
+            self.type_(class) as u16,
+            self.type_(field_type) as u16,
+            self.string(name),
+        );
+        self.fields.push(item);
+        self.fields.len() as u32 - 1
+    }
+
+    pub fn method(&mut self, class: &str, proto_idx: u32, name: &str) -> u32 {
+        let item = (
+            self.type_(class) as u16,
+            proto_idx as u16,
+            self.string(name),
+        );
+        self.methods.push(item);
+        self.methods.len() as u32 - 1
+    }
+
+    /// Adds a `class_def_item` given as its eight fields in file order.
+    pub fn class_def(&mut self, class_def: [u32; 8]) -> u32 {
+        self.class_defs.push(class_def);
+        self.class_defs.len() as u32 - 1
+    }
+
+    /// Appends `bytes` to the data section, aligned to 4 bytes, and returns their offset.
+    pub fn data(&mut self, bytes: &[u8]) -> u32 {
+        while !self.data.len().is_multiple_of(4) {
+            self.data.push(0);
+        }
+        let offset = self.base + DATA_OFF + self.data.len() as u32;
+        self.data.extend_from_slice(bytes);
+        offset
+    }
+
+    /// Declares a section of `size` items of `type_code` at `offset` in the `map_list`, which is not checked against what the data section holds.
+    pub fn map_item(&mut self, type_code: u16, size: u32, offset: u32) {
+        self.data_sections.push((type_code, size, offset));
+    }
+
+    /// Lays out the file and fills in `file_size`, the checksum and the signature. The result starts at the header, to be placed at the `base` the builder was made with.
+    pub fn build(&self) -> Vec<u8> {
+        let header_size = self.header_size();
+        let mut out = vec![0; header_size as usize];
+        let mut map = vec![(0x0000u16, 1u32, self.base)];
+
+        // id sections, whose string offsets are filled in once the string data is placed
+        let mut section = |out: &mut Vec<u8>, type_code: u16, size: usize, item: &[u8]| {
+            let offset = self.base + out.len() as u32;
+            if size > 0 {
+                map.push((type_code, size as u32, offset));
+            }
+            out.extend_from_slice(item);
+            (size as u32, if size > 0 { offset } else { 0 })
+        };
+        let string_ids = section(
+            &mut out,
+            0x0001,
+            self.strings.len(),
+            &vec![0; self.strings.len() * 4],
+        );
+        let types: Vec<u8> = self
+            .types
+            .iter()
+            .flat_map(|idx| idx.to_le_bytes())
+            .collect();
+        let type_ids = section(&mut out, 0x0002, self.types.len(), &types);
+        let protos: Vec<u8> = self
+            .protos
+            .iter()
+            .flatten()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let proto_ids = section(&mut out, 0x0003, self.protos.len(), &protos);
+        let member = |(class_idx, idx, name_idx): &(u16, u16, u32)| {
+            [
+                &class_idx.to_le_bytes()[..],
+                &idx.to_le_bytes(),
+                &name_idx.to_le_bytes(),
+            ]
+            .concat()
+        };
+        let fields: Vec<u8> = self.fields.iter().flat_map(member).collect();
+        let field_ids = section(&mut out, 0x0004, self.fields.len(), &fields);
+        let methods: Vec<u8> = self.methods.iter().flat_map(member).collect();
+        let method_ids = section(&mut out, 0x0005, self.methods.len(), &methods);
+        let class_defs: Vec<u8> = self
+            .class_defs
+            .iter()
+            .flatten()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let class_def_ids = section(&mut out, 0x0006, self.class_defs.len(), &class_defs);
+
+        assert!(
+            out.len() <= DATA_OFF as usize,
+            "too many ids for the fixture"
+        );
+        out.resize(DATA_OFF as usize, 0);
+        out.extend_from_slice(&self.data);
+
+        map.extend(&self.data_sections);
+        if !self.strings.is_empty() {
+            map.push((
+                0x2002,
+                self.strings.len() as u32,
+                self.base + out.len() as u32,
+            ));
+        }
+        for (i, string) in self.strings.iter().enumerate() {
+            let offset = self.base + out.len() as u32;
+            let id = (string_ids.1 - self.base) as usize + i * 4;
+            out[id..id + 4].copy_from_slice(&offset.to_le_bytes());
+            out.push(string.len() as u8);
+            out.extend_from_slice(string.as_bytes());
+            out.push(0);
+        }
+
+        while !out.len().is_multiple_of(4) {
+            out.push(0);
+        }
+        let map_off = self.base + out.len() as u32;
+        map.push((0x1000, 1, map_off));
+        map.sort_by_key(|(_, _, offset)| *offset);
+        out.extend((map.len() as u32).to_le_bytes());
+        for (type_code, size, offset) in &map {
+            out.extend(type_code.to_le_bytes());
+            out.extend([0, 0]);
+            out.extend(size.to_le_bytes());
+            out.extend(offset.to_le_bytes());
+        }
+
+        let file_size = out.len() as u32;
+        let mut put = |offset: usize, value: u32| {
+            out[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        put(32, file_size);
+        put(36, header_size);
+        put(40, 0x12345678);
+        put(52, map_off);
+        for (i, (size, offset)) in [
+            string_ids,
+            type_ids,
+            proto_ids,
+            field_ids,
+            method_ids,
+            class_def_ids,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            put(56 + i * 8, size);
+            put(60 + i * 8, offset);
+        }
+        put(104, file_size - DATA_OFF);
+        put(108, self.base + DATA_OFF);
+        if header_size == 0x78 {
+            put(112, self.base + file_size);
+            put(116, self.base);
+        }
+        out[..4].copy_from_slice(b"dex\n");
+        out[4..7].copy_from_slice(self.version);
+        seal(&mut out);
+        out
+    }
+}
+
+/// Recomputes the signature and checksum of the dex file at the start of `file`, after its contents were changed.
+pub fn seal(file: &mut [u8]) {
+    let signature = sha1_smol::Sha1::from(&file[32..]).digest().bytes();
+    file[12..32].copy_from_slice(&signature);
+    let checksum = adler32(&file[12..]);
+    file[8..12].copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// Builds a version 041 container from files made with [`DexBuilder::v41`], each at the `base` it was made with, and points every `container_size` at the end of the last one.
+pub fn container(dexes: &[DexBuilder]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut starts = Vec::new();
+    for dex in dexes {
+        assert_eq!(dex.base as usize, out.len(), "dex files must be contiguous");
+        starts.push(out.len());
+        out.extend(dex.build());
+    }
+    let container_size = out.len() as u32;
+    for (i, start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(out.len());
+        out[start + 112..start + 116].copy_from_slice(&container_size.to_le_bytes());
+        seal(&mut out[*start..end]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dex::Dex, diagnostics::Diagnostics};
+
+    #[test]
+    fn test_fixture_is_well_formed() {
+        let mut builder = DexBuilder::new();
+        let proto = builder.proto("VI", "V", &["I"]);
+        builder.method("LFoo;", proto, "run");
+        builder.field("LFoo;", "I", "count");
+        let file = builder.build();
+
+        let diagnostics = Diagnostics::new(true);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+        assert!(dex.verify_integrity().is_empty());
+        assert_eq!(dex.proto_descriptor(proto as usize).unwrap(), "(I)V");
+        assert_eq!(dex.method_ids.len(), 1);
+        assert_eq!(
+            dex.types.get(dex.field_ids[0].type_idx as usize).unwrap(),
+            "I"
+        );
+    }
+}
@@ -1,4 +1,53 @@
-use crate::{traits::parse::TryParseFromBytes, utils::read_u32_le};
+use crate::{errors::DexParseError, utils::read_u32_le};
+
+/// `header_size` of dex files up to version 040
+const HEADER_SIZE_V40: usize = 0x70;
+/// `header_size` of dex files from version 041, which adds `container_size` and `header_offset`
+const HEADER_SIZE_V41: usize = 0x78;
+/// the standard `endian_tag`
+const ENDIAN_CONSTANT: u32 = 0x12345678;
+/// the `endian_tag` of a file whose contents are byte-swapped
+const REVERSE_ENDIAN_CONSTANT: u32 = 0x78563412;
+
+/// https://source.android.com/docs/core/runtime/dex-format#dex-file-magic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DexVersion {
+    /// the original format
+    V035,
+    /// adds default and static interface methods (Android 7.0)
+    V037,
+    /// adds `invoke-polymorphic`, `invoke-custom`, method handles and call sites (Android 8.0)
+    V038,
+    /// adds `const-method-handle` and `const-method-type` (Android 9)
+    V039,
+    /// allows more characters in simple names (Android 10)
+    V040,
+    /// adds containers holding several dex files (Android 15)
+    V041,
+}
+
+impl DexVersion {
+    /// Parses `magic`, which must be `dex\n` followed by a known three-digit version and a NUL.
+    pub fn from_magic(magic: &[u8]) -> Option<Self> {
+        let [b'd', b'e', b'x', b'\n', a, b, c, 0] = *magic.get(..8)? else {
+            return None;
+        };
+        match &[a, b, c] {
+            b"035" => Some(Self::V035),
+            b"037" => Some(Self::V037),
+            b"038" => Some(Self::V038),
+            b"039" => Some(Self::V039),
+            b"040" => Some(Self::V040),
+            b"041" => Some(Self::V041),
+            _ => None,
+        }
+    }
+
+    /// Whether files of this version may hold several dex files behind one another, each with a 0x78-byte header.
+    pub fn is_container(self) -> bool {
+        self >= Self::V041
+    }
+}
 
 /// https://source.android.com/docs/core/runtime/dex-format#header-item
 #[allow(unused)]
@@ -6,6 +55,8 @@ use crate::{traits::parse::TryParseFromBytes, utils::read_u32_le};
 pub struct HeaderItem {
     /// magic value. See discussion above under "`DEX_FILE_MAGIC`" for more details.
     pub magic: [u8; 8],
+    /// the version encoded in `magic`
+    pub version: DexVersion,
     /// adler32 checksum of the rest of the file (everything but `magic` and this field); used to detect file corruption
     pub checksum: u32,
     /// SHA-1 signature (hash) of the rest of the file (everything but `magic`, checksum, and this field); used to uniquely identify files
@@ -63,23 +114,71 @@ pub struct HeaderItem {
     /// this field does not exist. It can be assumed to be equal to file_size. (v40 or earlier)
     ///
     /// size of the entire file (including other dex headers and their data). (v41 or later)
-    containzer_size: u32,
+    pub container_size: u32,
     /// this field does not exist. It can be assumed to be equal to 0. (v40 or earlier)
     ///
     /// offset from the start of the file to the start of this header. (v41 or later)
-    header_offset: u32,
+    pub header_offset: u32,
 }
 
-impl TryParseFromBytes for HeaderItem {
-    const NAME: &'static str = "header_item";
-    const SIZE: usize = 112;
+impl HeaderItem {
+    pub const NAME: &'static str = "header_item";
+
+    /// Whether this header may be one of several in a container, which was introduced in version 041.
+    pub fn is_container(&self) -> bool {
+        self.version.is_container()
+    }
+
+    /// Parses the 0x70-byte header, or the 0x78-byte one when `magic` reports version 041 or later.
+    ///
+    /// Rejects an unknown `magic` and any `endian_tag` other than `ENDIAN_CONSTANT`. Reverse-endian files are not produced by any known toolchain, so they are reported rather than byte-swapped.
+    pub fn try_parse_from_bytes(buffer: &[u8]) -> Result<Self, DexParseError> {
+        if buffer.len() < HEADER_SIZE_V40 {
+            return Err(DexParseError::InvalidElementSize {
+                field: Self::NAME,
+                expected: HEADER_SIZE_V40,
+                actual: buffer.len(),
+            });
+        }
+
+        let version = DexVersion::from_magic(buffer).ok_or(DexParseError::InvalidMagic {
+            magic: buffer[0..8].try_into().unwrap(),
+        })?;
+        let expected = if version.is_container() {
+            HEADER_SIZE_V41
+        } else {
+            HEADER_SIZE_V40
+        };
+        if buffer.len() < expected {
+            return Err(DexParseError::InvalidElementSize {
+                field: Self::NAME,
+                expected,
+                actual: buffer.len(),
+            });
+        }
+
+        match read_u32_le(buffer, 40) {
+            ENDIAN_CONSTANT => Ok(Self::parse_fields(buffer, version)),
+            REVERSE_ENDIAN_CONSTANT => Err(DexParseError::ReverseEndian),
+            endian_tag => Err(DexParseError::InvalidEndianTag(endian_tag)),
+        }
+    }
+
+    /// Reads the fields of a header whose size, `magic` and `endian_tag` have been checked.
+    fn parse_fields(buffer: &[u8], version: DexVersion) -> Self {
+        let file_size = read_u32_le(buffer, 32);
+        let (container_size, header_offset) = if version.is_container() {
+            (read_u32_le(buffer, 112), read_u32_le(buffer, 116))
+        } else {
+            (file_size, 0)
+        };
 
-    fn parse_from_bytes(buffer: &[u8]) -> Self {
         Self {
             magic: buffer[0..8].try_into().unwrap(),
+            version,
             checksum: read_u32_le(buffer, 8),
             signature: buffer[12..32].try_into().unwrap(),
-            file_size: read_u32_le(buffer, 32),
+            file_size,
             header_size: read_u32_le(buffer, 36),
             endian_tag: read_u32_le(buffer, 40),
             link_size: read_u32_le(buffer, 44),
@@ -99,8 +198,87 @@ impl TryParseFromBytes for HeaderItem {
             class_defs_off: read_u32_le(buffer, 100),
             data_size: read_u32_le(buffer, 104),
             data_off: read_u32_le(buffer, 108),
-            containzer_size: 0, // We will ignore this
-            header_offset: 0,   // We will ignore this
+            container_size,
+            header_offset,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dex::fixture::DexBuilder;
+
+    #[test]
+    fn test_header_item() {
+        let header = HeaderItem::try_parse_from_bytes(&DexBuilder::new().build()).unwrap();
+        assert_eq!(header.version, DexVersion::V035);
+        assert_eq!(header.header_size, 0x70);
+        assert_eq!(header.header_offset, 0);
+        assert_eq!(header.container_size, header.file_size);
+
+        let header = HeaderItem::try_parse_from_bytes(&DexBuilder::v41(0).build()).unwrap();
+        assert_eq!(header.version, DexVersion::V041);
+        assert!(header.is_container());
+        assert_eq!(header.header_size, 0x78);
+    }
+
+    #[test]
+    fn test_from_magic() {
+        assert_eq!(
+            DexVersion::from_magic(b"dex\n039\0"),
+            Some(DexVersion::V039)
+        );
+        assert_eq!(DexVersion::from_magic(b"dex\n036\0"), None);
+        assert_eq!(DexVersion::from_magic(b"dex\n035"), None);
+        assert_eq!(DexVersion::from_magic(b"dey\n035\0"), None);
+    }
+
+    #[test]
+    fn test_bad_magic() {
+        let mut file = DexBuilder::new().build();
+        file[4..7].copy_from_slice(b"099");
+        assert!(matches!(
+            HeaderItem::try_parse_from_bytes(&file),
+            Err(DexParseError::InvalidMagic { magic }) if &magic == b"dex\n099\0"
+        ));
+    }
+
+    #[test]
+    fn test_endian_tag() {
+        let mut file = DexBuilder::new().build();
+        file[40..44].copy_from_slice(&REVERSE_ENDIAN_CONSTANT.to_le_bytes());
+        assert!(matches!(
+            HeaderItem::try_parse_from_bytes(&file),
+            Err(DexParseError::ReverseEndian)
+        ));
+
+        file[40..44].copy_from_slice(&0xDEADBEEFu32.to_le_bytes());
+        assert!(matches!(
+            HeaderItem::try_parse_from_bytes(&file),
+            Err(DexParseError::InvalidEndianTag(0xDEADBEEF))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_header() {
+        let file = DexBuilder::v41(0).build();
+        assert!(matches!(
+            HeaderItem::try_parse_from_bytes(&file[..0x20]),
+            Err(DexParseError::InvalidElementSize {
+                expected: 0x70,
+                actual: 0x20,
+                ..
+            })
+        ));
+        // Version 041 needs the longer header
+        assert!(matches!(
+            HeaderItem::try_parse_from_bytes(&file[..0x74]),
+            Err(DexParseError::InvalidElementSize {
+                expected: 0x78,
+                actual: 0x74,
+                ..
+            })
+        ));
+    }
+}
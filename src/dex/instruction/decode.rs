@@ -24,7 +24,12 @@ impl Instruction {
 
         let inst = match opcode {
             // 00-0D: Basic operations
-            0x00 => Instruction::Nop,
+            0x00 => match buffer[1] {
+                0x01 => Self::decode_packed_switch_payload(buffer)?,
+                0x02 => Self::decode_sparse_switch_payload(buffer)?,
+                0x03 => Self::decode_fill_array_data_payload(buffer)?,
+                _ => Instruction::Nop,
+            },
             0x01 => {
                 let (dst, src) = to_nibbles(buffer[1]);
                 Instruction::Move { dst, src }
@@ -80,11 +85,10 @@ impl Instruction {
 
             // 12-1F: Constants and checks
             0x12 => {
-                let (dst, value) = to_nibbles(buffer[1]);
-                Instruction::Const4 {
-                    dst,
-                    value: value as i8,
-                }
+                let (dst, _) = to_nibbles(buffer[1]);
+                // The literal is the signed high nibble
+                let value = buffer[1] as i8 >> 4;
+                Instruction::Const4 { dst, value }
             }
             0x13 => {
                 let dst = buffer[1];
@@ -765,4 +769,60 @@ impl Instruction {
         };
         Ok(inst)
     }
+
+    /// Returns [`InstructionError::Size`] if `buffer` cannot hold a payload of `expected` bytes.
+    fn check_payload_size(buffer: &[u8], expected: usize) -> Result<(), InstructionError> {
+        if buffer.len() < expected {
+            return Err(InstructionError::Size {
+                opcode: buffer[0],
+                expected,
+                actual: buffer.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn decode_packed_switch_payload(buffer: &[u8]) -> Result<Self, InstructionError> {
+        Self::check_payload_size(buffer, 8)?;
+        let size = read_u16_le(buffer, 2) as usize;
+        let first_key = read_u32_le(buffer, 4) as i32;
+
+        Self::check_payload_size(buffer, 8 + size * 4)?;
+        let targets = (0..size)
+            .map(|i| read_u32_le(buffer, 8 + i * 4) as i32)
+            .collect();
+
+        Ok(Instruction::PackedSwitchPayload { first_key, targets })
+    }
+
+    fn decode_sparse_switch_payload(buffer: &[u8]) -> Result<Self, InstructionError> {
+        Self::check_payload_size(buffer, 4)?;
+        let size = read_u16_le(buffer, 2) as usize;
+
+        Self::check_payload_size(buffer, 4 + size * 8)?;
+        let keys = (0..size)
+            .map(|i| read_u32_le(buffer, 4 + i * 4) as i32)
+            .collect();
+        let targets = (0..size)
+            .map(|i| read_u32_le(buffer, 4 + size * 4 + i * 4) as i32)
+            .collect();
+
+        Ok(Instruction::SparseSwitchPayload { keys, targets })
+    }
+
+    fn decode_fill_array_data_payload(buffer: &[u8]) -> Result<Self, InstructionError> {
+        Self::check_payload_size(buffer, 8)?;
+        let element_width = read_u16_le(buffer, 2);
+        let size = read_u32_le(buffer, 4);
+
+        let data_size = element_width as usize * size as usize;
+        Self::check_payload_size(buffer, 8 + data_size)?;
+        let data = buffer[8..8 + data_size].to_vec();
+
+        Ok(Instruction::FillArrayDataPayload {
+            element_width,
+            size,
+            data,
+        })
+    }
 }
@@ -0,0 +1,761 @@
+use crate::errors::InstructionError;
+
+use super::Instruction;
+
+/// Collects the code units of a single instruction, checking that every operand fits its field.
+struct Encoder {
+    opcode: &'static str,
+    out: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.out.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Packs two 4-bit operands into a byte, the inverse of [`crate::utils::to_nibbles`].
+    fn nibbles(&mut self, low: u8, high: u8) -> Result<(), InstructionError> {
+        for value in [low, high] {
+            if value > 0x0F {
+                return Err(self.out_of_range(value.into(), 4));
+            }
+        }
+        self.u8(high << 4 | low);
+        Ok(())
+    }
+
+    /// `op vAA`, and the other formats starting with an 8-bit operand
+    fn op_aa(&mut self, opcode: u8, aa: u8) {
+        self.u8(opcode);
+        self.u8(aa);
+    }
+
+    /// `op vA, vB`, and the other formats starting with two 4-bit operands
+    fn op_ba(&mut self, opcode: u8, a: u8, b: u8) -> Result<(), InstructionError> {
+        self.u8(opcode);
+        self.nibbles(a, b)
+    }
+
+    /// `op {vC, vD, vE, vF, vG}, kind@BBBB` (format 35c)
+    fn op_35c(
+        &mut self,
+        opcode: u8,
+        idx: u16,
+        args: &[u8; 5],
+        arg_cnt: u8,
+    ) -> Result<(), InstructionError> {
+        let [c, d, e, f, g] = *args;
+        self.op_ba(opcode, g, arg_cnt)?;
+        self.u16(idx);
+        self.nibbles(c, d)?;
+        self.nibbles(e, f)
+    }
+
+    /// `op {vCCCC .. vNNNN}, kind@BBBB` (format 3rc)
+    fn op_3rc(&mut self, opcode: u8, idx: u16, first_arg: u16, arg_cnt: u8) {
+        self.op_aa(opcode, arg_cnt);
+        self.u16(idx);
+        self.u16(first_arg);
+    }
+
+    /// `op vAA, vBB, vCC` (format 23x), also used by `binop/lit8` with a literal as the last byte
+    fn op_23x(&mut self, opcode: u8, aa: u8, bb: u8, cc: u8) {
+        self.op_aa(opcode, aa);
+        self.u8(bb);
+        self.u8(cc);
+    }
+
+    /// Returns the number of entries of a payload, which must fit in its 16-bit size field.
+    fn payload_size(&self, len: usize) -> Result<u16, InstructionError> {
+        u16::try_from(len).map_err(|_| self.out_of_range(len as i64, 16))
+    }
+
+    fn out_of_range(&self, value: i64, bits: u32) -> InstructionError {
+        InstructionError::OperandRange {
+            opcode: self.opcode,
+            value,
+            bits,
+        }
+    }
+}
+
+impl Instruction {
+    /// Encodes the instruction into exactly the [`Instruction::size_bytes`] bytes [`Instruction::try_decode`] reads it from, such that decoding the result yields `self` again.
+    ///
+    /// Returns [`InstructionError::OperandRange`] if an operand does not fit its field, e.g. a register above `v15` in a 4-bit operand or a `const/4` literal outside `-8..=7`, and [`InstructionError::PayloadLength`] if the contents of a payload disagree with each other.
+    pub fn encode(&self) -> Result<Vec<u8>, InstructionError> {
+        let mut e = Encoder {
+            opcode: self.opcode(),
+            out: Vec::with_capacity(self.size_bytes()),
+        };
+
+        match self {
+            // 00-0D: Basic operations
+            Self::Nop => e.op_aa(0x00, 0x00),
+            Self::Move { dst, src } => e.op_ba(0x01, *dst, *src)?,
+            Self::MoveFrom16 { dst, src } => {
+                e.op_aa(0x02, *dst);
+                e.u16(*src);
+            }
+            Self::Move16 { dst, src } => {
+                e.op_aa(0x03, 0x00);
+                e.u16(*dst);
+                e.u16(*src);
+            }
+            Self::MoveWide { dst, src } => e.op_ba(0x04, *dst, *src)?,
+            Self::MoveWideFrom16 { dst, src } => {
+                e.op_aa(0x05, *dst);
+                e.u16(*src);
+            }
+            Self::MoveWide16 { dst, src } => {
+                e.op_aa(0x06, 0x00);
+                e.u16(*dst);
+                e.u16(*src);
+            }
+            Self::MoveObject { dst, src } => e.op_ba(0x07, *dst, *src)?,
+            Self::MoveObjectFrom16 { dst, src } => {
+                e.op_aa(0x08, *dst);
+                e.u16(*src);
+            }
+            Self::MoveObject16 { dst, src } => {
+                e.op_aa(0x09, 0x00);
+                e.u16(*dst);
+                e.u16(*src);
+            }
+            Self::MoveResult { dst } => e.op_aa(0x0A, *dst),
+            Self::MoveResultWide { dst } => e.op_aa(0x0B, *dst),
+            Self::MoveResultObject { dst } => e.op_aa(0x0C, *dst),
+            Self::MoveException { dst } => e.op_aa(0x0D, *dst),
+
+            // 0E-11: Returns
+            Self::ReturnVoid => e.op_aa(0x0E, 0x00),
+            Self::Return { value } => e.op_aa(0x0F, *value),
+            Self::ReturnWide { value } => e.op_aa(0x10, *value),
+            Self::ReturnObject { value } => e.op_aa(0x11, *value),
+
+            // 12-1F: Constants and checks
+            Self::Const4 { dst, value } => {
+                if !(-8..=7).contains(value) {
+                    return Err(e.out_of_range((*value).into(), 4));
+                }
+                // The literal is stored as the two's complement high nibble
+                e.op_ba(0x12, *dst, *value as u8 & 0x0F)?;
+            }
+            Self::Const16 { dst, value } => {
+                e.op_aa(0x13, *dst);
+                e.u16(*value as u16);
+            }
+            Self::Const { dst, value } => {
+                e.op_aa(0x14, *dst);
+                e.u32(*value as u32);
+            }
+            Self::ConstHigh16 { dst, value } => {
+                e.op_aa(0x15, *dst);
+                e.u16(*value as u16);
+            }
+            Self::ConstWide16 { dst, value } => {
+                e.op_aa(0x16, *dst);
+                e.u16(*value as u16);
+            }
+            Self::ConstWide32 { dst, value } => {
+                e.op_aa(0x17, *dst);
+                e.u32(*value as u32);
+            }
+            Self::ConstWide { dst, value } => {
+                e.op_aa(0x18, *dst);
+                e.u64(*value as u64);
+            }
+            Self::ConstWideHigh16 { dst, value } => {
+                e.op_aa(0x19, *dst);
+                e.u16(*value as u16);
+            }
+            Self::ConstString { dst, string_idx } => {
+                e.op_aa(0x1A, *dst);
+                e.u16(*string_idx);
+            }
+            Self::ConstStringJumbo { dst, string_idx } => {
+                e.op_aa(0x1B, *dst);
+                e.u32(*string_idx);
+            }
+            Self::ConstClass { dst, type_idx } => {
+                e.op_aa(0x1C, *dst);
+                e.u16(*type_idx);
+            }
+            Self::MonitorEnter { reference } => e.op_aa(0x1D, *reference),
+            Self::MonitorExit { reference } => e.op_aa(0x1E, *reference),
+            Self::CheckCast {
+                reference,
+                type_idx,
+            } => {
+                e.op_aa(0x1F, *reference);
+                e.u16(*type_idx);
+            }
+            Self::InstanceOf {
+                dst,
+                reference,
+                type_idx,
+            } => {
+                e.op_ba(0x20, *dst, *reference)?;
+                e.u16(*type_idx);
+            }
+
+            // 21-2C: Arrays and jumps
+            Self::ArrayLength { dst, array } => e.op_ba(0x21, *dst, *array)?,
+            Self::NewInstance { dst, type_idx } => {
+                e.op_aa(0x22, *dst);
+                e.u16(*type_idx);
+            }
+            Self::NewArray {
+                dst,
+                size,
+                type_idx,
+            } => {
+                e.op_ba(0x23, *dst, *size)?;
+                e.u16(*type_idx);
+            }
+            Self::FilledNewArray {
+                type_idx,
+                args,
+                arg_cnt,
+            } => e.op_35c(0x24, *type_idx, args, *arg_cnt)?,
+            Self::FilledNewArrayRange {
+                type_idx,
+                first_arg,
+                arg_cnt,
+            } => e.op_3rc(0x25, *type_idx, *first_arg, *arg_cnt),
+            Self::FillArrayData { array, offset } => {
+                e.op_aa(0x26, *array);
+                e.u32(*offset as u32);
+            }
+            Self::Throw { exception } => e.op_aa(0x27, *exception),
+            Self::Goto { offset } => e.op_aa(0x28, *offset as u8),
+            Self::Goto16 { offset } => {
+                e.op_aa(0x29, 0x00);
+                e.u16(*offset as u16);
+            }
+            Self::Goto32 { offset } => {
+                e.op_aa(0x2A, 0x00);
+                e.u32(*offset as u32);
+            }
+            Self::PackedSwitch { value, offset } => {
+                e.op_aa(0x2B, *value);
+                e.u32(*offset as u32);
+            }
+            Self::SparseSwitch { value, offset } => {
+                e.op_aa(0x2C, *value);
+                e.u32(*offset as u32);
+            }
+
+            // 2D-3D: Comparisons and branches
+            Self::CmplFloat { dst, src_a, src_b } => e.op_23x(0x2D, *dst, *src_a, *src_b),
+            Self::CmpgFloat { dst, src_a, src_b } => e.op_23x(0x2E, *dst, *src_a, *src_b),
+            Self::CmplDouble { dst, src_a, src_b } => e.op_23x(0x2F, *dst, *src_a, *src_b),
+            Self::CmpgDouble { dst, src_a, src_b } => e.op_23x(0x30, *dst, *src_a, *src_b),
+            Self::CmpLong { dst, src_a, src_b } => e.op_23x(0x31, *dst, *src_a, *src_b),
+            Self::IfEq { a, b, offset }
+            | Self::IfNe { a, b, offset }
+            | Self::IfLt { a, b, offset }
+            | Self::IfGe { a, b, offset }
+            | Self::IfGt { a, b, offset }
+            | Self::IfLe { a, b, offset } => {
+                let opcode = match self {
+                    Self::IfEq { .. } => 0x32,
+                    Self::IfNe { .. } => 0x33,
+                    Self::IfLt { .. } => 0x34,
+                    Self::IfGe { .. } => 0x35,
+                    Self::IfGt { .. } => 0x36,
+                    _ => 0x37,
+                };
+                e.op_ba(opcode, *a, *b)?;
+                e.u16(*offset as u16);
+            }
+            Self::IfEqz { a, offset }
+            | Self::IfNez { a, offset }
+            | Self::IfLtz { a, offset }
+            | Self::IfGez { a, offset }
+            | Self::IfGtz { a, offset }
+            | Self::IfLez { a, offset } => {
+                let opcode = match self {
+                    Self::IfEqz { .. } => 0x38,
+                    Self::IfNez { .. } => 0x39,
+                    Self::IfLtz { .. } => 0x3A,
+                    Self::IfGez { .. } => 0x3B,
+                    Self::IfGtz { .. } => 0x3C,
+                    _ => 0x3D,
+                };
+                e.op_aa(opcode, *a);
+                e.u16(*offset as u16);
+            }
+
+            // 44-51: Array accesses
+            Self::Aget { src, array, index } => e.op_23x(0x44, *src, *array, *index),
+            Self::AgetWide { src, array, index } => e.op_23x(0x45, *src, *array, *index),
+            Self::AgetObject { src, array, index } => e.op_23x(0x46, *src, *array, *index),
+            Self::AgetBoolean { src, array, index } => e.op_23x(0x47, *src, *array, *index),
+            Self::AgetByte { src, array, index } => e.op_23x(0x48, *src, *array, *index),
+            Self::AgetChar { src, array, index } => e.op_23x(0x49, *src, *array, *index),
+            Self::AgetShort { src, array, index } => e.op_23x(0x4A, *src, *array, *index),
+            Self::Aput { dst, array, index } => e.op_23x(0x4B, *dst, *array, *index),
+            Self::AputWide { dst, array, index } => e.op_23x(0x4C, *dst, *array, *index),
+            Self::AputObject { dst, array, index } => e.op_23x(0x4D, *dst, *array, *index),
+            Self::AputBoolean { dst, array, index } => e.op_23x(0x4E, *dst, *array, *index),
+            Self::AputByte { dst, array, index } => e.op_23x(0x4F, *dst, *array, *index),
+            Self::AputChar { dst, array, index } => e.op_23x(0x50, *dst, *array, *index),
+            Self::AputShort { dst, array, index } => e.op_23x(0x51, *dst, *array, *index),
+
+            // 52-5F: Instance field accesses
+            Self::Iget {
+                src: value,
+                object,
+                field_idx,
+            }
+            | Self::IgetWide {
+                src: value,
+                object,
+                field_idx,
+            }
+            | Self::IgetObject {
+                src: value,
+                object,
+                field_idx,
+            }
+            | Self::IgetBoolean {
+                src: value,
+                object,
+                field_idx,
+            }
+            | Self::IgetByte {
+                src: value,
+                object,
+                field_idx,
+            }
+            | Self::IgetChar {
+                src: value,
+                object,
+                field_idx,
+            }
+            | Self::IgetShort {
+                src: value,
+                object,
+                field_idx,
+            }
+            | Self::Iput {
+                dst: value,
+                object,
+                field_idx,
+            }
+            | Self::IputWide {
+                dst: value,
+                object,
+                field_idx,
+            }
+            | Self::IputObject {
+                dst: value,
+                object,
+                field_idx,
+            }
+            | Self::IputBoolean {
+                dst: value,
+                object,
+                field_idx,
+            }
+            | Self::IputByte {
+                dst: value,
+                object,
+                field_idx,
+            }
+            | Self::IputChar {
+                dst: value,
+                object,
+                field_idx,
+            }
+            | Self::IputShort {
+                dst: value,
+                object,
+                field_idx,
+            } => {
+                let opcode = match self {
+                    Self::Iget { .. } => 0x52,
+                    Self::IgetWide { .. } => 0x53,
+                    Self::IgetObject { .. } => 0x54,
+                    Self::IgetBoolean { .. } => 0x55,
+                    Self::IgetByte { .. } => 0x56,
+                    Self::IgetChar { .. } => 0x57,
+                    Self::IgetShort { .. } => 0x58,
+                    Self::Iput { .. } => 0x59,
+                    Self::IputWide { .. } => 0x5A,
+                    Self::IputObject { .. } => 0x5B,
+                    Self::IputBoolean { .. } => 0x5C,
+                    Self::IputByte { .. } => 0x5D,
+                    Self::IputChar { .. } => 0x5E,
+                    _ => 0x5F,
+                };
+                e.op_ba(opcode, *value, *object)?;
+                e.u16(*field_idx);
+            }
+
+            // 60-6D: Static field accesses
+            Self::Sget {
+                src: value,
+                field_idx,
+            }
+            | Self::SgetWide {
+                src: value,
+                field_idx,
+            }
+            | Self::SgetObject {
+                src: value,
+                field_idx,
+            }
+            | Self::SgetBoolean {
+                src: value,
+                field_idx,
+            }
+            | Self::SgetByte {
+                src: value,
+                field_idx,
+            }
+            | Self::SgetChar {
+                src: value,
+                field_idx,
+            }
+            | Self::SgetShort {
+                src: value,
+                field_idx,
+            }
+            | Self::Sput {
+                dst: value,
+                field_idx,
+            }
+            | Self::SputWide {
+                dst: value,
+                field_idx,
+            }
+            | Self::SputObject {
+                dst: value,
+                field_idx,
+            }
+            | Self::SputBoolean {
+                dst: value,
+                field_idx,
+            }
+            | Self::SputByte {
+                dst: value,
+                field_idx,
+            }
+            | Self::SputChar {
+                dst: value,
+                field_idx,
+            }
+            | Self::SputShort {
+                dst: value,
+                field_idx,
+            } => {
+                let opcode = match self {
+                    Self::Sget { .. } => 0x60,
+                    Self::SgetWide { .. } => 0x61,
+                    Self::SgetObject { .. } => 0x62,
+                    Self::SgetBoolean { .. } => 0x63,
+                    Self::SgetByte { .. } => 0x64,
+                    Self::SgetChar { .. } => 0x65,
+                    Self::SgetShort { .. } => 0x66,
+                    Self::Sput { .. } => 0x67,
+                    Self::SputWide { .. } => 0x68,
+                    Self::SputObject { .. } => 0x69,
+                    Self::SputBoolean { .. } => 0x6A,
+                    Self::SputByte { .. } => 0x6B,
+                    Self::SputChar { .. } => 0x6C,
+                    _ => 0x6D,
+                };
+                e.op_aa(opcode, *value);
+                e.u16(*field_idx);
+            }
+
+            // 6E-78: Invokes
+            Self::InvokeVirtual {
+                method_idx,
+                args,
+                arg_cnt,
+            } => e.op_35c(0x6E, *method_idx, args, *arg_cnt)?,
+            Self::InvokeSuper {
+                method_idx,
+                args,
+                arg_cnt,
+            } => e.op_35c(0x6F, *method_idx, args, *arg_cnt)?,
+            Self::InvokeDirect {
+                method_idx,
+                args,
+                arg_cnt,
+            } => e.op_35c(0x70, *method_idx, args, *arg_cnt)?,
+            Self::InvokeStatic {
+                method_idx,
+                args,
+                arg_cnt,
+            } => e.op_35c(0x71, *method_idx, args, *arg_cnt)?,
+            Self::InvokeInterface {
+                method_idx,
+                args,
+                arg_cnt,
+            } => e.op_35c(0x72, *method_idx, args, *arg_cnt)?,
+            Self::InvokeVirtualRange {
+                method_idx,
+                first_arg,
+                arg_cnt,
+            } => e.op_3rc(0x74, *method_idx, *first_arg, *arg_cnt),
+            Self::InvokeSuperRange {
+                method_idx,
+                first_arg,
+                arg_cnt,
+            } => e.op_3rc(0x75, *method_idx, *first_arg, *arg_cnt),
+            Self::InvokeDirectRange {
+                method_idx,
+                first_arg,
+                arg_cnt,
+            } => e.op_3rc(0x76, *method_idx, *first_arg, *arg_cnt),
+            Self::InvokeStaticRange {
+                method_idx,
+                first_arg,
+                arg_cnt,
+            } => e.op_3rc(0x77, *method_idx, *first_arg, *arg_cnt),
+            Self::InvokeInterfaceRange {
+                method_idx,
+                first_arg,
+                arg_cnt,
+            } => e.op_3rc(0x78, *method_idx, *first_arg, *arg_cnt),
+
+            // 7B-8F: Unary operations
+            Self::NegInt { dst, src } => e.op_ba(0x7B, *dst, *src)?,
+            Self::NotInt { dst, src } => e.op_ba(0x7C, *dst, *src)?,
+            Self::NegLong { dst, src } => e.op_ba(0x7D, *dst, *src)?,
+            Self::NotLong { dst, src } => e.op_ba(0x7E, *dst, *src)?,
+            Self::NegFloat { dst, src } => e.op_ba(0x7F, *dst, *src)?,
+            Self::NegDouble { dst, src } => e.op_ba(0x80, *dst, *src)?,
+            Self::IntToLong { dst, src } => e.op_ba(0x81, *dst, *src)?,
+            Self::IntToFloat { dst, src } => e.op_ba(0x82, *dst, *src)?,
+            Self::IntToDouble { dst, src } => e.op_ba(0x83, *dst, *src)?,
+            Self::LongToInt { dst, src } => e.op_ba(0x84, *dst, *src)?,
+            Self::LongToFloat { dst, src } => e.op_ba(0x85, *dst, *src)?,
+            Self::LongToDouble { dst, src } => e.op_ba(0x86, *dst, *src)?,
+            Self::FloatToInt { dst, src } => e.op_ba(0x87, *dst, *src)?,
+            Self::FloatToLong { dst, src } => e.op_ba(0x88, *dst, *src)?,
+            Self::FloatToDouble { dst, src } => e.op_ba(0x89, *dst, *src)?,
+            Self::DoubleToInt { dst, src } => e.op_ba(0x8A, *dst, *src)?,
+            Self::DoubleToLong { dst, src } => e.op_ba(0x8B, *dst, *src)?,
+            Self::DoubleToFloat { dst, src } => e.op_ba(0x8C, *dst, *src)?,
+            Self::IntToByte { dst, src } => e.op_ba(0x8D, *dst, *src)?,
+            Self::IntToChar { dst, src } => e.op_ba(0x8E, *dst, *src)?,
+            Self::IntToShort { dst, src } => e.op_ba(0x8F, *dst, *src)?,
+
+            // 90-AF: Binary operations
+            Self::AddInt { dst, src_a, src_b } => e.op_23x(0x90, *dst, *src_a, *src_b),
+            Self::SubInt { dst, src_a, src_b } => e.op_23x(0x91, *dst, *src_a, *src_b),
+            Self::MulInt { dst, src_a, src_b } => e.op_23x(0x92, *dst, *src_a, *src_b),
+            Self::DivInt { dst, src_a, src_b } => e.op_23x(0x93, *dst, *src_a, *src_b),
+            Self::RemInt { dst, src_a, src_b } => e.op_23x(0x94, *dst, *src_a, *src_b),
+            Self::AndInt { dst, src_a, src_b } => e.op_23x(0x95, *dst, *src_a, *src_b),
+            Self::OrInt { dst, src_a, src_b } => e.op_23x(0x96, *dst, *src_a, *src_b),
+            Self::XorInt { dst, src_a, src_b } => e.op_23x(0x97, *dst, *src_a, *src_b),
+            Self::ShlInt { dst, src_a, src_b } => e.op_23x(0x98, *dst, *src_a, *src_b),
+            Self::ShrInt { dst, src_a, src_b } => e.op_23x(0x99, *dst, *src_a, *src_b),
+            Self::UShrInt { dst, src_a, src_b } => e.op_23x(0x9A, *dst, *src_a, *src_b),
+            Self::AddLong { dst, src_a, src_b } => e.op_23x(0x9B, *dst, *src_a, *src_b),
+            Self::SubLong { dst, src_a, src_b } => e.op_23x(0x9C, *dst, *src_a, *src_b),
+            Self::MulLong { dst, src_a, src_b } => e.op_23x(0x9D, *dst, *src_a, *src_b),
+            Self::DivLong { dst, src_a, src_b } => e.op_23x(0x9E, *dst, *src_a, *src_b),
+            Self::RemLong { dst, src_a, src_b } => e.op_23x(0x9F, *dst, *src_a, *src_b),
+            Self::AndLong { dst, src_a, src_b } => e.op_23x(0xA0, *dst, *src_a, *src_b),
+            Self::OrLong { dst, src_a, src_b } => e.op_23x(0xA1, *dst, *src_a, *src_b),
+            Self::XorLong { dst, src_a, src_b } => e.op_23x(0xA2, *dst, *src_a, *src_b),
+            Self::ShlLong { dst, src_a, src_b } => e.op_23x(0xA3, *dst, *src_a, *src_b),
+            Self::ShrLong { dst, src_a, src_b } => e.op_23x(0xA4, *dst, *src_a, *src_b),
+            Self::UShrLong { dst, src_a, src_b } => e.op_23x(0xA5, *dst, *src_a, *src_b),
+            Self::AddFloat { dst, src_a, src_b } => e.op_23x(0xA6, *dst, *src_a, *src_b),
+            Self::SubFloat { dst, src_a, src_b } => e.op_23x(0xA7, *dst, *src_a, *src_b),
+            Self::MulFloat { dst, src_a, src_b } => e.op_23x(0xA8, *dst, *src_a, *src_b),
+            Self::DivFloat { dst, src_a, src_b } => e.op_23x(0xA9, *dst, *src_a, *src_b),
+            Self::RemFloat { dst, src_a, src_b } => e.op_23x(0xAA, *dst, *src_a, *src_b),
+            Self::AddDouble { dst, src_a, src_b } => e.op_23x(0xAB, *dst, *src_a, *src_b),
+            Self::SubDouble { dst, src_a, src_b } => e.op_23x(0xAC, *dst, *src_a, *src_b),
+            Self::MulDouble { dst, src_a, src_b } => e.op_23x(0xAD, *dst, *src_a, *src_b),
+            Self::DivDouble { dst, src_a, src_b } => e.op_23x(0xAE, *dst, *src_a, *src_b),
+            Self::RemDouble { dst, src_a, src_b } => e.op_23x(0xAF, *dst, *src_a, *src_b),
+
+            // B0-CF: Binary operations in place
+            Self::AddInt2Addr { dst, src } => e.op_ba(0xB0, *dst, *src)?,
+            Self::SubInt2Addr { dst, src } => e.op_ba(0xB1, *dst, *src)?,
+            Self::MulInt2Addr { dst, src } => e.op_ba(0xB2, *dst, *src)?,
+            Self::DivInt2Addr { dst, src } => e.op_ba(0xB3, *dst, *src)?,
+            Self::RemInt2Addr { dst, src } => e.op_ba(0xB4, *dst, *src)?,
+            Self::AndInt2Addr { dst, src } => e.op_ba(0xB5, *dst, *src)?,
+            Self::OrInt2Addr { dst, src } => e.op_ba(0xB6, *dst, *src)?,
+            Self::XorInt2Addr { dst, src } => e.op_ba(0xB7, *dst, *src)?,
+            Self::ShlInt2Addr { dst, src } => e.op_ba(0xB8, *dst, *src)?,
+            Self::ShrInt2Addr { dst, src } => e.op_ba(0xB9, *dst, *src)?,
+            Self::UShrInt2Addr { dst, src } => e.op_ba(0xBA, *dst, *src)?,
+            Self::AddLong2Addr { dst, src } => e.op_ba(0xBB, *dst, *src)?,
+            Self::SubLong2Addr { dst, src } => e.op_ba(0xBC, *dst, *src)?,
+            Self::MulLong2Addr { dst, src } => e.op_ba(0xBD, *dst, *src)?,
+            Self::DivLong2Addr { dst, src } => e.op_ba(0xBE, *dst, *src)?,
+            Self::RemLong2Addr { dst, src } => e.op_ba(0xBF, *dst, *src)?,
+            Self::AndLong2Addr { dst, src } => e.op_ba(0xC0, *dst, *src)?,
+            Self::OrLong2Addr { dst, src } => e.op_ba(0xC1, *dst, *src)?,
+            Self::XorLong2Addr { dst, src } => e.op_ba(0xC2, *dst, *src)?,
+            Self::ShlLong2Addr { dst, src } => e.op_ba(0xC3, *dst, *src)?,
+            Self::ShrLong2Addr { dst, src } => e.op_ba(0xC4, *dst, *src)?,
+            Self::UShrLong2Addr { dst, src } => e.op_ba(0xC5, *dst, *src)?,
+            Self::AddFloat2Addr { dst, src } => e.op_ba(0xC6, *dst, *src)?,
+            Self::SubFloat2Addr { dst, src } => e.op_ba(0xC7, *dst, *src)?,
+            Self::MulFloat2Addr { dst, src } => e.op_ba(0xC8, *dst, *src)?,
+            Self::DivFloat2Addr { dst, src } => e.op_ba(0xC9, *dst, *src)?,
+            Self::RemFloat2Addr { dst, src } => e.op_ba(0xCA, *dst, *src)?,
+            Self::AddDouble2Addr { dst, src } => e.op_ba(0xCB, *dst, *src)?,
+            Self::SubDouble2Addr { dst, src } => e.op_ba(0xCC, *dst, *src)?,
+            Self::MulDouble2Addr { dst, src } => e.op_ba(0xCD, *dst, *src)?,
+            Self::DivDouble2Addr { dst, src } => e.op_ba(0xCE, *dst, *src)?,
+            Self::RemDouble2Addr { dst, src } => e.op_ba(0xCF, *dst, *src)?,
+
+            // D0-D7: Binary operations with a 16-bit literal
+            Self::AddIntLit16 { dst, src, value }
+            | Self::RsubInt { dst, src, value }
+            | Self::MulIntLit16 { dst, src, value }
+            | Self::DivIntLit16 { dst, src, value }
+            | Self::RemIntLit16 { dst, src, value }
+            | Self::AndIntLit16 { dst, src, value }
+            | Self::OrIntLit16 { dst, src, value }
+            | Self::XorIntLit16 { dst, src, value } => {
+                let opcode = match self {
+                    Self::AddIntLit16 { .. } => 0xD0,
+                    Self::RsubInt { .. } => 0xD1,
+                    Self::MulIntLit16 { .. } => 0xD2,
+                    Self::DivIntLit16 { .. } => 0xD3,
+                    Self::RemIntLit16 { .. } => 0xD4,
+                    Self::AndIntLit16 { .. } => 0xD5,
+                    Self::OrIntLit16 { .. } => 0xD6,
+                    _ => 0xD7,
+                };
+                e.op_ba(opcode, *dst, *src)?;
+                e.u16(*value as u16);
+            }
+
+            // D8-E2: Binary operations with an 8-bit literal
+            Self::AddIntLit8 { dst, src, value } => e.op_23x(0xD8, *dst, *src, *value as u8),
+            Self::RsubIntLit8 { dst, src, value } => e.op_23x(0xD9, *dst, *src, *value as u8),
+            Self::MulIntLit8 { dst, src, value } => e.op_23x(0xDA, *dst, *src, *value as u8),
+            Self::DivIntLit8 { dst, src, value } => e.op_23x(0xDB, *dst, *src, *value as u8),
+            Self::RemIntLit8 { dst, src, value } => e.op_23x(0xDC, *dst, *src, *value as u8),
+            Self::AndIntLit8 { dst, src, value } => e.op_23x(0xDD, *dst, *src, *value as u8),
+            Self::OrIntLit8 { dst, src, value } => e.op_23x(0xDE, *dst, *src, *value as u8),
+            Self::XorIntLit8 { dst, src, value } => e.op_23x(0xDF, *dst, *src, *value as u8),
+            Self::ShlIntLit8 { dst, src, value } => e.op_23x(0xE0, *dst, *src, *value as u8),
+            Self::ShrIntLit8 { dst, src, value } => e.op_23x(0xE1, *dst, *src, *value as u8),
+            Self::UShrIntLit8 { dst, src, value } => e.op_23x(0xE2, *dst, *src, *value as u8),
+
+            // FA-FF: Method handles and call sites
+            Self::InvokePolymorphic {
+                method_idx,
+                proto_idx,
+                args,
+                arg_cnt,
+            } => {
+                e.op_35c(0xFA, *method_idx, args, *arg_cnt)?;
+                e.u16(*proto_idx);
+            }
+            Self::InvokePolymorphicRange {
+                method_idx,
+                proto_idx,
+                first_arg,
+                arg_cnt,
+            } => {
+                e.op_3rc(0xFB, *method_idx, *first_arg, *arg_cnt);
+                e.u16(*proto_idx);
+            }
+            Self::InvokeCustom {
+                call_site_idx,
+                args,
+                arg_cnt,
+            } => e.op_35c(0xFC, *call_site_idx, args, *arg_cnt)?,
+            Self::InvokeCustomRange {
+                call_site_idx,
+                first_arg,
+                arg_cnt,
+            } => e.op_3rc(0xFD, *call_site_idx, *first_arg, *arg_cnt),
+            Self::ConstMethodHandle {
+                dst,
+                method_handle_idx,
+            } => {
+                e.op_aa(0xFE, *dst);
+                e.u16(*method_handle_idx);
+            }
+            Self::ConstMethodType { dst, proto_idx } => {
+                e.op_aa(0xFF, *dst);
+                e.u16(*proto_idx);
+            }
+
+            // Payloads, identified by a `nop` with a non-zero high byte
+            Self::PackedSwitchPayload { first_key, targets } => {
+                let size = e.payload_size(targets.len())?;
+                e.u16(0x0100);
+                e.u16(size);
+                e.u32(*first_key as u32);
+                for target in targets {
+                    e.u32(*target as u32);
+                }
+            }
+            Self::SparseSwitchPayload { keys, targets } => {
+                if keys.len() != targets.len() {
+                    return Err(InstructionError::PayloadLength {
+                        payload: e.opcode,
+                        expected: keys.len(),
+                        actual: targets.len(),
+                    });
+                }
+                let size = e.payload_size(keys.len())?;
+                e.u16(0x0200);
+                e.u16(size);
+                for key in keys {
+                    e.u32(*key as u32);
+                }
+                for target in targets {
+                    e.u32(*target as u32);
+                }
+            }
+            Self::FillArrayDataPayload {
+                element_width,
+                size,
+                data,
+            } => {
+                let expected = *element_width as usize * *size as usize;
+                if data.len() != expected {
+                    return Err(InstructionError::PayloadLength {
+                        payload: e.opcode,
+                        expected,
+                        actual: data.len(),
+                    });
+                }
+                e.u16(0x0300);
+                e.u16(*element_width);
+                e.u32(*size);
+                e.out.extend_from_slice(data);
+                // The data is padded to a whole number of code units
+                if data.len() % 2 != 0 {
+                    e.u8(0x00);
+                }
+            }
+        }
+
+        debug_assert_eq!(e.out.len(), self.size_bytes());
+        Ok(e.out)
+    }
+}
@@ -1,11 +1,60 @@
-use crate::{dex::Dex, errors::TableIdxError};
+use crate::{
+    dex::{code_item::RenderContext, Dex},
+    errors::TableIdxError,
+    utils::hex_literal,
+};
 
 use super::Instruction;
 
+/// Renders a little-endian `fill-array-data` element of 1, 2, 4 or 8 bytes with the smali suffix for its width.
+fn array_element_to_human_readable(bytes: &[u8]) -> String {
+    let (value, suffix) = match bytes.len() {
+        1 => (bytes[0] as i8 as i64, "t"),
+        2 => (i16::from_le_bytes([bytes[0], bytes[1]]) as i64, "s"),
+        4 => (
+            i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64,
+            "",
+        ),
+        8 => (
+            i64::from_le_bytes([
+                bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+            ]),
+            "L",
+        ),
+        _ => {
+            let hex: String = bytes.iter().rev().map(|b| format!("{b:02x}")).collect();
+            return format!("0x{hex}");
+        }
+    };
+    hex_literal(value, suffix)
+}
+
 impl Instruction {
-    pub fn to_human_readable(&self, dex: &Dex) -> Result<String, TableIdxError> {
+    /// Renders the instruction as smali. `address` is the position of the instruction in 16-bit code units, which `context` resolves branch targets and payloads against. Registers are named by `context` as well.
+    ///
+    /// Payloads render as a multi-line directive block.
+    pub fn to_human_readable(
+        &self,
+        dex: &Dex,
+        address: u32,
+        context: &RenderContext,
+    ) -> Result<String, TableIdxError> {
         let mut out = String::from(self.opcode());
 
+        let label = |prefix: &'static str, offset: i32| context.label(prefix, address, offset);
+        // Switch targets are relative to the switch instruction rather than to its payload
+        let switch_label = |prefix: &'static str, offset: i32| match context.switch_address(address)
+        {
+            Some(switch_address) => context.label(prefix, switch_address, offset),
+            None => format!("{offset}"),
+        };
+
+        macro_rules! reg {
+            ($register:expr) => {
+                context.register(u16::from($register))
+            };
+        }
+
         macro_rules! pull_something {
             ($idx:expr, $table:expr, $err:ident) => {
                 if let Some(something) = $table.get($idx as usize) {
@@ -16,12 +65,6 @@ impl Instruction {
             };
         }
 
-        macro_rules! pull_string {
-            ($idx:expr) => {
-                pull_something!($idx, dex.strings, String)
-            };
-        }
-
         macro_rules! pull_type {
             ($idx:expr) => {
                 pull_something!($idx, dex.types, Type)
@@ -73,17 +116,17 @@ impl Instruction {
             | Self::IntToByte { dst, src }
             | Self::IntToChar { dst, src }
             | Self::IntToShort { dst, src } => {
-                format!("v{dst} v{src}")
+                format!("{} {}", reg!(*dst), reg!(*src))
             }
             Self::MoveFrom16 { dst, src }
             | Self::MoveWideFrom16 { dst, src }
             | Self::MoveObjectFrom16 { dst, src } => {
-                format!("v{dst} v{src}")
+                format!("{} {}", reg!(*dst), reg!(*src))
             }
             Self::Move16 { dst, src }
             | Self::MoveWide16 { dst, src }
             | Self::MoveObject16 { dst, src } => {
-                format!("v{dst} v{src}")
+                format!("{} {}", reg!(*dst), reg!(*src))
             }
             Self::MoveResult { dst }
             | Self::MoveResultWide { dst }
@@ -92,36 +135,40 @@ impl Instruction {
             | Self::MonitorEnter { reference: dst }
             | Self::MonitorExit { reference: dst }
             | Self::Throw { exception: dst } => {
-                format!("v{dst}")
+                reg!(*dst)
             }
             Self::Return { value } | Self::ReturnWide { value } | Self::ReturnObject { value } => {
-                format!("v{value}")
+                reg!(*value)
             }
             Self::Const4 { dst, value } => {
-                format!("v{dst} {value}")
+                format!("{} {value}", reg!(*dst))
             }
             Self::Const16 { dst, value }
             | Self::ConstHigh16 { dst, value }
             | Self::ConstWide16 { dst, value }
             | Self::ConstWideHigh16 { dst, value } => {
-                format!("v{dst} {value}")
+                format!("{} {value}", reg!(*dst))
             }
             Self::Const { dst, value } | Self::ConstWide32 { dst, value } => {
-                format!("v{dst} {value}")
+                format!("{} {value}", reg!(*dst))
             }
             Self::ConstWide { dst, value } => {
-                format!("v{dst} {value}")
+                format!("{} {value}", reg!(*dst))
             }
 
             Self::ConstString { dst, string_idx } => {
-                let idx = *string_idx as usize;
-                let string = pull_string!(idx);
-                format!("v{dst} \"{string}\"")
+                format!(
+                    "{} {}",
+                    reg!(*dst),
+                    dex.string_literal(*string_idx as usize)?
+                )
             }
             Self::ConstStringJumbo { dst, string_idx } => {
-                let idx = *string_idx as usize;
-                let string = pull_string!(idx);
-                format!("v{dst} \"{string}\"")
+                format!(
+                    "{} {}",
+                    reg!(*dst),
+                    dex.string_literal(*string_idx as usize)?
+                )
             }
             Self::ConstClass { dst, type_idx }
             | Self::CheckCast {
@@ -131,7 +178,7 @@ impl Instruction {
             | Self::NewInstance { dst, type_idx } => {
                 let idx = *type_idx as usize;
                 let t = pull_type!(idx);
-                format!("v{dst} {t}")
+                format!("{} {t}", reg!(*dst))
             }
             Self::InstanceOf {
                 dst,
@@ -140,7 +187,7 @@ impl Instruction {
             } => {
                 let idx = *type_idx as usize;
                 let t = pull_type!(idx);
-                format!("v{dst} v{reference} {t}")
+                format!("{} {} {t}", reg!(*dst), reg!(*reference))
             }
             Self::NewArray {
                 dst,
@@ -149,7 +196,7 @@ impl Instruction {
             } => {
                 let idx = *type_idx as usize;
                 let t = pull_type!(idx);
-                format!("v{dst} v{size} {t}")
+                format!("{} {} {t}", reg!(*dst), reg!(*size))
             }
             Self::FilledNewArray {
                 type_idx,
@@ -160,7 +207,7 @@ impl Instruction {
                 let mut args_str = String::new();
                 for i in 0..*arg_cnt {
                     if let Some(arg) = args.get(i as usize) {
-                        let local_arg = format!(" v{arg}");
+                        let local_arg = format!(" {}", reg!(*arg));
                         args_str.push_str(&local_arg);
                     }
                 }
@@ -176,7 +223,7 @@ impl Instruction {
                 let idx = *type_idx as usize;
                 let mut args_str = String::new();
                 for i in 0..*arg_cnt {
-                    let local_arg = format!(" v{}", *first_arg + i as u16);
+                    let local_arg = format!(" {}", reg!(*first_arg + i as u16));
                     args_str.push_str(&local_arg);
                 }
                 args_str = args_str.trim_start().to_string();
@@ -184,26 +231,23 @@ impl Instruction {
                 format!("{args_str} {t}")
             }
             Self::FillArrayData { array, offset } => {
-                format!("v{array} {offset}") // TODO: Use label?
+                format!("{} {}", reg!(*array), label("array", *offset))
             }
-            Self::Goto { offset } => {
-                format!("{offset}")
+            Self::Goto { offset } => label("goto", i32::from(*offset)),
+            Self::Goto16 { offset } => label("goto", i32::from(*offset)),
+            Self::Goto32 { offset } => label("goto", *offset),
+            Self::PackedSwitch { value, offset } => {
+                format!("{} {}", reg!(*value), label("pswitch_data", *offset))
             }
-            Self::Goto16 { offset } => {
-                format!("{offset}")
-            }
-            Self::Goto32 { offset } => {
-                format!("{offset}")
-            }
-            Self::PackedSwitch { value, offset } | Self::SparseSwitch { value, offset } => {
-                format!("v{value} {offset}") // TODO: Use label?
+            Self::SparseSwitch { value, offset } => {
+                format!("{} {}", reg!(*value), label("sswitch_data", *offset))
             }
             Self::CmplFloat { dst, src_a, src_b }
             | Self::CmpgFloat { dst, src_a, src_b }
             | Self::CmplDouble { dst, src_a, src_b }
             | Self::CmpgDouble { dst, src_a, src_b }
             | Self::CmpLong { dst, src_a, src_b } => {
-                format!("v{dst} v{src_a} v{src_b}")
+                format!("{} {} {}", reg!(*dst), reg!(*src_a), reg!(*src_b))
             }
             Self::IfEq { a, b, offset }
             | Self::IfNe { a, b, offset }
@@ -211,7 +255,12 @@ impl Instruction {
             | Self::IfGe { a, b, offset }
             | Self::IfGt { a, b, offset }
             | Self::IfLe { a, b, offset } => {
-                format!("v{a} v{b} {offset}") // TODO: Use label?
+                format!(
+                    "{} {} {}",
+                    reg!(*a),
+                    reg!(*b),
+                    label("cond", i32::from(*offset))
+                )
             }
             Self::IfEqz { a, offset }
             | Self::IfNez { a, offset }
@@ -219,7 +268,7 @@ impl Instruction {
             | Self::IfGez { a, offset }
             | Self::IfGtz { a, offset }
             | Self::IfLez { a, offset } => {
-                format!("v{a} {offset}") // TODO: Use label?
+                format!("{} {}", reg!(*a), label("cond", i32::from(*offset)))
             }
             Self::Aget { src, array, index }
             | Self::AgetWide { src, array, index }
@@ -263,7 +312,7 @@ impl Instruction {
                 array,
                 index,
             } => {
-                format!("v{src} v{array} v{index}")
+                format!("{} {} {}", reg!(*src), reg!(*array), reg!(*index))
             }
             Self::Iget {
                 src,
@@ -336,7 +385,9 @@ impl Instruction {
                 field_idx,
             } => {
                 format!(
-                    "v{src} v{object} {}",
+                    "{} {} {}",
+                    reg!(*src),
+                    reg!(*object),
                     pull_field!(*field_idx).to_human_readable(dex)?
                 )
             }
@@ -375,7 +426,11 @@ impl Instruction {
                 dst: src,
                 field_idx,
             } => {
-                format!("v{src} {}", pull_field!(*field_idx).to_human_readable(dex)?)
+                format!(
+                    "{} {}",
+                    reg!(*src),
+                    pull_field!(*field_idx).to_human_readable(dex)?
+                )
             }
             Self::InvokeVirtual {
                 method_idx,
@@ -407,7 +462,7 @@ impl Instruction {
                 let mut args_str = String::new();
                 for i in 0..*arg_cnt {
                     if let Some(arg) = args.get(i as usize) {
-                        let local_arg = format!(" v{arg}");
+                        let local_arg = format!(" {}", reg!(*arg));
                         args_str.push_str(&local_arg);
                     }
                 }
@@ -443,7 +498,7 @@ impl Instruction {
 
                 let mut args_str = String::new();
                 for i in 0..*arg_cnt {
-                    let local_arg = format!(" v{}", *first_arg + i as u16);
+                    let local_arg = format!(" {}", reg!(*first_arg + i as u16));
                     args_str.push_str(&local_arg);
                 }
                 args_str = args_str.trim_start().to_string();
@@ -481,7 +536,7 @@ impl Instruction {
             | Self::MulDouble { dst, src_a, src_b }
             | Self::DivDouble { dst, src_a, src_b }
             | Self::RemDouble { dst, src_a, src_b } => {
-                format!("v{dst} v{src_a} v{src_b}")
+                format!("{} {} {}", reg!(*dst), reg!(*src_a), reg!(*src_b))
             }
             Self::AddInt2Addr { dst, src }
             | Self::SubInt2Addr { dst, src }
@@ -515,7 +570,7 @@ impl Instruction {
             | Self::MulDouble2Addr { dst, src }
             | Self::DivDouble2Addr { dst, src }
             | Self::RemDouble2Addr { dst, src } => {
-                format!("v{dst} v{src}")
+                format!("{} {}", reg!(*dst), reg!(*src))
             }
             Self::AddIntLit16 { dst, src, value }
             | Self::RsubInt { dst, src, value }
@@ -525,7 +580,7 @@ impl Instruction {
             | Self::AndIntLit16 { dst, src, value }
             | Self::OrIntLit16 { dst, src, value }
             | Self::XorIntLit16 { dst, src, value } => {
-                format!("v{dst} v{src} {value}")
+                format!("{} {} {value}", reg!(*dst), reg!(*src))
             }
             Self::AddIntLit8 { dst, src, value }
             | Self::RsubIntLit8 { dst, src, value }
@@ -538,7 +593,7 @@ impl Instruction {
             | Self::ShlIntLit8 { dst, src, value }
             | Self::ShrIntLit8 { dst, src, value }
             | Self::UShrIntLit8 { dst, src, value } => {
-                format!("v{dst} v{src} {value}")
+                format!("{} {} {value}", reg!(*dst), reg!(*src))
             }
             Self::InvokePolymorphic {
                 method_idx,
@@ -552,7 +607,7 @@ impl Instruction {
                 let mut args_str = String::new();
                 for i in 0..*arg_cnt {
                     if let Some(arg) = args.get(i as usize) {
-                        let local_arg = format!(" v{arg}");
+                        let local_arg = format!(" {}", reg!(*arg));
                         args_str.push_str(&local_arg);
                     }
                 }
@@ -575,7 +630,7 @@ impl Instruction {
 
                 let mut args_str = String::new();
                 for i in 0..*arg_cnt {
-                    let local_arg = format!(" v{}", *first_arg + i as u16);
+                    let local_arg = format!(" {}", reg!(*first_arg + i as u16));
                     args_str.push_str(&local_arg);
                 }
                 args_str = args_str.trim_start().to_string();
@@ -591,32 +646,42 @@ impl Instruction {
                 args,
                 arg_cnt,
             } => {
-                let call_site = pull_something!(*call_site_idx, dex.call_site_items, CallSite);
+                let call_site = pull_something!(*call_site_idx, dex.call_site_items, CallSite)
+                    .as_ref()
+                    .ok_or(TableIdxError::CallSite(*call_site_idx as usize))?;
                 let mut args_str = String::new();
                 for i in 0..*arg_cnt {
                     if let Some(arg) = args.get(i as usize) {
-                        let local_arg = format!(" v{arg}");
+                        let local_arg = format!(" {}", reg!(*arg));
                         args_str.push_str(&local_arg);
                     }
                 }
                 args_str = args_str.trim_start().to_string();
-                format!("{args_str} {call_site:?}")
+                format!(
+                    "{args_str} {}",
+                    call_site.to_human_readable(*call_site_idx as usize, dex)?
+                )
             }
             Self::InvokeCustomRange {
                 call_site_idx,
                 first_arg,
                 arg_cnt,
             } => {
-                let call_site = pull_method!(*call_site_idx);
+                let call_site = pull_something!(*call_site_idx, dex.call_site_items, CallSite)
+                    .as_ref()
+                    .ok_or(TableIdxError::CallSite(*call_site_idx as usize))?;
 
                 let mut args_str = String::new();
                 for i in 0..*arg_cnt {
-                    let local_arg = format!(" v{}", *first_arg + i as u16);
+                    let local_arg = format!(" {}", reg!(*first_arg + i as u16));
                     args_str.push_str(&local_arg);
                 }
                 args_str = args_str.trim_start().to_string();
 
-                format!("{args_str} {}", call_site.to_human_readable(dex)?)
+                format!(
+                    "{args_str} {}",
+                    call_site.to_human_readable(*call_site_idx as usize, dex)?
+                )
             }
             Self::ConstMethodHandle {
                 dst,
@@ -624,11 +689,46 @@ impl Instruction {
             } => {
                 let method_handle =
                     pull_something!(*method_handle_idx, dex.method_handles, MethodHandle);
-                format!("v{dst} {method_handle:?}")
+                format!("{} {}", reg!(*dst), method_handle.to_human_readable(dex)?)
             }
             Self::ConstMethodType { dst, proto_idx } => {
                 let proto = pull_proto!(*proto_idx);
-                format!("v{dst} {}", proto.to_human_readable(dex)?)
+                format!("{} {}", reg!(*dst), proto.to_human_readable(dex)?)
+            }
+            Self::PackedSwitchPayload { first_key, targets } => {
+                let mut lines = vec![format!(
+                    ".packed-switch {}",
+                    hex_literal((*first_key).into(), "")
+                )];
+                for target in targets {
+                    lines.push(format!("    {}", switch_label("pswitch", *target)));
+                }
+                lines.push(String::from(".end packed-switch"));
+                return Ok(lines.join("\n"));
+            }
+            Self::SparseSwitchPayload { keys, targets } => {
+                let mut lines = vec![String::from(".sparse-switch")];
+                for (key, target) in keys.iter().zip(targets) {
+                    lines.push(format!(
+                        "    {} -> {}",
+                        hex_literal((*key).into(), ""),
+                        switch_label("sswitch", *target)
+                    ));
+                }
+                lines.push(String::from(".end sparse-switch"));
+                return Ok(lines.join("\n"));
+            }
+            Self::FillArrayDataPayload {
+                element_width,
+                data,
+                ..
+            } => {
+                let mut lines = vec![format!(".array-data {element_width}")];
+                for element in data.chunks((*element_width as usize).max(1)) {
+                    lines.push(format!("    {}", array_element_to_human_readable(element)));
+                }
+                lines.push(String::from(".end array-data"));
+                return Ok(lines.join("\n"));
             }
         };
 
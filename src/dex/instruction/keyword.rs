@@ -241,6 +241,10 @@ impl Instruction {
             Self::InvokeCustomRange { .. } => "invoke-custom/range",
             Self::ConstMethodHandle { .. } => "const-method-handle",
             Self::ConstMethodType { .. } => "const-method-type",
+
+            Self::PackedSwitchPayload { .. } => "packed-switch-payload",
+            Self::SparseSwitchPayload { .. } => "sparse-switch-payload",
+            Self::FillArrayDataPayload { .. } => "fill-array-data-payload",
         }
     }
 }
@@ -1,7 +1,11 @@
 mod decode;
+#[cfg(test)]
+mod encode;
 mod human_readable;
 mod keyword;
+pub mod operands;
 mod size;
+pub mod verify;
 
 #[cfg(test)]
 mod tests;
@@ -1022,4 +1026,21 @@ pub enum Instruction {
         dst: u8,
         proto_idx: u16,
     },
+
+    // 00 with a non-zero high byte: Payloads referenced by switches and array initializers
+    PackedSwitchPayload {
+        first_key: i32,
+        /// branch targets, relative to the address of the referencing `packed-switch`
+        targets: Vec<i32>,
+    },
+    SparseSwitchPayload {
+        keys: Vec<i32>,
+        /// branch targets, relative to the address of the referencing `sparse-switch`
+        targets: Vec<i32>,
+    },
+    FillArrayDataPayload {
+        element_width: u16,
+        size: u32,
+        data: Vec<u8>,
+    },
 }
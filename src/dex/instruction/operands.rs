@@ -0,0 +1,519 @@
+use super::Instruction;
+
+/// A register read or written by an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Register {
+    pub register: u16,
+    /// whether the operand is a `long` or `double` held in the pair `register`, `register + 1`
+    pub wide: bool,
+}
+
+/// The table an index operand points into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexKind {
+    String,
+    Type,
+    Field,
+    Method,
+    Proto,
+    CallSite,
+    MethodHandle,
+}
+
+impl IndexKind {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Type => "type",
+            Self::Field => "field",
+            Self::Method => "method",
+            Self::Proto => "proto",
+            Self::CallSite => "call site",
+            Self::MethodHandle => "method handle",
+        }
+    }
+}
+
+fn single(register: impl Into<u16>) -> Register {
+    Register {
+        register: register.into(),
+        wide: false,
+    }
+}
+
+fn wide(register: impl Into<u16>) -> Register {
+    Register {
+        register: register.into(),
+        wide: true,
+    }
+}
+
+/// The first `arg_cnt` registers of a 35c-format argument list
+fn arg_list(args: &[u8; 5], arg_cnt: u8) -> Vec<Register> {
+    args.iter()
+        .take(arg_cnt as usize)
+        .copied()
+        .map(single)
+        .collect()
+}
+
+/// The registers of a 3rc-format argument range
+fn arg_range(first_arg: u16, arg_cnt: u8) -> Vec<Register> {
+    (0..u16::from(arg_cnt))
+        .map(|i| single(first_arg.saturating_add(i)))
+        .collect()
+}
+
+impl Instruction {
+    /// Returns every register operand in the order they are encoded. Argument lists of invokes are reported as single registers, as their width depends on the prototype.
+    pub fn registers(&self) -> Vec<Register> {
+        match self {
+            Self::Nop
+            | Self::ReturnVoid
+            | Self::Goto { .. }
+            | Self::Goto16 { .. }
+            | Self::Goto32 { .. }
+            | Self::PackedSwitchPayload { .. }
+            | Self::SparseSwitchPayload { .. }
+            | Self::FillArrayDataPayload { .. } => Vec::new(),
+
+            Self::Move { dst, src } | Self::MoveObject { dst, src } => {
+                vec![single(*dst), single(*src)]
+            }
+            Self::MoveFrom16 { dst, src } | Self::MoveObjectFrom16 { dst, src } => {
+                vec![single(*dst), single(*src)]
+            }
+            Self::Move16 { dst, src } | Self::MoveObject16 { dst, src } => {
+                vec![single(*dst), single(*src)]
+            }
+            Self::MoveWide { dst, src } => vec![wide(*dst), wide(*src)],
+            Self::MoveWideFrom16 { dst, src } => vec![wide(*dst), wide(*src)],
+            Self::MoveWide16 { dst, src } => vec![wide(*dst), wide(*src)],
+
+            Self::MoveResult { dst }
+            | Self::MoveResultObject { dst }
+            | Self::MoveException { dst }
+            | Self::Const4 { dst, .. }
+            | Self::Const16 { dst, .. }
+            | Self::Const { dst, .. }
+            | Self::ConstHigh16 { dst, .. }
+            | Self::ConstString { dst, .. }
+            | Self::ConstStringJumbo { dst, .. }
+            | Self::ConstClass { dst, .. }
+            | Self::NewInstance { dst, .. }
+            | Self::ConstMethodHandle { dst, .. }
+            | Self::ConstMethodType { dst, .. } => vec![single(*dst)],
+            Self::MoveResultWide { dst }
+            | Self::ConstWide16 { dst, .. }
+            | Self::ConstWide32 { dst, .. }
+            | Self::ConstWide { dst, .. }
+            | Self::ConstWideHigh16 { dst, .. } => vec![wide(*dst)],
+
+            Self::Return { value } | Self::ReturnObject { value } => vec![single(*value)],
+            Self::ReturnWide { value } => vec![wide(*value)],
+            Self::MonitorEnter { reference }
+            | Self::MonitorExit { reference }
+            | Self::CheckCast { reference, .. } => vec![single(*reference)],
+            Self::Throw { exception } => vec![single(*exception)],
+            Self::FillArrayData { array, .. } => vec![single(*array)],
+            Self::PackedSwitch { value, .. } | Self::SparseSwitch { value, .. } => {
+                vec![single(*value)]
+            }
+            Self::InstanceOf { dst, reference, .. } => vec![single(*dst), single(*reference)],
+            Self::ArrayLength { dst, array } => vec![single(*dst), single(*array)],
+            Self::NewArray { dst, size, .. } => vec![single(*dst), single(*size)],
+
+            Self::FilledNewArray { args, arg_cnt, .. }
+            | Self::InvokeVirtual { args, arg_cnt, .. }
+            | Self::InvokeSuper { args, arg_cnt, .. }
+            | Self::InvokeDirect { args, arg_cnt, .. }
+            | Self::InvokeStatic { args, arg_cnt, .. }
+            | Self::InvokeInterface { args, arg_cnt, .. }
+            | Self::InvokePolymorphic { args, arg_cnt, .. }
+            | Self::InvokeCustom { args, arg_cnt, .. } => arg_list(args, *arg_cnt),
+            Self::FilledNewArrayRange {
+                first_arg, arg_cnt, ..
+            }
+            | Self::InvokeVirtualRange {
+                first_arg, arg_cnt, ..
+            }
+            | Self::InvokeSuperRange {
+                first_arg, arg_cnt, ..
+            }
+            | Self::InvokeDirectRange {
+                first_arg, arg_cnt, ..
+            }
+            | Self::InvokeStaticRange {
+                first_arg, arg_cnt, ..
+            }
+            | Self::InvokeInterfaceRange {
+                first_arg, arg_cnt, ..
+            }
+            | Self::InvokePolymorphicRange {
+                first_arg, arg_cnt, ..
+            }
+            | Self::InvokeCustomRange {
+                first_arg, arg_cnt, ..
+            } => arg_range(*first_arg, *arg_cnt),
+
+            Self::CmplFloat { dst, src_a, src_b } | Self::CmpgFloat { dst, src_a, src_b } => {
+                vec![single(*dst), single(*src_a), single(*src_b)]
+            }
+            Self::CmplDouble { dst, src_a, src_b }
+            | Self::CmpgDouble { dst, src_a, src_b }
+            | Self::CmpLong { dst, src_a, src_b } => {
+                vec![single(*dst), wide(*src_a), wide(*src_b)]
+            }
+
+            Self::IfEq { a, b, .. }
+            | Self::IfNe { a, b, .. }
+            | Self::IfLt { a, b, .. }
+            | Self::IfGe { a, b, .. }
+            | Self::IfGt { a, b, .. }
+            | Self::IfLe { a, b, .. } => vec![single(*a), single(*b)],
+            Self::IfEqz { a, .. }
+            | Self::IfNez { a, .. }
+            | Self::IfLtz { a, .. }
+            | Self::IfGez { a, .. }
+            | Self::IfGtz { a, .. }
+            | Self::IfLez { a, .. } => vec![single(*a)],
+
+            Self::Aget { src, array, index }
+            | Self::AgetObject { src, array, index }
+            | Self::AgetBoolean { src, array, index }
+            | Self::AgetByte { src, array, index }
+            | Self::AgetChar { src, array, index }
+            | Self::AgetShort { src, array, index } => {
+                vec![single(*src), single(*array), single(*index)]
+            }
+            Self::AgetWide { src, array, index } => {
+                vec![wide(*src), single(*array), single(*index)]
+            }
+            Self::Aput { dst, array, index }
+            | Self::AputObject { dst, array, index }
+            | Self::AputBoolean { dst, array, index }
+            | Self::AputByte { dst, array, index }
+            | Self::AputChar { dst, array, index }
+            | Self::AputShort { dst, array, index } => {
+                vec![single(*dst), single(*array), single(*index)]
+            }
+            Self::AputWide { dst, array, index } => {
+                vec![wide(*dst), single(*array), single(*index)]
+            }
+
+            Self::Iget { src, object, .. }
+            | Self::IgetObject { src, object, .. }
+            | Self::IgetBoolean { src, object, .. }
+            | Self::IgetByte { src, object, .. }
+            | Self::IgetChar { src, object, .. }
+            | Self::IgetShort { src, object, .. } => vec![single(*src), single(*object)],
+            Self::IgetWide { src, object, .. } => vec![wide(*src), single(*object)],
+            Self::Iput { dst, object, .. }
+            | Self::IputObject { dst, object, .. }
+            | Self::IputBoolean { dst, object, .. }
+            | Self::IputByte { dst, object, .. }
+            | Self::IputChar { dst, object, .. }
+            | Self::IputShort { dst, object, .. } => vec![single(*dst), single(*object)],
+            Self::IputWide { dst, object, .. } => vec![wide(*dst), single(*object)],
+            Self::Sget { src, .. }
+            | Self::SgetObject { src, .. }
+            | Self::SgetBoolean { src, .. }
+            | Self::SgetByte { src, .. }
+            | Self::SgetChar { src, .. }
+            | Self::SgetShort { src, .. } => vec![single(*src)],
+            Self::SgetWide { src, .. } => vec![wide(*src)],
+            Self::Sput { dst, .. }
+            | Self::SputObject { dst, .. }
+            | Self::SputBoolean { dst, .. }
+            | Self::SputByte { dst, .. }
+            | Self::SputChar { dst, .. }
+            | Self::SputShort { dst, .. } => vec![single(*dst)],
+            Self::SputWide { dst, .. } => vec![wide(*dst)],
+
+            Self::NegInt { dst, src }
+            | Self::NotInt { dst, src }
+            | Self::NegFloat { dst, src }
+            | Self::IntToFloat { dst, src }
+            | Self::FloatToInt { dst, src }
+            | Self::IntToByte { dst, src }
+            | Self::IntToChar { dst, src }
+            | Self::IntToShort { dst, src } => vec![single(*dst), single(*src)],
+            Self::NegLong { dst, src }
+            | Self::NotLong { dst, src }
+            | Self::NegDouble { dst, src }
+            | Self::LongToDouble { dst, src }
+            | Self::DoubleToLong { dst, src } => vec![wide(*dst), wide(*src)],
+            Self::IntToLong { dst, src }
+            | Self::IntToDouble { dst, src }
+            | Self::FloatToLong { dst, src }
+            | Self::FloatToDouble { dst, src } => vec![wide(*dst), single(*src)],
+            Self::LongToInt { dst, src }
+            | Self::LongToFloat { dst, src }
+            | Self::DoubleToInt { dst, src }
+            | Self::DoubleToFloat { dst, src } => vec![single(*dst), wide(*src)],
+
+            Self::AddInt { dst, src_a, src_b }
+            | Self::SubInt { dst, src_a, src_b }
+            | Self::MulInt { dst, src_a, src_b }
+            | Self::DivInt { dst, src_a, src_b }
+            | Self::RemInt { dst, src_a, src_b }
+            | Self::AndInt { dst, src_a, src_b }
+            | Self::OrInt { dst, src_a, src_b }
+            | Self::XorInt { dst, src_a, src_b }
+            | Self::ShlInt { dst, src_a, src_b }
+            | Self::ShrInt { dst, src_a, src_b }
+            | Self::UShrInt { dst, src_a, src_b }
+            | Self::AddFloat { dst, src_a, src_b }
+            | Self::SubFloat { dst, src_a, src_b }
+            | Self::MulFloat { dst, src_a, src_b }
+            | Self::DivFloat { dst, src_a, src_b }
+            | Self::RemFloat { dst, src_a, src_b } => {
+                vec![single(*dst), single(*src_a), single(*src_b)]
+            }
+            Self::AddLong { dst, src_a, src_b }
+            | Self::SubLong { dst, src_a, src_b }
+            | Self::MulLong { dst, src_a, src_b }
+            | Self::DivLong { dst, src_a, src_b }
+            | Self::RemLong { dst, src_a, src_b }
+            | Self::AndLong { dst, src_a, src_b }
+            | Self::OrLong { dst, src_a, src_b }
+            | Self::XorLong { dst, src_a, src_b }
+            | Self::AddDouble { dst, src_a, src_b }
+            | Self::SubDouble { dst, src_a, src_b }
+            | Self::MulDouble { dst, src_a, src_b }
+            | Self::DivDouble { dst, src_a, src_b }
+            | Self::RemDouble { dst, src_a, src_b } => {
+                vec![wide(*dst), wide(*src_a), wide(*src_b)]
+            }
+            // The shift distance is an int
+            Self::ShlLong { dst, src_a, src_b }
+            | Self::ShrLong { dst, src_a, src_b }
+            | Self::UShrLong { dst, src_a, src_b } => {
+                vec![wide(*dst), wide(*src_a), single(*src_b)]
+            }
+
+            Self::AddInt2Addr { dst, src }
+            | Self::SubInt2Addr { dst, src }
+            | Self::MulInt2Addr { dst, src }
+            | Self::DivInt2Addr { dst, src }
+            | Self::RemInt2Addr { dst, src }
+            | Self::AndInt2Addr { dst, src }
+            | Self::OrInt2Addr { dst, src }
+            | Self::XorInt2Addr { dst, src }
+            | Self::ShlInt2Addr { dst, src }
+            | Self::ShrInt2Addr { dst, src }
+            | Self::UShrInt2Addr { dst, src }
+            | Self::AddFloat2Addr { dst, src }
+            | Self::SubFloat2Addr { dst, src }
+            | Self::MulFloat2Addr { dst, src }
+            | Self::DivFloat2Addr { dst, src }
+            | Self::RemFloat2Addr { dst, src } => vec![single(*dst), single(*src)],
+            Self::AddLong2Addr { dst, src }
+            | Self::SubLong2Addr { dst, src }
+            | Self::MulLong2Addr { dst, src }
+            | Self::DivLong2Addr { dst, src }
+            | Self::RemLong2Addr { dst, src }
+            | Self::AndLong2Addr { dst, src }
+            | Self::OrLong2Addr { dst, src }
+            | Self::XorLong2Addr { dst, src }
+            | Self::AddDouble2Addr { dst, src }
+            | Self::SubDouble2Addr { dst, src }
+            | Self::MulDouble2Addr { dst, src }
+            | Self::DivDouble2Addr { dst, src }
+            | Self::RemDouble2Addr { dst, src } => vec![wide(*dst), wide(*src)],
+            Self::ShlLong2Addr { dst, src }
+            | Self::ShrLong2Addr { dst, src }
+            | Self::UShrLong2Addr { dst, src } => vec![wide(*dst), single(*src)],
+
+            Self::AddIntLit16 { dst, src, .. }
+            | Self::RsubInt { dst, src, .. }
+            | Self::MulIntLit16 { dst, src, .. }
+            | Self::DivIntLit16 { dst, src, .. }
+            | Self::RemIntLit16 { dst, src, .. }
+            | Self::AndIntLit16 { dst, src, .. }
+            | Self::OrIntLit16 { dst, src, .. }
+            | Self::XorIntLit16 { dst, src, .. }
+            | Self::AddIntLit8 { dst, src, .. }
+            | Self::RsubIntLit8 { dst, src, .. }
+            | Self::MulIntLit8 { dst, src, .. }
+            | Self::DivIntLit8 { dst, src, .. }
+            | Self::RemIntLit8 { dst, src, .. }
+            | Self::AndIntLit8 { dst, src, .. }
+            | Self::OrIntLit8 { dst, src, .. }
+            | Self::XorIntLit8 { dst, src, .. }
+            | Self::ShlIntLit8 { dst, src, .. }
+            | Self::ShrIntLit8 { dst, src, .. }
+            | Self::UShrIntLit8 { dst, src, .. } => vec![single(*dst), single(*src)],
+        }
+    }
+
+    /// Returns every index operand along with the table it points into.
+    pub fn indices(&self) -> Vec<(IndexKind, u32)> {
+        match self {
+            Self::ConstString { string_idx, .. } => {
+                vec![(IndexKind::String, u32::from(*string_idx))]
+            }
+            Self::ConstStringJumbo { string_idx, .. } => vec![(IndexKind::String, *string_idx)],
+            Self::ConstClass { type_idx, .. }
+            | Self::CheckCast { type_idx, .. }
+            | Self::InstanceOf { type_idx, .. }
+            | Self::NewInstance { type_idx, .. }
+            | Self::NewArray { type_idx, .. }
+            | Self::FilledNewArray { type_idx, .. }
+            | Self::FilledNewArrayRange { type_idx, .. } => {
+                vec![(IndexKind::Type, u32::from(*type_idx))]
+            }
+            Self::Iget { field_idx, .. }
+            | Self::IgetWide { field_idx, .. }
+            | Self::IgetObject { field_idx, .. }
+            | Self::IgetBoolean { field_idx, .. }
+            | Self::IgetByte { field_idx, .. }
+            | Self::IgetChar { field_idx, .. }
+            | Self::IgetShort { field_idx, .. }
+            | Self::Iput { field_idx, .. }
+            | Self::IputWide { field_idx, .. }
+            | Self::IputObject { field_idx, .. }
+            | Self::IputBoolean { field_idx, .. }
+            | Self::IputByte { field_idx, .. }
+            | Self::IputChar { field_idx, .. }
+            | Self::IputShort { field_idx, .. }
+            | Self::Sget { field_idx, .. }
+            | Self::SgetWide { field_idx, .. }
+            | Self::SgetObject { field_idx, .. }
+            | Self::SgetBoolean { field_idx, .. }
+            | Self::SgetByte { field_idx, .. }
+            | Self::SgetChar { field_idx, .. }
+            | Self::SgetShort { field_idx, .. }
+            | Self::Sput { field_idx, .. }
+            | Self::SputWide { field_idx, .. }
+            | Self::SputObject { field_idx, .. }
+            | Self::SputBoolean { field_idx, .. }
+            | Self::SputByte { field_idx, .. }
+            | Self::SputChar { field_idx, .. }
+            | Self::SputShort { field_idx, .. } => vec![(IndexKind::Field, u32::from(*field_idx))],
+            Self::InvokeVirtual { method_idx, .. }
+            | Self::InvokeSuper { method_idx, .. }
+            | Self::InvokeDirect { method_idx, .. }
+            | Self::InvokeStatic { method_idx, .. }
+            | Self::InvokeInterface { method_idx, .. }
+            | Self::InvokeVirtualRange { method_idx, .. }
+            | Self::InvokeSuperRange { method_idx, .. }
+            | Self::InvokeDirectRange { method_idx, .. }
+            | Self::InvokeStaticRange { method_idx, .. }
+            | Self::InvokeInterfaceRange { method_idx, .. } => {
+                vec![(IndexKind::Method, u32::from(*method_idx))]
+            }
+            Self::InvokePolymorphic {
+                method_idx,
+                proto_idx,
+                ..
+            }
+            | Self::InvokePolymorphicRange {
+                method_idx,
+                proto_idx,
+                ..
+            } => vec![
+                (IndexKind::Method, u32::from(*method_idx)),
+                (IndexKind::Proto, u32::from(*proto_idx)),
+            ],
+            Self::InvokeCustom { call_site_idx, .. }
+            | Self::InvokeCustomRange { call_site_idx, .. } => {
+                vec![(IndexKind::CallSite, u32::from(*call_site_idx))]
+            }
+            Self::ConstMethodHandle {
+                method_handle_idx, ..
+            } => vec![(IndexKind::MethodHandle, u32::from(*method_handle_idx))],
+            Self::ConstMethodType { proto_idx, .. } => {
+                vec![(IndexKind::Proto, u32::from(*proto_idx))]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the branch offset of a `goto` or `if-*`, in 16-bit code units relative to this instruction.
+    pub fn branch_offset(&self) -> Option<i32> {
+        match self {
+            Self::Goto { offset } => Some(i32::from(*offset)),
+            Self::Goto16 { offset } => Some(i32::from(*offset)),
+            Self::Goto32 { offset } => Some(*offset),
+            Self::IfEq { offset, .. }
+            | Self::IfNe { offset, .. }
+            | Self::IfLt { offset, .. }
+            | Self::IfGe { offset, .. }
+            | Self::IfGt { offset, .. }
+            | Self::IfLe { offset, .. }
+            | Self::IfEqz { offset, .. }
+            | Self::IfNez { offset, .. }
+            | Self::IfLtz { offset, .. }
+            | Self::IfGez { offset, .. }
+            | Self::IfGtz { offset, .. }
+            | Self::IfLez { offset, .. } => Some(i32::from(*offset)),
+            _ => None,
+        }
+    }
+
+    /// Returns the offset of the payload referenced by a switch or `fill-array-data`, in 16-bit code units relative to this instruction.
+    pub fn payload_offset(&self) -> Option<i32> {
+        match self {
+            Self::PackedSwitch { offset, .. }
+            | Self::SparseSwitch { offset, .. }
+            | Self::FillArrayData { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a switch or array payload, which holds data rather than code.
+    pub fn is_payload(&self) -> bool {
+        matches!(
+            self,
+            Self::PackedSwitchPayload { .. }
+                | Self::SparseSwitchPayload { .. }
+                | Self::FillArrayDataPayload { .. }
+        )
+    }
+
+    /// Whether execution may continue with the next instruction, i.e. this is neither an unconditional jump, a return nor a `throw`.
+    pub fn can_continue(&self) -> bool {
+        !matches!(
+            self,
+            Self::Goto { .. }
+                | Self::Goto16 { .. }
+                | Self::Goto32 { .. }
+                | Self::ReturnVoid
+                | Self::Return { .. }
+                | Self::ReturnWide { .. }
+                | Self::ReturnObject { .. }
+                | Self::Throw { .. }
+        ) && !self.is_payload()
+    }
+
+    /// Whether this instruction leaves a result for a following `move-result*`: every invoke and `filled-new-array`.
+    pub fn has_result(&self) -> bool {
+        matches!(
+            self,
+            Self::FilledNewArray { .. }
+                | Self::FilledNewArrayRange { .. }
+                | Self::InvokeVirtual { .. }
+                | Self::InvokeSuper { .. }
+                | Self::InvokeDirect { .. }
+                | Self::InvokeStatic { .. }
+                | Self::InvokeInterface { .. }
+                | Self::InvokeVirtualRange { .. }
+                | Self::InvokeSuperRange { .. }
+                | Self::InvokeDirectRange { .. }
+                | Self::InvokeStaticRange { .. }
+                | Self::InvokeInterfaceRange { .. }
+                | Self::InvokePolymorphic { .. }
+                | Self::InvokePolymorphicRange { .. }
+                | Self::InvokeCustom { .. }
+                | Self::InvokeCustomRange { .. }
+        )
+    }
+
+    pub fn is_move_result(&self) -> bool {
+        matches!(
+            self,
+            Self::MoveResult { .. } | Self::MoveResultWide { .. } | Self::MoveResultObject { .. }
+        )
+    }
+}
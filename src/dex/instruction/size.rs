@@ -8,13 +8,13 @@ impl Instruction {
             Instruction::Nop => 2,
             Instruction::Move { .. } => 2,
             Instruction::MoveFrom16 { .. } => 4,
-            Instruction::Move16 { .. } => 4,
+            Instruction::Move16 { .. } => 6,
             Instruction::MoveWide { .. } => 2,
             Instruction::MoveWideFrom16 { .. } => 4,
-            Instruction::MoveWide16 { .. } => 4,
+            Instruction::MoveWide16 { .. } => 6,
             Instruction::MoveObject { .. } => 2,
             Instruction::MoveObjectFrom16 { .. } => 4,
-            Instruction::MoveObject16 { .. } => 4,
+            Instruction::MoveObject16 { .. } => 6,
             Instruction::MoveResult { .. } => 2,
             Instruction::MoveResultWide { .. } => 2,
             Instruction::MoveResultObject { .. } => 2,
@@ -229,6 +229,13 @@ impl Instruction {
             Instruction::InvokeCustomRange { .. } => 6,
             Instruction::ConstMethodHandle { .. } => 4,
             Instruction::ConstMethodType { .. } => 4,
+            Instruction::PackedSwitchPayload { targets, .. } => 8 + targets.len() * 4,
+            Instruction::SparseSwitchPayload { keys, .. } => 4 + keys.len() * 8,
+            Instruction::FillArrayDataPayload {
+                element_width,
+                size,
+                ..
+            } => 8 + (*element_width as usize * *size as usize).div_ceil(2) * 2,
         }
     }
 }
@@ -1,9 +1,15 @@
 use super::*;
+use crate::{
+    dex::{code_item::CodeItem, fixture::DexBuilder, Dex},
+    diagnostics::Diagnostics,
+    errors::InstructionError,
+};
 
 fn assert_helper(buffer: &[u8], expected_inst: Instruction, expected_size: usize) {
     let inst = Instruction::try_decode(buffer).unwrap();
     assert_eq!(inst, expected_inst);
     assert_eq!(inst.size_bytes(), expected_size);
+    assert_eq!(inst.encode().unwrap(), &buffer[..expected_size]);
 }
 
 #[test]
@@ -24,6 +30,29 @@ fn test_move_from16() {
     assert_helper(&buffer, Instruction::MoveFrom16 { dst: 0, src: 25 }, 4);
 }
 
+/// Checks the decoder alone, for the fixes that the encoder round trip in [`assert_helper`] cannot tell apart from a matching encoder bug.
+fn assert_decodes(buffer: &[u8], expected_inst: Instruction, expected_size: usize) {
+    let inst = Instruction::try_decode(buffer).unwrap();
+    assert_eq!(inst, expected_inst);
+    assert_eq!(inst.size_bytes(), expected_size);
+    assert_eq!(
+        size::instruction_size_bytes(buffer[0]).unwrap(),
+        expected_size
+    );
+}
+
+#[test]
+fn test_move16() {
+    // The 32x format takes three code units, an opcode unit and a 16-bit unit per register
+    let buffer = [0x03, 0x00, 0x00, 0x01, 0x19, 0x00];
+    assert_decodes(&buffer, Instruction::Move16 { dst: 256, src: 25 }, 6);
+    let buffer = [0x06, 0x00, 0x02, 0x00, 0x00, 0x01];
+    assert_decodes(&buffer, Instruction::MoveWide16 { dst: 2, src: 256 }, 6);
+    let buffer = [0x09, 0x00, 0xFF, 0xFF, 0x00, 0x00];
+    assert_decodes(&buffer, Instruction::MoveObject16 { dst: 65535, src: 0 }, 6);
+    assert_helper(&buffer, Instruction::MoveObject16 { dst: 65535, src: 0 }, 6);
+}
+
 #[test]
 fn test_move_wide_from16() {
     let buffer = [0x05, 0x16, 0x00, 0x00];
@@ -100,6 +129,15 @@ fn test_const4() {
     assert_helper(&buffer, Instruction::Const4 { dst: 1, value: 2 }, 2);
 }
 
+#[test]
+fn test_const4_negative() {
+    // The literal is the signed high nibble
+    assert_decodes(&[0x12, 0xF0], Instruction::Const4 { dst: 0, value: -1 }, 2);
+    assert_decodes(&[0x12, 0x83], Instruction::Const4 { dst: 3, value: -8 }, 2);
+    assert_decodes(&[0x12, 0x74], Instruction::Const4 { dst: 4, value: 7 }, 2);
+    assert_helper(&[0x12, 0xF0], Instruction::Const4 { dst: 0, value: -1 }, 2);
+}
+
 #[test]
 fn test_const16() {
     let buffer = [0x13, 0x00, 0x0A, 0x00];
@@ -2379,3 +2417,365 @@ fn test_const_method_type() {
         4,
     );
 }
+
+#[test]
+fn test_packed_switch_payload() {
+    let buffer = [
+        0x00, 0x01, 0x02, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0xFE, 0xFF, 0xFF,
+        0xFF,
+    ];
+    assert_helper(
+        &buffer,
+        Instruction::PackedSwitchPayload {
+            first_key: 10,
+            targets: vec![5, -2],
+        },
+        16,
+    );
+}
+
+#[test]
+fn test_sparse_switch_payload() {
+    let buffer = [
+        0x00, 0x02, 0x02, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x64, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x00, 0x07, 0x00, 0x00, 0x00,
+    ];
+    assert_helper(
+        &buffer,
+        Instruction::SparseSwitchPayload {
+            keys: vec![-1, 100],
+            targets: vec![3, 7],
+        },
+        20,
+    );
+}
+
+#[test]
+fn test_fill_array_data_payload() {
+    // Three one-byte elements are padded to a whole number of code units
+    let buffer = [
+        0x00, 0x03, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x02, 0xFF, 0x00,
+    ];
+    assert_helper(
+        &buffer,
+        Instruction::FillArrayDataPayload {
+            element_width: 1,
+            size: 3,
+            data: vec![0x01, 0x02, 0xFF],
+        },
+        12,
+    );
+}
+
+#[test]
+fn test_truncated_payload() {
+    let buffer = [0x00, 0x01, 0x02, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x05, 0x00];
+    assert!(matches!(
+        Instruction::try_decode(&buffer),
+        Err(InstructionError::Size { expected: 16, .. })
+    ));
+}
+
+fn verify_helper(instructions: &[Instruction], registers_size: u16) -> Vec<(u32, String)> {
+    let mut address = 0;
+    let mut stream = Vec::new();
+    for insn in instructions {
+        stream.push((address, insn));
+        address += insn.size_bytes() as u32 / 2;
+    }
+    let tables = verify::TableSizes {
+        types: 2,
+        methods: 1,
+        ..Default::default()
+    };
+    verify::verify_instructions(&stream, registers_size, &tables)
+        .into_iter()
+        .map(|(address, e)| (address, e.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_verify_valid_method() {
+    let instructions = [
+        Instruction::InvokeStatic {
+            method_idx: 0,
+            args: [1, 0, 0, 0, 0],
+            arg_cnt: 1,
+        },
+        Instruction::MoveResultWide { dst: 0 },
+        Instruction::PackedSwitch {
+            value: 1,
+            offset: 6,
+        },
+        Instruction::Goto { offset: 1 },
+        Instruction::ReturnVoid,
+        Instruction::Nop,
+        Instruction::PackedSwitchPayload {
+            first_key: 0,
+            targets: vec![3, 4],
+        },
+    ];
+    assert_eq!(verify_helper(&instructions, 2), []);
+}
+
+#[test]
+fn test_verify_registers() {
+    let instructions = [
+        Instruction::Move { dst: 2, src: 0 },
+        Instruction::ConstWide16 { dst: 1, value: 0 },
+        Instruction::InvokeStaticRange {
+            method_idx: 0,
+            first_arg: 1,
+            arg_cnt: 3,
+        },
+        Instruction::ReturnVoid,
+    ];
+    assert_eq!(
+        verify_helper(&instructions, 2),
+        [
+            (
+                0,
+                "Register v2 is out of range, the method has 2 registers".to_string()
+            ),
+            (
+                1,
+                "Wide register pair v1/v2 is out of range, the method has 2 registers".to_string()
+            ),
+            (
+                3,
+                "Register v2 is out of range, the method has 2 registers".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_verify_control_flow() {
+    let instructions = [
+        Instruction::MoveResult { dst: 0 },
+        Instruction::IfEqz { a: 0, offset: 0 },
+        Instruction::Goto16 { offset: 1 },
+        Instruction::FillArrayData {
+            array: 0,
+            offset: 2,
+        },
+        Instruction::Nop,
+    ];
+    assert_eq!(
+        verify_helper(&instructions, 1),
+        [
+            (
+                0,
+                "move-result does not immediately follow an invoke or filled-new-array".to_string()
+            ),
+            (1, "Branch offset of zero".to_string()),
+            (
+                3,
+                "Branch target 0x4 is not the start of an instruction".to_string()
+            ),
+            (
+                5,
+                "Payload target 0x7 is not a fill-array-data-payload".to_string()
+            ),
+            (
+                8,
+                "Execution can continue past the end of the code".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_verify_indices() {
+    let instructions = [
+        Instruction::NewInstance {
+            dst: 0,
+            type_idx: 2,
+        },
+        Instruction::ConstString {
+            dst: 0,
+            string_idx: 0,
+        },
+        Instruction::ReturnVoid,
+    ];
+    assert_eq!(
+        verify_helper(&instructions, 1),
+        [
+            (
+                0,
+                "type index 2 is out of range, there are 2 entries".to_string()
+            ),
+            (
+                2,
+                "string index 0 is out of range, there are 0 entries".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_wide_operands() {
+    let insn = Instruction::ShlLong {
+        dst: 0,
+        src_a: 2,
+        src_b: 4,
+    };
+    let wide: Vec<bool> = insn.registers().iter().map(|r| r.wide).collect();
+    // The shift distance is an int even for long shifts
+    assert_eq!(wide, [true, true, false]);
+    assert_eq!(insn.branch_offset(), None);
+    assert!(!Instruction::Throw { exception: 0 }.can_continue());
+}
+
+#[test]
+fn test_round_trip_every_opcode() {
+    let mut encoded = 0;
+    for opcode in 0..=u8::MAX {
+        let Ok(size) = size::instruction_size_bytes(opcode) else {
+            continue;
+        };
+        // Operands of all zeroes, all ones and a mix of both, to cover nibble packing and sign handling
+        for pattern in [[0x00, 0x00], [0xFF, 0xFF], [0x21, 0xF3], [0x5A, 0x8C]] {
+            let mut buffer = vec![opcode];
+            buffer.extend((1..size).map(|i| pattern[i % 2]));
+            let inst = Instruction::try_decode(&buffer).unwrap();
+            let bytes = inst.encode().unwrap();
+            assert_eq!(bytes.len(), inst.size_bytes());
+            assert_eq!(bytes[0], opcode);
+            assert_eq!(Instruction::try_decode(&bytes).unwrap(), inst);
+        }
+        encoded += 1;
+    }
+    // 0x00-0xFF minus the unused 3E-43, 73, 79-7A, E3-F9
+    assert_eq!(encoded, 256 - 6 - 1 - 2 - 23);
+}
+
+#[test]
+fn test_encode_operand_range() {
+    let errors = [
+        Instruction::Move { dst: 16, src: 0 }.encode(),
+        Instruction::Const4 { dst: 0, value: 8 }.encode(),
+        Instruction::InvokeStatic {
+            method_idx: 0,
+            args: [0, 17, 0, 0, 0],
+            arg_cnt: 2,
+        }
+        .encode(),
+    ];
+    let messages: Vec<String> = errors
+        .into_iter()
+        .map(|e| e.unwrap_err().to_string())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "Operand 16 of move does not fit in 4 bits",
+            "Operand 8 of const/4 does not fit in 4 bits",
+            "Operand 17 of invoke-static does not fit in 4 bits",
+        ]
+    );
+}
+
+#[test]
+fn test_encode_payload_length() {
+    let sparse = Instruction::SparseSwitchPayload {
+        keys: vec![1, 2],
+        targets: vec![3],
+    };
+    assert!(matches!(
+        sparse.encode(),
+        Err(InstructionError::PayloadLength {
+            expected: 2,
+            actual: 1,
+            ..
+        })
+    ));
+    let fill = Instruction::FillArrayDataPayload {
+        element_width: 4,
+        size: 2,
+        data: vec![0; 4],
+    };
+    assert!(matches!(
+        fill.encode(),
+        Err(InstructionError::PayloadLength {
+            expected: 8,
+            actual: 4,
+            ..
+        })
+    ));
+}
+
+/// Disassembles the method body `insns` the way smali files are written, one `(address, line)` per instruction.
+fn disassemble(insns: &[u8]) -> Vec<(u32, String)> {
+    let file = DexBuilder::new().build();
+    let diagnostics = Diagnostics::new(true);
+    let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+    let mut buffer = vec![0x00, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    buffer.extend((insns.len() as u32 / 2).to_le_bytes());
+    buffer.extend(insns);
+    let code_item = CodeItem::try_parse_from_bytes_unsized(&buffer).unwrap();
+    let context = code_item.render_context(false);
+    code_item
+        .instructions()
+        .map(|(address, insn)| {
+            let line = insn.to_human_readable(&dex, address, &context).unwrap();
+            (address, line)
+        })
+        .collect()
+}
+
+#[test]
+fn test_const4_disassembly() {
+    // The literal is the signed high nibble, so 0xF is -1 rather than 15
+    let lines = disassemble(&[0x12, 0xF0, 0x12, 0x71, 0x12, 0x82]);
+    assert_eq!(
+        lines,
+        [
+            (0, String::from("const/4 v0 -1")),
+            (1, String::from("const/4 v1 7")),
+            (2, String::from("const/4 v2 -8")),
+        ]
+    );
+}
+
+#[test]
+fn test_move16_disassembly() {
+    // move/16 takes three code units, so the next instruction starts at address 3
+    let lines = disassemble(&[
+        0x03, 0x00, 0x00, 0x01, 0x19, 0x00, // move/16 v256, v25
+        0x06, 0x00, 0x02, 0x00, 0x00, 0x01, // move-wide/16 v2, v256
+        0x09, 0x00, 0xFF, 0xFF, 0x00, 0x00, // move-object/16 v65535, v0
+        0x0E, 0x00, // return-void
+    ]);
+    let addresses: Vec<_> = lines.iter().map(|(address, _)| *address).collect();
+    assert_eq!(addresses, [0, 3, 6, 9]);
+    assert_eq!(lines[0].1, "move/16 v256 v25");
+    assert_eq!(lines[1].1, "move-wide/16 v2 v256");
+    assert_eq!(lines[2].1, "move-object/16 v65535 v0");
+}
+
+#[test]
+fn test_negative_switch_keys() {
+    #[rustfmt::skip]
+    let lines = disassemble(&[
+        0x2b, 0x00, 0x08, 0x00, 0x00, 0x00, // 0: packed-switch v0, +8
+        0x2c, 0x00, 0x0b, 0x00, 0x00, 0x00, // 3: sparse-switch v0, +11
+        0x0e, 0x00, // 6: return-void
+        0x00, 0x00, // 7: nop, aligning the payloads
+        0x00, 0x01, 0x01, 0x00, // 8: packed-switch-payload, 1 target
+        0xff, 0xff, 0xff, 0xff, // first_key -1
+        0x06, 0x00, 0x00, 0x00,
+        0x00, 0x02, 0x02, 0x00, // 14: sparse-switch-payload, 2 targets
+        0x00, 0x00, 0x00, 0x80, 0x10, 0x00, 0x00, 0x00, // keys i32::MIN and 0x10
+        0x03, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    ]);
+    assert_eq!(
+        lines[4].1,
+        ".packed-switch -0x1\n    :pswitch_0\n.end packed-switch"
+    );
+    assert_eq!(
+        lines[5].1,
+        ".sparse-switch\n    -0x80000000 -> :sswitch_0\n    0x10 -> :sswitch_0\n.end sparse-switch"
+    );
+}
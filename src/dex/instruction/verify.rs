@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use crate::{dex::Dex, errors::BytecodeError};
+
+use super::{operands::IndexKind, Instruction};
+
+/// The number of entries in each table an index operand may point into.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TableSizes {
+    pub strings: usize,
+    pub types: usize,
+    pub fields: usize,
+    pub methods: usize,
+    pub protos: usize,
+    pub call_sites: usize,
+    pub method_handles: usize,
+}
+
+impl TableSizes {
+    pub fn of(dex: &Dex) -> Self {
+        Self {
+            strings: dex.strings.len(),
+            types: dex.types.len(),
+            fields: dex.field_ids.len(),
+            methods: dex.method_ids.len(),
+            protos: dex.proto_ids.len(),
+            call_sites: dex.call_site_items.len(),
+            method_handles: dex.method_handles.len(),
+        }
+    }
+
+    fn size(&self, kind: IndexKind) -> usize {
+        match kind {
+            IndexKind::String => self.strings,
+            IndexKind::Type => self.types,
+            IndexKind::Field => self.fields,
+            IndexKind::Method => self.methods,
+            IndexKind::Proto => self.protos,
+            IndexKind::CallSite => self.call_sites,
+            IndexKind::MethodHandle => self.method_handles,
+        }
+    }
+}
+
+/// Returns the payload a switch or `fill-array-data` must point at.
+fn expected_payload(insn: &Instruction) -> &'static str {
+    match insn {
+        Instruction::PackedSwitch { .. } => "packed-switch-payload",
+        Instruction::SparseSwitch { .. } => "sparse-switch-payload",
+        _ => "fill-array-data-payload",
+    }
+}
+
+/// Checks a method body, given as instructions along with their address in 16-bit code units, against the constraints ART's method verifier enforces before any type checking. Returns every violation along with the address of the offending instruction.
+///
+/// - register operands must be below `registers_size`, and so must the second half of wide pairs. Only the first bad register of an instruction is reported.
+/// - branch targets must be the start of an instruction, switch and `fill-array-data` targets a 4-byte aligned payload of the right kind, and switch payload targets the start of an instruction
+/// - execution must not continue past the last instruction or into a payload, except from the `nop` padding that aligns the payload
+/// - `move-result*` must immediately follow an invoke or `filled-new-array`
+/// - index operands must be in range of `tables`
+pub fn verify_instructions(
+    instructions: &[(u32, &Instruction)],
+    registers_size: u16,
+    tables: &TableSizes,
+) -> Vec<(u32, BytecodeError)> {
+    let by_address: BTreeMap<u32, &Instruction> = instructions.iter().copied().collect();
+    let instruction_at = |target: i64| {
+        u32::try_from(target)
+            .ok()
+            .and_then(|target| by_address.get(&target))
+            .copied()
+    };
+    let is_code = |target: i64| instruction_at(target).is_some_and(|insn| !insn.is_payload());
+
+    let mut violations = Vec::new();
+    for (i, (address, insn)) in instructions.iter().copied().enumerate() {
+        // Payloads are data and only checked through the instruction referencing them
+        if insn.is_payload() {
+            continue;
+        }
+        let mut report = |e: BytecodeError| violations.push((address, e));
+
+        let bad_register = insn.registers().into_iter().find(|register| {
+            u32::from(register.register) + u32::from(register.wide) >= u32::from(registers_size)
+        });
+        if let Some(register) = bad_register {
+            let register_num = u32::from(register.register);
+            report(
+                if register.wide && register_num < u32::from(registers_size) {
+                    BytecodeError::WidePairOutOfRange {
+                        register: register_num,
+                        registers_size,
+                    }
+                } else {
+                    BytecodeError::RegisterOutOfRange {
+                        register: register_num,
+                        registers_size,
+                    }
+                },
+            );
+        }
+
+        if let Some(offset) = insn.branch_offset() {
+            let target = i64::from(address) + i64::from(offset);
+            // Only goto/32 may branch to itself
+            if offset == 0 && !matches!(insn, Instruction::Goto32 { .. }) {
+                report(BytecodeError::ZeroBranchOffset);
+            } else if !is_code(target) {
+                report(BytecodeError::BranchTarget { target });
+            }
+        }
+
+        if let Some(offset) = insn.payload_offset() {
+            let target = i64::from(address) + i64::from(offset);
+            let expected = expected_payload(insn);
+            match instruction_at(target) {
+                Some(payload) if payload.opcode() == expected => {
+                    if target % 2 != 0 {
+                        report(BytecodeError::MisalignedPayload { target });
+                    }
+                    let switch_targets = match payload {
+                        Instruction::PackedSwitchPayload { targets, .. }
+                        | Instruction::SparseSwitchPayload { targets, .. } => targets.as_slice(),
+                        _ => &[],
+                    };
+                    for switch_target in switch_targets {
+                        let target = i64::from(address) + i64::from(*switch_target);
+                        if !is_code(target) {
+                            report(BytecodeError::BranchTarget { target });
+                        }
+                    }
+                }
+                _ => report(BytecodeError::PayloadTarget { target, expected }),
+            }
+        }
+
+        if insn.can_continue() {
+            match instructions.get(i + 1) {
+                None => report(BytecodeError::FallsOffEnd),
+                // Compilers align payloads with a `nop` that is never reached
+                Some((next_address, next))
+                    if next.is_payload() && !matches!(insn, Instruction::Nop) =>
+                {
+                    report(BytecodeError::FallsIntoPayload {
+                        address: *next_address,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        if insn.is_move_result() {
+            let previous = i.checked_sub(1).and_then(|i| instructions.get(i));
+            if !previous.is_some_and(|(_, previous)| previous.has_result()) {
+                report(BytecodeError::MisplacedMoveResult {
+                    opcode: insn.opcode(),
+                });
+            }
+        }
+
+        for (kind, idx) in insn.indices() {
+            let size = tables.size(kind);
+            if idx as usize >= size {
+                report(BytecodeError::IndexOutOfRange {
+                    kind: kind.name(),
+                    idx,
+                    size,
+                });
+            }
+        }
+    }
+    violations
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+
+use crate::{
+    errors::Error,
+    traits::parse::TryParseFromBytes,
+    utils::{read_u16_le, read_u32_le, Reader},
+};
+
+/// https://source.android.com/docs/core/runtime/dex-format#type-codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MapItemType {
+    HeaderItem,
+    StringIdItem,
+    TypeIdItem,
+    ProtoIdItem,
+    FieldIdItem,
+    MethodIdItem,
+    ClassDefItem,
+    CallSiteIdItem,
+    MethodHandleItem,
+    MapList,
+    TypeList,
+    AnnotationSetRefList,
+    AnnotationSetItem,
+    ClassDataItem,
+    CodeItem,
+    StringDataItem,
+    DebugInfoItem,
+    AnnotationItem,
+    EncodedArrayItem,
+    AnnotationsDirectoryItem,
+    HiddenapiClassDataItem,
+    /// A type code not described by the specification
+    Unknown(u16),
+}
+
+impl MapItemType {
+    /// Returns the name of the item as used in the specification, e.g. `string_id_item`.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::HeaderItem => "header_item",
+            Self::StringIdItem => "string_id_item",
+            Self::TypeIdItem => "type_id_item",
+            Self::ProtoIdItem => "proto_id_item",
+            Self::FieldIdItem => "field_id_item",
+            Self::MethodIdItem => "method_id_item",
+            Self::ClassDefItem => "class_def_item",
+            Self::CallSiteIdItem => "call_site_id_item",
+            Self::MethodHandleItem => "method_handle_item",
+            Self::MapList => "map_list",
+            Self::TypeList => "type_list",
+            Self::AnnotationSetRefList => "annotation_set_ref_list",
+            Self::AnnotationSetItem => "annotation_set_item",
+            Self::ClassDataItem => "class_data_item",
+            Self::CodeItem => "code_item",
+            Self::StringDataItem => "string_data_item",
+            Self::DebugInfoItem => "debug_info_item",
+            Self::AnnotationItem => "annotation_item",
+            Self::EncodedArrayItem => "encoded_array_item",
+            Self::AnnotationsDirectoryItem => "annotations_directory_item",
+            Self::HiddenapiClassDataItem => "hiddenapi_class_data_item",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+    /// Returns the size of one item of this type, or `None` for variable-size items. The header and `map_list` depend on the file and are `None` as well.
+    pub const fn item_size(&self) -> Option<usize> {
+        match self {
+            Self::StringIdItem | Self::TypeIdItem | Self::CallSiteIdItem => Some(4),
+            Self::FieldIdItem | Self::MethodIdItem | Self::MethodHandleItem => Some(8),
+            Self::ProtoIdItem => Some(12),
+            Self::ClassDefItem => Some(32),
+            _ => None,
+        }
+    }
+
+    /// Returns the alignment the specification requires of items of this type, in bytes.
+    pub const fn alignment(&self) -> usize {
+        match self {
+            Self::ClassDataItem
+            | Self::StringDataItem
+            | Self::DebugInfoItem
+            | Self::AnnotationItem
+            | Self::EncodedArrayItem
+            | Self::Unknown(_) => 1,
+            _ => 4,
+        }
+    }
+}
+
+impl From<u16> for MapItemType {
+    fn from(value: u16) -> Self {
+        match value {
+            0x0000 => Self::HeaderItem,
+            0x0001 => Self::StringIdItem,
+            0x0002 => Self::TypeIdItem,
+            0x0003 => Self::ProtoIdItem,
+            0x0004 => Self::FieldIdItem,
+            0x0005 => Self::MethodIdItem,
+            0x0006 => Self::ClassDefItem,
+            0x0007 => Self::CallSiteIdItem,
+            0x0008 => Self::MethodHandleItem,
+            0x1000 => Self::MapList,
+            0x1001 => Self::TypeList,
+            0x1002 => Self::AnnotationSetRefList,
+            0x1003 => Self::AnnotationSetItem,
+            0x2000 => Self::ClassDataItem,
+            0x2001 => Self::CodeItem,
+            0x2002 => Self::StringDataItem,
+            0x2003 => Self::DebugInfoItem,
+            0x2004 => Self::AnnotationItem,
+            0x2005 => Self::EncodedArrayItem,
+            0x2006 => Self::AnnotationsDirectoryItem,
+            0xF000 => Self::HiddenapiClassDataItem,
+            unknown => Self::Unknown(unknown),
+        }
+    }
+}
+
+impl From<MapItemType> for u16 {
+    fn from(value: MapItemType) -> Self {
+        match value {
+            MapItemType::HeaderItem => 0x0000,
+            MapItemType::StringIdItem => 0x0001,
+            MapItemType::TypeIdItem => 0x0002,
+            MapItemType::ProtoIdItem => 0x0003,
+            MapItemType::FieldIdItem => 0x0004,
+            MapItemType::MethodIdItem => 0x0005,
+            MapItemType::ClassDefItem => 0x0006,
+            MapItemType::CallSiteIdItem => 0x0007,
+            MapItemType::MethodHandleItem => 0x0008,
+            MapItemType::MapList => 0x1000,
+            MapItemType::TypeList => 0x1001,
+            MapItemType::AnnotationSetRefList => 0x1002,
+            MapItemType::AnnotationSetItem => 0x1003,
+            MapItemType::ClassDataItem => 0x2000,
+            MapItemType::CodeItem => 0x2001,
+            MapItemType::StringDataItem => 0x2002,
+            MapItemType::DebugInfoItem => 0x2003,
+            MapItemType::AnnotationItem => 0x2004,
+            MapItemType::EncodedArrayItem => 0x2005,
+            MapItemType::AnnotationsDirectoryItem => 0x2006,
+            MapItemType::HiddenapiClassDataItem => 0xF000,
+            MapItemType::Unknown(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for MapItemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(value) => write!(f, "unknown({value:#06x})"),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#map-item
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct MapItem {
+    /// type of the items; see table above
+    pub type_: MapItemType,
+    /// (unused)
+    pub unused: u16,
+    /// count of the number of items to be found at the indicated offset
+    pub size: u32,
+    /// offset from the start of the file to the items in question
+    pub offset: u32,
+}
+
+impl TryParseFromBytes for MapItem {
+    const NAME: &'static str = "map_item";
+    const SIZE: usize = 12;
+
+    fn parse_from_bytes(buffer: &[u8]) -> Self {
+        let type_ = MapItemType::from(read_u16_le(buffer, 0));
+        let unused = read_u16_le(buffer, 2);
+        let size = read_u32_le(buffer, 4);
+        let offset = read_u32_le(buffer, 8);
+        Self {
+            type_,
+            unused,
+            size,
+            offset,
+        }
+    }
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#map-list
+#[derive(Debug, Default)]
+pub struct MapList {
+    /// elements of the list
+    pub list: Vec<MapItem>,
+}
+
+#[allow(unused)]
+impl MapList {
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(buffer, "map_list");
+        let size = reader.u32()? as usize;
+        let items = reader.bytes(size.saturating_mul(MapItem::SIZE))?;
+        let list = items
+            .chunks_exact(MapItem::SIZE)
+            .map(MapItem::parse_from_bytes)
+            .collect();
+
+        Ok(Self { list })
+    }
+
+    /// Returns the map entry describing the section holding items of type `type_`, if the file has one.
+    pub fn get(&self, type_: MapItemType) -> Option<&MapItem> {
+        self.list.iter().find(|item| item.type_ == type_)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorKind;
+
+    #[test]
+    fn test_map_list() {
+        // A string_id_item entry followed by one with an unknown type code
+        let buffer = [
+            0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x70, 0x00,
+            0x00, 0x00, 0x34, 0x12, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        ];
+        let map_list = MapList::try_parse_from_bytes_unsized(&buffer).unwrap();
+        assert_eq!(map_list.list.len(), 2);
+        let strings = map_list.get(MapItemType::StringIdItem).unwrap();
+        assert_eq!((strings.size, strings.offset), (3, 0x70));
+        assert_eq!(map_list.list[1].type_, MapItemType::Unknown(0x1234));
+        assert_eq!(map_list.list[1].type_.to_string(), "unknown(0x1234)");
+        assert!(map_list.get(MapItemType::CodeItem).is_none());
+    }
+
+    #[test]
+    fn test_map_item_type_round_trip() {
+        for value in [0x0000, 0x0008, 0x1003, 0x2006, 0xF000, 0x0009] {
+            assert_eq!(u16::from(MapItemType::from(value)), value);
+        }
+    }
+
+    #[test]
+    fn test_truncated_map_list() {
+        // Declares two items but only holds one
+        let mut buffer = vec![0x02, 0x00, 0x00, 0x00];
+        buffer.extend([0; 12]);
+        let e = MapList::try_parse_from_bytes_unsized(&buffer).unwrap_err();
+        assert_eq!(e.structure, "map_list");
+        assert_eq!(e.offset, 4);
+        assert!(matches!(
+            e.kind,
+            ErrorKind::UnexpectedEof {
+                needed: 24,
+                available: 12
+            }
+        ));
+    }
+}
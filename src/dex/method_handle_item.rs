@@ -1,20 +1,123 @@
-use crate::{traits::parse::TryParseFromBytes, utils::read_u16_le};
+use std::fmt;
+
+use crate::{errors::TableIdxError, traits::parse::TryParseFromBytes, utils::read_u16_le};
+
+use super::Dex;
+
+/// https://source.android.com/docs/core/runtime/dex-format#method-handle-type-codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodHandleType {
+    /// Method handle is a static field setter (accessor)
+    StaticPut,
+    /// Method handle is a static field getter (accessor)
+    StaticGet,
+    /// Method handle is an instance field setter (accessor)
+    InstancePut,
+    /// Method handle is an instance field getter (accessor)
+    InstanceGet,
+    /// Method handle is a static method invoker
+    InvokeStatic,
+    /// Method handle is an instance method invoker
+    InvokeInstance,
+    /// Method handle is a constructor method invoker
+    InvokeConstructor,
+    /// Method handle is a direct method invoker
+    InvokeDirect,
+    /// Method handle is an interface method invoker
+    InvokeInterface,
+    /// A type code not described by the specification
+    Unknown(u16),
+}
+
+impl MethodHandleType {
+    /// Returns `true` if `field_or_method_id` is an index into the `field_ids` list rather than the `method_ids` list.
+    pub const fn is_field_accessor(&self) -> bool {
+        matches!(
+            self,
+            Self::StaticPut | Self::StaticGet | Self::InstancePut | Self::InstanceGet
+        )
+    }
+}
+
+impl From<u16> for MethodHandleType {
+    fn from(value: u16) -> Self {
+        match value {
+            0x00 => Self::StaticPut,
+            0x01 => Self::StaticGet,
+            0x02 => Self::InstancePut,
+            0x03 => Self::InstanceGet,
+            0x04 => Self::InvokeStatic,
+            0x05 => Self::InvokeInstance,
+            0x06 => Self::InvokeConstructor,
+            0x07 => Self::InvokeDirect,
+            0x08 => Self::InvokeInterface,
+            unknown => Self::Unknown(unknown),
+        }
+    }
+}
+
+impl fmt::Display for MethodHandleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::StaticPut => "static-put",
+            Self::StaticGet => "static-get",
+            Self::InstancePut => "instance-put",
+            Self::InstanceGet => "instance-get",
+            Self::InvokeStatic => "invoke-static",
+            Self::InvokeInstance => "invoke-instance",
+            Self::InvokeConstructor => "invoke-constructor",
+            Self::InvokeDirect => "invoke-direct",
+            Self::InvokeInterface => "invoke-interface",
+            Self::Unknown(value) => return write!(f, "unknown({value:#04x})"),
+        };
+        f.write_str(name)
+    }
+}
 
 #[allow(unused)]
 #[derive(Debug)]
 pub struct MethodHandleItem {
     /// type of the method handle; see [Method handle type codes](https://source.android.com/docs/core/runtime/dex-format#method-handle-type-codes)
-    pub method_handle_type: u16,
+    pub method_handle_type: MethodHandleType,
     /// Field or method id depending on whether the method handle type is an accessor or a method invoker
     pub field_or_method_id: u16,
 }
 
+impl MethodHandleItem {
+    /// Renders the field or method this handle points at, e.g. `Lfoo;->bar()V`.
+    pub fn target_to_human_readable(&self, dex: &Dex) -> Result<String, TableIdxError> {
+        let idx = self.field_or_method_id as usize;
+        match self.method_handle_type {
+            MethodHandleType::Unknown(_) => Ok(format!("{idx}")),
+            handle_type if handle_type.is_field_accessor() => dex
+                .field_ids
+                .get(idx)
+                .ok_or(TableIdxError::FieldId(idx))?
+                .to_human_readable(dex),
+            _ => dex
+                .method_ids
+                .get(idx)
+                .ok_or(TableIdxError::MethodId(idx))?
+                .to_human_readable(dex),
+        }
+    }
+
+    /// Renders the handle the way baksmali does, e.g. `invoke-static@Lfoo;->bar()V`.
+    pub fn to_human_readable(&self, dex: &Dex) -> Result<String, TableIdxError> {
+        Ok(format!(
+            "{}@{}",
+            self.method_handle_type,
+            self.target_to_human_readable(dex)?
+        ))
+    }
+}
+
 impl TryParseFromBytes for MethodHandleItem {
     const NAME: &'static str = "method_handle_item";
     const SIZE: usize = 8;
 
     fn parse_from_bytes(buffer: &[u8]) -> Self {
-        let method_handle_type = read_u16_le(buffer, 0);
+        let method_handle_type = MethodHandleType::from(read_u16_le(buffer, 0));
         let field_or_method_id = read_u16_le(buffer, 4);
         MethodHandleItem {
             method_handle_type,
@@ -22,3 +125,40 @@ impl TryParseFromBytes for MethodHandleItem {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::DexParseError;
+
+    #[test]
+    fn test_method_handle_item() {
+        let buffer = [0x04, 0x00, 0x00, 0x00, 0x2A, 0x01, 0x00, 0x00];
+        let item = MethodHandleItem::try_parse_from_bytes(&buffer).unwrap();
+        assert_eq!(item.method_handle_type, MethodHandleType::InvokeStatic);
+        assert!(!item.method_handle_type.is_field_accessor());
+        assert_eq!(item.field_or_method_id, 0x12A);
+    }
+
+    #[test]
+    fn test_unknown_method_handle_type() {
+        let buffer = [0x09, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+        let item = MethodHandleItem::try_parse_from_bytes(&buffer).unwrap();
+        assert_eq!(item.method_handle_type, MethodHandleType::Unknown(9));
+        assert_eq!(item.method_handle_type.to_string(), "unknown(0x09)");
+        assert!(MethodHandleType::from(0x03).is_field_accessor());
+    }
+
+    #[test]
+    fn test_truncated_method_handle_item() {
+        let buffer = [0x04, 0x00, 0x00, 0x00, 0x2A, 0x01];
+        assert!(matches!(
+            MethodHandleItem::try_parse_from_bytes(&buffer),
+            Err(DexParseError::InvalidElementSize {
+                expected: 8,
+                actual: 6,
+                ..
+            })
+        ));
+    }
+}
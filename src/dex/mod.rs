@@ -1,162 +1,627 @@
+pub mod access_flags;
+pub mod annotation_item;
+pub mod annotations_directory_item;
+pub mod call_site_item;
 pub mod class_data_item;
 pub mod class_def_item;
 pub mod code_item;
+pub mod container;
+pub mod debug_info_item;
 pub mod encoded;
+pub mod encoded_value;
 pub mod field_id_item;
+#[cfg(test)]
+pub mod fixture;
 pub mod header_item;
 mod instruction;
+pub mod map_list;
 pub mod method_handle_item;
 pub mod method_id_item;
 pub mod proto_id_item;
 mod string;
-mod type_list;
+pub mod try_item;
+pub mod type_list;
+mod verify;
 
-use std::borrow::Cow;
-
-use crate::errors::DexParseError;
+use crate::diagnostics::Diagnostics;
+use crate::errors::{Error, ErrorKind, IntegrityError, StringDataError, TableIdxError};
 use crate::traits::parse::TryParseFromBytes;
-use crate::utils::read_u32_le;
+use crate::utils::{adler32, read_u32_le, Reader};
+use annotation_item::{AnnotationItem, AnnotationSetItem, AnnotationSetRefList};
+use annotations_directory_item::AnnotationsDirectoryItem;
+use call_site_item::{CallSiteIdItem, CallSiteItem};
+use class_data_item::ClassDataItem;
 use class_def_item::ClassDefItem;
+use code_item::CodeItem;
+use debug_info_item::DebugInfoItem;
+use encoded_value::EncodedArray;
 use field_id_item::FieldIdItem;
 use header_item::HeaderItem;
+use map_list::{MapItemType, MapList};
 use method_handle_item::MethodHandleItem;
 use method_id_item::MethodIdItem;
 use proto_id_item::ProtoIdItem;
+use string::StringTable;
+use type_list::TypeList;
+
+/// The value used in place of an index to mean "no item", e.g. the superclass of a root class.
+pub const NO_INDEX: u32 = 0xFFFFFFFF;
+
+/// Offset of `header_offset` in `header_item`
+const HEADER_OFFSET_OFFSET: usize = 0x74;
 
 #[allow(unused)]
 pub struct Dex<'a> {
     pub raw: &'a [u8],
+    /// where problems found while reading this file are recorded
+    pub diagnostics: &'a Diagnostics,
     pub header_item: HeaderItem,
-    pub strings: Vec<Cow<'a, str>>,
-    pub types: Vec<Cow<'a, str>>,
+    pub map_list: MapList,
+    pub strings: StringTable<'a>,
+    pub types: StringTable<'a>,
     pub proto_ids: Vec<ProtoIdItem>,
     pub field_ids: Vec<FieldIdItem>,
     pub method_ids: Vec<MethodIdItem>,
     pub class_defs: Vec<ClassDefItem>,
-    pub call_site_items: Vec<&'a [u8]>,
+    /// `None` for call sites that failed to parse in lenient mode, so later indices still resolve
+    pub call_site_items: Vec<Option<CallSiteItem>>,
     pub method_handles: Vec<MethodHandleItem>,
 }
 
 impl<'a> Dex<'a> {
-    fn read_strings(buffer: &'a [u8], header: &HeaderItem) -> Vec<Cow<'a, str>> {
-        let string_ids_off = header.string_ids_off as usize;
-        let string_ids_size = header.string_ids_size as usize;
-        let mut strings = Vec::with_capacity(string_ids_size);
-        for i in 0..string_ids_size {
-            let string_data_off = read_u32_le(buffer, string_ids_off + i * 4) as usize;
-            if let Ok(str) = string::read_string_from_bytes(buffer, string_data_off) {
-                strings.push(str);
-            }
-        }
-        strings
+    /// Reads a section of `size` consecutive `u32` values, such as `string_ids`.
+    fn read_u32_section(
+        buffer: &[u8],
+        offset: u32,
+        size: u32,
+        structure: &'static str,
+    ) -> Result<Vec<u32>, Error> {
+        let mut reader = Reader::at(buffer, offset as usize, structure);
+        let section = reader.bytes((size as usize).saturating_mul(4))?;
+        Ok(section
+            .chunks_exact(4)
+            .map(|value| read_u32_le(value, 0))
+            .collect())
     }
 
-    fn read_types(buffer: &'a [u8], header: &HeaderItem) -> Vec<Cow<'a, str>> {
-        let type_ids_off = header.type_ids_off as usize;
-        let type_ids_size = header.type_ids_size as usize;
-        let mut types = Vec::with_capacity(type_ids_size);
-        for i in 0..type_ids_size {
-            let descriptor_idx = read_u32_le(buffer, type_ids_off + i * 4) as usize;
-            if let Some(str) = Self::read_strings(buffer, header)
-                .get(descriptor_idx)
-                .cloned()
-            {
-                types.push(str);
-            } else {
-                eprintln!(
-                    "Warning: Type ID {} out of bounds for string IDs",
-                    descriptor_idx
-                );
+    /// Parses the `size` fixed-size items of the section at `offset`. In lenient mode a truncated section keeps the items before the cut, so every index that can be resolved still points at the right item.
+    fn read_section<T: TryParseFromBytes>(
+        buffer: &[u8],
+        offset: u32,
+        size: u32,
+        diagnostics: &Diagnostics,
+    ) -> Result<Vec<T>, Error> {
+        let mut reader = Reader::at(buffer, offset as usize, T::NAME);
+        let mut items = Vec::with_capacity((size as usize).min(reader.remaining() / T::SIZE));
+        for _ in 0..size {
+            match diagnostics.recover(reader.parse().map(Some), || None)? {
+                Some(item) => items.push(item),
+                None => break,
             }
         }
-        types
+        Ok(items)
     }
 
-    fn read_proto_id_items(buffer: &[u8], header: &HeaderItem) -> Vec<ProtoIdItem> {
-        let proto_ids_off = header.proto_ids_off as usize;
-        let proto_ids_size = header.proto_ids_size as usize;
-
-        let mut proto_ids = Vec::with_capacity(proto_ids_size);
-        for i in 0..proto_ids_size {
-            let offset = proto_ids_off + i * 12;
-            match ProtoIdItem::try_parse_from_bytes(&buffer[offset..]) {
-                Ok(proto_id) => proto_ids.push(proto_id),
-                Err(e) => eprintln!("Failed to parse ProtoIdItem at offset {}: {}", offset, e),
-            }
+    fn read_strings(
+        buffer: &'a [u8],
+        header: &HeaderItem,
+        diagnostics: &'a Diagnostics,
+    ) -> Result<StringTable<'a>, Error> {
+        let data_offs = diagnostics.recover(
+            Self::read_u32_section(
+                buffer,
+                header.string_ids_off,
+                header.string_ids_size,
+                "string_id_item",
+            ),
+            Vec::new,
+        )?;
+        let strings = StringTable::new(
+            buffer,
+            data_offs.into_iter().map(Some).collect(),
+            diagnostics,
+        );
+        if diagnostics.is_strict() {
+            strings.validate()?;
         }
-
-        proto_ids
+        Ok(strings)
     }
 
-    fn read_field_id_items(buffer: &[u8], header: &HeaderItem) -> Vec<FieldIdItem> {
-        let field_ids_off = header.field_ids_off as usize;
-        let field_ids_size = header.field_ids_size as usize;
-
-        let mut field_ids = Vec::with_capacity(field_ids_size);
-        for i in 0..field_ids_size {
-            let offset = field_ids_off + i * 8;
-            match FieldIdItem::try_parse_from_bytes(&buffer[offset..]) {
-                Ok(field_id) => field_ids.push(field_id),
-                Err(e) => eprintln!("Failed to parse FieldIdItem at offset {}: {}", offset, e),
-            }
+    /// Resolves each type id to the string data of its descriptor. Only the offsets are read, so this is linear in the number of types.
+    fn read_types(
+        buffer: &'a [u8],
+        header: &HeaderItem,
+        strings: &StringTable<'a>,
+        diagnostics: &'a Diagnostics,
+    ) -> Result<StringTable<'a>, Error> {
+        let descriptor_idxs = diagnostics.recover(
+            Self::read_u32_section(
+                buffer,
+                header.type_ids_off,
+                header.type_ids_size,
+                "type_id_item",
+            ),
+            Vec::new,
+        )?;
+        let mut data_offs = Vec::with_capacity(descriptor_idxs.len());
+        for (i, descriptor_idx) in descriptor_idxs.into_iter().enumerate() {
+            let descriptor_idx = descriptor_idx as usize;
+            let data_off = strings.data_off(descriptor_idx).ok_or_else(|| {
+                Error::new(
+                    "type_id_item",
+                    header.type_ids_off as usize + i * 4,
+                    TableIdxError::String(descriptor_idx),
+                )
+            });
+            data_offs.push(diagnostics.recover(data_off.map(Some), || None)?);
         }
-
-        field_ids
+        Ok(StringTable::new(buffer, data_offs, diagnostics))
     }
 
-    fn read_method_id_items(buffer: &[u8], header: &HeaderItem) -> Vec<MethodIdItem> {
-        let method_ids_off = header.method_ids_off as usize;
-        let method_ids_size = header.method_ids_size as usize;
+    fn read_call_site_items(
+        buffer: &[u8],
+        map_list: &MapList,
+        diagnostics: &Diagnostics,
+    ) -> Result<Vec<Option<CallSiteItem>>, Error> {
+        let Some(call_site_ids) = map_list.get(MapItemType::CallSiteIdItem) else {
+            return Ok(Vec::new());
+        };
+        let call_site_ids: Vec<CallSiteIdItem> = Self::read_section(
+            buffer,
+            call_site_ids.offset,
+            call_site_ids.size,
+            diagnostics,
+        )?;
 
-        let mut method_ids = Vec::with_capacity(method_ids_size);
-        for i in 0..method_ids_size {
-            let offset = method_ids_off + i * 8;
-            match MethodIdItem::try_parse_from_bytes(&buffer[offset..offset + 8]) {
-                Ok(method_id) => method_ids.push(method_id),
-                Err(e) => eprintln!("Failed to parse MethodIdItem at offset {}: {}", offset, e),
-            }
-        }
-
-        method_ids
+        call_site_ids
+            .into_iter()
+            .map(|call_site_id| {
+                let call_site_off = call_site_id.call_site_off as usize;
+                let call_site = CallSiteItem::try_parse_from_bytes_unsized(
+                    buffer.get(call_site_off..).unwrap_or_default(),
+                )
+                .map_err(|e| e.offset_by(call_site_off));
+                diagnostics.recover(call_site.map(Some), || None)
+            })
+            .collect()
     }
 
-    fn read_class_def_items(buffer: &[u8], header: &HeaderItem) -> Vec<ClassDefItem> {
-        let class_defs_off = header.class_defs_off as usize;
-        let class_defs_size = header.class_defs_size as usize;
-
-        let mut class_defs = Vec::with_capacity(class_defs_size);
-        for i in 0..class_defs_size {
-            let offset = class_defs_off + i * 32;
-            match ClassDefItem::try_parse_from_bytes(&buffer[offset..]) {
-                Ok(class_def) => class_defs.push(class_def),
-                Err(e) => eprintln!("Failed to parse ClassDefItem at offset {}: {}", offset, e),
-            }
-        }
+    fn read_method_handle_items(
+        buffer: &[u8],
+        map_list: &MapList,
+        diagnostics: &Diagnostics,
+    ) -> Result<Vec<MethodHandleItem>, Error> {
+        let Some(method_handles) = map_list.get(MapItemType::MethodHandleItem) else {
+            return Ok(Vec::new());
+        };
+        Self::read_section(
+            buffer,
+            method_handles.offset,
+            method_handles.size,
+            diagnostics,
+        )
+    }
 
-        class_defs
+    #[allow(unused)]
+    pub fn try_parse_from_bytes(
+        buffer: &'a [u8],
+        diagnostics: &'a Diagnostics,
+    ) -> Result<Self, Error> {
+        Self::try_parse_from_bytes_at(buffer, 0, diagnostics)
     }
 
-    pub fn try_parse_from_bytes(buffer: &'a [u8]) -> Result<Self, DexParseError> {
-        let header_item = HeaderItem::try_parse_from_bytes(buffer)?;
+    /// Parses the dex file whose header starts at `header_offset` in `buffer`. Apart from the header itself, all offsets are relative to the start of `buffer`, which for version 041 containers is shared by every dex file in it.
+    ///
+    /// The header and map list are always required. Past those, what happens to a structure that fails to parse is up to `diagnostics`.
+    pub fn try_parse_from_bytes_at(
+        buffer: &'a [u8],
+        header_offset: usize,
+        diagnostics: &'a Diagnostics,
+    ) -> Result<Self, Error> {
+        let header_item =
+            HeaderItem::try_parse_from_bytes(buffer.get(header_offset..).unwrap_or_default())
+                .map_err(|e| Error::new(HeaderItem::NAME, header_offset, e))?;
+        // Everything that locates this dex in its container goes through `header_offset`, so it must say where the header really is
+        if header_item.header_offset as usize != header_offset {
+            return Err(Error::new(
+                HeaderItem::NAME,
+                header_offset + HEADER_OFFSET_OFFSET,
+                ErrorKind::Malformed(format!(
+                    "header_offset {:#x} does not match the position of the header {header_offset:#x}",
+                    header_item.header_offset
+                )),
+            ));
+        }
+        let map_off = header_item.map_off as usize;
+        let map_list =
+            MapList::try_parse_from_bytes_unsized(buffer.get(map_off..).unwrap_or_default())
+                .map_err(|e| e.offset_by(map_off))?;
 
-        let strings = Self::read_strings(buffer, &header_item);
-        let types = Self::read_types(buffer, &header_item);
-        let proto_ids = Self::read_proto_id_items(buffer, &header_item);
-        let field_ids = Self::read_field_id_items(buffer, &header_item);
-        let method_ids = Self::read_method_id_items(buffer, &header_item);
-        let class_defs = Self::read_class_def_items(buffer, &header_item);
+        let header = &header_item;
+        let strings = Self::read_strings(buffer, header, diagnostics)?;
+        let types = Self::read_types(buffer, header, &strings, diagnostics)?;
+        let proto_ids = Self::read_section(
+            buffer,
+            header.proto_ids_off,
+            header.proto_ids_size,
+            diagnostics,
+        )?;
+        let field_ids = Self::read_section(
+            buffer,
+            header.field_ids_off,
+            header.field_ids_size,
+            diagnostics,
+        )?;
+        let method_ids = Self::read_section(
+            buffer,
+            header.method_ids_off,
+            header.method_ids_size,
+            diagnostics,
+        )?;
+        let class_defs = Self::read_section(
+            buffer,
+            header.class_defs_off,
+            header.class_defs_size,
+            diagnostics,
+        )?;
+        let call_site_items = Self::read_call_site_items(buffer, &map_list, diagnostics)?;
+        let method_handles = Self::read_method_handle_items(buffer, &map_list, diagnostics)?;
 
         Ok(Self {
             raw: buffer,
+            diagnostics,
             header_item,
+            map_list,
             strings,
             types,
             proto_ids,
             field_ids,
             method_ids,
             class_defs,
-            call_site_items: Vec::new(),
-            method_handles: Vec::new(),
+            call_site_items,
+            method_handles,
         })
     }
+
+    /// Returns the bytes of this dex file as declared by `file_size`, starting at its header. The file must end within both the buffer and the `container_size` declared by the header.
+    fn file_bytes(&self) -> Result<&'a [u8], IntegrityError> {
+        let start = self.header_item.header_offset as usize;
+        let expected = self.header_item.file_size as usize;
+        let container_end = (self.header_item.container_size as usize).min(self.raw.len());
+        self.raw
+            .get(start..container_end)
+            .and_then(|file| file.get(..expected))
+            .ok_or(IntegrityError::Truncated {
+                expected,
+                actual: container_end.saturating_sub(start),
+            })
+    }
+
+    /// Recomputes the adler32 checksum over everything after the `checksum` field and compares it to the header.
+    pub fn verify_checksum(&self) -> Result<(), IntegrityError> {
+        let actual = adler32(self.file_bytes()?.get(12..).unwrap_or_default());
+        let expected = self.header_item.checksum;
+        if actual != expected {
+            return Err(IntegrityError::Checksum { expected, actual });
+        }
+        Ok(())
+    }
+
+    /// Recomputes the SHA-1 signature over everything after the `signature` field and compares it to the header.
+    pub fn verify_signature(&self) -> Result<(), IntegrityError> {
+        let actual = sha1_smol::Sha1::from(self.file_bytes()?.get(32..).unwrap_or_default())
+            .digest()
+            .bytes();
+        let expected = self.header_item.signature;
+        if actual != expected {
+            return Err(IntegrityError::Signature { expected, actual });
+        }
+        Ok(())
+    }
+
+    /// Runs every integrity check, returning all mismatches found. A truncated file is reported once.
+    ///
+    /// Parsing never runs these checks, even with strict diagnostics, so a caller that wants to refuse corrupted files has to do it with the result.
+    pub fn verify_integrity(&self) -> Vec<IntegrityError> {
+        if let Err(e) = self.file_bytes() {
+            return vec![e];
+        }
+        [self.verify_checksum(), self.verify_signature()]
+            .into_iter()
+            .filter_map(Result::err)
+            .collect()
+    }
+
+    /// Parses the `type_list` at `offset` from the start of the file. The `0` offset, used by the spec to mean "no list", yields an empty list.
+    pub fn type_list(&self, offset: u32) -> Result<TypeList, Error> {
+        if offset == 0 {
+            return Ok(TypeList::default());
+        }
+
+        self.parse_at(offset, TypeList::try_parse_from_bytes_unsized)
+    }
+
+    /// Runs `parse` on the file contents starting at `offset`, moving its errors to their offset from the start of the file.
+    fn parse_at<T>(
+        &self,
+        offset: u32,
+        parse: impl FnOnce(&'a [u8]) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let offset = offset as usize;
+        parse(self.raw.get(offset..).unwrap_or_default()).map_err(|e| e.offset_by(offset))
+    }
+
+    /// Parses the `encoded_array_item` at `offset` from the start of the file. The `0` offset, used by the spec to mean "no array", yields an empty array.
+    pub fn encoded_array(&self, offset: u32) -> Result<EncodedArray, Error> {
+        if offset == 0 {
+            return Ok(EncodedArray::default());
+        }
+
+        self.parse_at(offset, EncodedArray::try_parse_from_bytes_unsized)
+    }
+
+    /// Returns the string at `idx` as raw UTF-16 code units, preserving unpaired surrogates that [`Self::strings`] replaces.
+    pub fn string_utf16(&self, idx: usize) -> Result<Vec<u16>, StringDataError> {
+        let string_data_off = self
+            .strings
+            .data_off(idx)
+            .ok_or(StringDataError::Index(idx))?;
+        string::read_utf16_from_bytes(self.raw, string_data_off as usize)
+    }
+
+    /// Renders the string at `idx` as a quoted smali literal, escaping it from the raw UTF-16 code units so unpaired surrogates survive.
+    pub fn string_literal(&self, idx: usize) -> Result<String, TableIdxError> {
+        match self.string_utf16(idx) {
+            Ok(units) => Ok(string::utf16_to_literal(&units)),
+            Err(StringDataError::Index(idx)) => Err(TableIdxError::String(idx)),
+            // The string failed to decode and the table holds a placeholder
+            Err(_) => {
+                let string = self.strings.get(idx).ok_or(TableIdxError::String(idx))?;
+                Ok(string::utf16_to_literal(
+                    &string.encode_utf16().collect::<Vec<_>>(),
+                ))
+            }
+        }
+    }
+
+    /// Parses and interprets the `debug_info_item` at `offset` from the start of the file. The `0` offset, used by methods without debug information, yields an empty item.
+    pub fn debug_info(&self, offset: u32) -> Result<DebugInfoItem, Error> {
+        if offset == 0 {
+            return Ok(DebugInfoItem::default());
+        }
+
+        self.parse_at(offset, DebugInfoItem::try_parse_from_bytes_unsized)
+    }
+
+    /// Parses the `class_data_item` at `offset` from the start of the file. The `0` offset, used by classes without fields or methods, yields an empty item.
+    pub fn class_data(&self, offset: u32) -> Result<ClassDataItem, Error> {
+        if offset == 0 {
+            return Ok(ClassDataItem::default());
+        }
+
+        self.parse_at(offset, ClassDataItem::try_parse_from_bytes_unsized)
+    }
+
+    /// Parses the `code_item` at `offset` from the start of the file. In lenient mode an instruction that fails to decode is recorded and the code item keeps the instructions before it.
+    pub fn code_item(&self, offset: u32) -> Result<CodeItem, Error> {
+        let (mut code_item, decode_error) =
+            self.parse_at(offset, CodeItem::try_parse_from_bytes_lenient)?;
+        if let Some(e) = decode_error {
+            self.diagnostics
+                .recover(Err(e.offset_by(offset as usize)), || ())?;
+        }
+        code_item.offset = offset;
+        Ok(code_item)
+    }
+
+    /// Parses the `annotations_directory_item` at `offset` from the start of the file. The `0` offset yields an empty directory.
+    pub fn annotations_directory(&self, offset: u32) -> Result<AnnotationsDirectoryItem, Error> {
+        if offset == 0 {
+            return Ok(AnnotationsDirectoryItem::default());
+        }
+
+        self.parse_at(
+            offset,
+            AnnotationsDirectoryItem::try_parse_from_bytes_unsized,
+        )
+    }
+
+    /// Parses the `annotation_set_item` at `offset` from the start of the file along with every `annotation_item` it points at. The `0` offset yields an empty set.
+    pub fn annotation_set(&self, offset: u32) -> Result<Vec<AnnotationItem>, Error> {
+        if offset == 0 {
+            return Ok(Vec::new());
+        }
+
+        self.parse_at(offset, AnnotationSetItem::try_parse_from_bytes_unsized)?
+            .entries
+            .into_iter()
+            .map(|annotation_off| {
+                self.parse_at(annotation_off, AnnotationItem::try_parse_from_bytes_unsized)
+            })
+            .collect()
+    }
+
+    /// Parses the `annotation_set_ref_list` at `offset` from the start of the file, returning the annotations of each parameter in order. The `0` offset yields an empty list.
+    pub fn annotation_set_ref_list(&self, offset: u32) -> Result<Vec<Vec<AnnotationItem>>, Error> {
+        if offset == 0 {
+            return Ok(Vec::new());
+        }
+
+        self.parse_at(offset, AnnotationSetRefList::try_parse_from_bytes_unsized)?
+            .list
+            .into_iter()
+            .map(|annotations_off| self.annotation_set(annotations_off))
+            .collect()
+    }
+
+    /// Resolves the prototype at `proto_idx` to its full descriptor, e.g. `(ILjava/lang/String;)V`.
+    pub fn proto_descriptor(&self, proto_idx: usize) -> Result<String, TableIdxError> {
+        self.proto_ids
+            .get(proto_idx)
+            .ok_or(TableIdxError::ProtoId(proto_idx))?
+            .to_human_readable(self)
+    }
+
+    /// Returns the `p` register number of each parameter of the prototype at `proto_idx`. Non-static methods receive `this` in `p0`, and `long`/`double` parameters take two registers.
+    pub fn parameter_registers(
+        &self,
+        proto_idx: usize,
+        is_static: bool,
+    ) -> Result<Vec<u16>, TableIdxError> {
+        let proto = self
+            .proto_ids
+            .get(proto_idx)
+            .ok_or(TableIdxError::ProtoId(proto_idx))?;
+
+        let mut register = if is_static { 0 } else { 1 };
+        let mut registers = Vec::new();
+        let parameters = self
+            .type_list(proto.parameters_off)
+            .map_err(|e| TableIdxError::TypeList(Box::new(e)))?;
+        for type_idx in parameters.list {
+            let parameter_type = self
+                .types
+                .get(type_idx as usize)
+                .ok_or(TableIdxError::Type(type_idx as usize))?;
+            registers.push(register);
+            register += if matches!(parameter_type.as_ref(), "J" | "D") {
+                2
+            } else {
+                1
+            };
+        }
+        Ok(registers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoded_value::EncodedValue;
+    use fixture::DexBuilder;
+
+    #[test]
+    fn test_encoded_array() {
+        let mut builder = DexBuilder::new();
+        let hello = builder.string("hello");
+        // size 2, int 5, string `hello`
+        let array_off = builder.data(&[0x02, 0x04, 0x05, 0x17, hello as u8]);
+        let file = builder.build();
+        let diagnostics = Diagnostics::new(true);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        assert_eq!(
+            dex.encoded_array(array_off).unwrap().values,
+            vec![EncodedValue::Int(5), EncodedValue::String(hello)]
+        );
+        assert!(dex.encoded_array(0).unwrap().values.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_encoded_array() {
+        let mut builder = DexBuilder::new();
+        // size 2, int 5, then a value of the unassigned type 0x05
+        let array_off = builder.data(&[0x02, 0x04, 0x05, 0x05]);
+        let file = builder.build();
+        let diagnostics = Diagnostics::new(true);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        let e = dex.encoded_array(array_off).unwrap_err();
+        assert_eq!(e.structure, "encoded_array_item");
+        assert_eq!(e.offset, array_off as usize + 4);
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+
+        // An offset past the end of the file leaves nothing to read the size from
+        let e = dex.encoded_array(file.len() as u32).unwrap_err();
+        assert_eq!(e.offset, file.len());
+        assert!(matches!(e.kind, ErrorKind::Leb128("encoded_array size")));
+    }
+
+    #[test]
+    fn test_flipped_byte_fails_the_checksum() {
+        let mut builder = DexBuilder::new();
+        builder.type_("LFoo;");
+        let mut file = builder.build();
+        let diagnostics = Diagnostics::new(true);
+        assert!(Dex::try_parse_from_bytes(&file, &diagnostics)
+            .unwrap()
+            .verify_integrity()
+            .is_empty());
+
+        // The padding before the data section is covered by both hashes but read by nothing else
+        file[0x300] ^= 0x01;
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+        let checksum = dex.header_item.checksum;
+        assert!(matches!(
+            dex.verify_checksum(),
+            Err(IntegrityError::Checksum { expected, actual }) if expected == checksum && actual != checksum
+        ));
+        assert!(matches!(
+            dex.verify_integrity()[..],
+            [
+                IntegrityError::Checksum { .. },
+                IntegrityError::Signature { .. }
+            ]
+        ));
+
+        // The signature does not cover the checksum field
+        file[0x300] ^= 0x01;
+        file[8] ^= 0x01;
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+        assert!(matches!(
+            dex.verify_integrity()[..],
+            [IntegrityError::Checksum { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_parameter_registers() {
+        let mut builder = DexBuilder::new();
+        let proto = builder.proto("VIJLD", "V", &["I", "J", "Ljava/lang/String;", "D"]);
+        let no_parameters = builder.proto("V", "V", &[]);
+        let file = builder.build();
+        let diagnostics = Diagnostics::new(true);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        // `this` takes p0, and the wide `long` and `double` take two registers each
+        assert_eq!(
+            dex.parameter_registers(proto as usize, false).unwrap(),
+            [1, 2, 4, 5]
+        );
+        assert_eq!(
+            dex.parameter_registers(proto as usize, true).unwrap(),
+            [0, 1, 3, 4]
+        );
+        assert!(dex
+            .parameter_registers(no_parameters as usize, false)
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            dex.parameter_registers(5, false),
+            Err(TableIdxError::ProtoId(5))
+        ));
+    }
+
+    #[test]
+    fn test_type_list_keeps_the_parse_error() {
+        let mut builder = DexBuilder::new();
+        let proto = builder.proto("VI", "V", &["I"]);
+        // a list declaring more types than the file can hold
+        let truncated = builder.data(&[0xFF, 0xFF, 0xFF, 0x7F]);
+        let file = builder.build();
+        let diagnostics = Diagnostics::new(true);
+        let mut dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        assert!(dex.type_list(0).unwrap().list.is_empty());
+        let e = dex.type_list(truncated).unwrap_err();
+        assert_eq!(e.structure, "type_list");
+        assert_eq!(e.offset, truncated as usize + 4);
+        assert!(matches!(e.kind, ErrorKind::UnexpectedEof { .. }));
+
+        dex.proto_ids[proto as usize].parameters_off = truncated;
+        let Err(TableIdxError::TypeList(e)) = dex.proto_descriptor(proto as usize) else {
+            panic!("the truncated parameters should fail the descriptor");
+        };
+        assert_eq!(e.offset, truncated as usize + 4);
+    }
 }
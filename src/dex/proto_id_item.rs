@@ -9,7 +9,7 @@ pub struct ProtoIdItem {
     /// index into the `type_ids` list for the return type of this prototype
     pub return_type_idx: u32,
     /// offset from the start of the file to the list of parameter types for this prototype, or 0 if this prototype has no parameters. This offset, if non-zero, should be in the data section, and the data there should be in the format specified by "type_list" below. Additionally, there should be no reference to the type void in the list.
-    pub parameters_off: u32,j 
+    pub parameters_off: u32,
 }
 
 impl ProtoIdItem {
@@ -18,7 +18,19 @@ impl ProtoIdItem {
             crate::errors::TableIdxError::Type(self.return_type_idx as usize),
         )?;
 
-        Ok(format!("({}){return_type}", self.parameters_off))
+        let mut parameters = String::new();
+        let parameter_types = dex
+            .type_list(self.parameters_off)
+            .map_err(|e| crate::errors::TableIdxError::TypeList(Box::new(e)))?;
+        for type_idx in parameter_types.list {
+            let parameter_type = dex
+                .types
+                .get(type_idx as usize)
+                .ok_or(crate::errors::TableIdxError::Type(type_idx as usize))?;
+            parameters.push_str(parameter_type);
+        }
+
+        Ok(format!("({parameters}){return_type}"))
     }
 }
 
@@ -1,25 +1,255 @@
-use std::ffi::CStr;
+use std::{borrow::Cow, sync::OnceLock};
 
-use crate::utils::decode_uleb128;
+use crate::{
+    diagnostics::Diagnostics,
+    errors::{Error, StringDataError},
+    utils::decode_uleb128,
+};
 
+/// Decodes Modified UTF-8 into UTF-16 code units. `base_offset` is only used to report the position of invalid bytes.
+///
+/// MUTF-8 differs from standard UTF-8 in that NUL is encoded as `C0 80`, code points above U+FFFF are encoded as a surrogate pair of two 3-byte sequences, and surrogates need not be paired.
+fn decode_mutf8(bytes: &[u8], base_offset: usize) -> Result<Vec<u16>, StringDataError> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    let continuation = |i: usize| match bytes.get(i) {
+        Some(byte) if byte & 0xC0 == 0x80 => Ok((byte & 0x3F) as u16),
+        Some(byte) => Err(StringDataError::InvalidByte {
+            offset: base_offset + i,
+            byte: *byte,
+        }),
+        None => Err(StringDataError::Unterminated(base_offset)),
+    };
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let unit = match byte {
+            0x01..=0x7F => {
+                i += 1;
+                byte as u16
+            }
+            0xC0..=0xDF => {
+                let unit = ((byte & 0x1F) as u16) << 6 | continuation(i + 1)?;
+                i += 2;
+                unit
+            }
+            0xE0..=0xEF => {
+                let unit =
+                    ((byte & 0x0F) as u16) << 12 | continuation(i + 1)? << 6 | continuation(i + 2)?;
+                i += 3;
+                unit
+            }
+            _ => {
+                return Err(StringDataError::InvalidByte {
+                    offset: base_offset + i,
+                    byte,
+                })
+            }
+        };
+        units.push(unit);
+    }
+
+    Ok(units)
+}
+
+/// Decodes the `string_data_item` at `offset`, returning its UTF-16 code units and the raw MUTF-8 bytes they were decoded from.
+fn read_string_data(buffer: &[u8], offset: usize) -> Result<(Vec<u16>, &[u8]), StringDataError> {
+    let (utf16_size, len_size) = decode_uleb128(buffer.get(offset..).unwrap_or_default())
+        .ok_or(StringDataError::Length(offset))?;
+
+    let data_offset = offset + len_size;
+    let data = buffer.get(data_offset..).unwrap_or_default();
+    let len = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .ok_or(StringDataError::Unterminated(data_offset))?;
+
+    let units = decode_mutf8(&data[..len], data_offset)?;
+    if units.len() as u64 != utf16_size {
+        return Err(StringDataError::LengthMismatch {
+            expected: utf16_size,
+            actual: units.len() as u64,
+        });
+    }
+    Ok((units, &data[..len]))
+}
+
+/// Reads the `string_data_item` at `offset` as raw UTF-16 code units, validating them against `utf16_size`.
+///
+/// Unlike [`read_string_from_bytes`], this preserves unpaired surrogates.
+pub fn read_utf16_from_bytes(buffer: &[u8], offset: usize) -> Result<Vec<u16>, StringDataError> {
+    read_string_data(buffer, offset).map(|(units, _)| units)
+}
+
+/// Reads the `string_data_item` at `offset`. ASCII strings are borrowed from `buffer`; unpaired surrogates are replaced with U+FFFD.
 pub fn read_string_from_bytes(
     buffer: &[u8],
     offset: usize,
-) -> Result<std::borrow::Cow<'_, str>, std::io::Error> {
-    let (utf16_len, len_size) = decode_uleb128(&buffer[offset..]).ok_or(std::io::Error::new(
-        std::io::ErrorKind::InvalidData,
-        "Failed to decode ULEB128 for string length",
-    ))?;
-    let string_bytes_offset = offset + len_size;
-    let cstr = CStr::from_bytes_until_nul(&buffer[string_bytes_offset..]).map_err(|e| {
-        std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            format!("Failed to parse CStr: {e}"),
-        )
-    })?;
-    let s = String::from_utf8_lossy(cstr.to_bytes());
-    if s.encode_utf16().count() as u64 != utf16_len {
-        eprintln!("Warning: String length mismatch for string: {}", s);
-    }
-    Ok(s)
+) -> Result<Cow<'_, str>, StringDataError> {
+    let (units, bytes) = read_string_data(buffer, offset)?;
+    if bytes.is_ascii() {
+        // ASCII is the only case where MUTF-8 and UTF-8 agree byte for byte
+        if let Ok(s) = std::str::from_utf8(bytes) {
+            return Ok(Cow::Borrowed(s));
+        }
+    }
+    Ok(Cow::Owned(String::from_utf16_lossy(&units)))
+}
+
+/// A table of strings indexed like `string_ids` or `type_ids`, decoding each `string_data_item` on first access.
+///
+/// Building the table only records where each string lives, so opening a file does not pay for strings that are never looked at. A string that fails to decode is recorded in the diagnostics once and reads as `""`, so later indices still resolve.
+pub struct StringTable<'a> {
+    buffer: &'a [u8],
+    /// offset from the start of the file to the `string_data_item` of each entry, or `None` if the entry does not point at one
+    data_offs: Vec<Option<u32>>,
+    strings: Vec<OnceLock<Cow<'a, str>>>,
+    diagnostics: &'a Diagnostics,
+}
+
+impl<'a> StringTable<'a> {
+    pub fn new(
+        buffer: &'a [u8],
+        data_offs: Vec<Option<u32>>,
+        diagnostics: &'a Diagnostics,
+    ) -> Self {
+        let strings = data_offs.iter().map(|_| OnceLock::new()).collect();
+        Self {
+            buffer,
+            data_offs,
+            strings,
+            diagnostics,
+        }
+    }
+
+    /// Decodes every entry up front, returning the first one that fails. Used in strict mode, where a bad string should stop parsing rather than turn up later as a placeholder.
+    pub fn validate(&self) -> Result<(), Error> {
+        for (idx, data_off) in self.data_offs.iter().enumerate() {
+            let Some(data_off) = *data_off else { continue };
+            let string = read_string_from_bytes(self.buffer, data_off as usize)
+                .map_err(|e| Error::new("string_data_item", data_off as usize, e))?;
+            let _ = self.strings[idx].set(string);
+        }
+        Ok(())
+    }
+
+    /// Returns the offset from the start of the file to the `string_data_item` of entry `idx`.
+    pub fn data_off(&self, idx: usize) -> Option<u32> {
+        self.data_offs.get(idx).copied().flatten()
+    }
+
+    /// Returns entry `idx`, decoding it if this is the first access.
+    pub fn get(&self, idx: usize) -> Option<&Cow<'a, str>> {
+        let data_off = self.data_off(idx)?;
+        Some(self.strings[idx].get_or_init(|| {
+            read_string_from_bytes(self.buffer, data_off as usize).unwrap_or_else(|e| {
+                self.diagnostics
+                    .record(Error::new("string_data_item", data_off as usize, e));
+                Cow::Borrowed("")
+            })
+        }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.data_offs.len()
+    }
+}
+
+/// Renders UTF-16 code units as a quoted smali string literal. Quotes, backslashes, control characters and unpaired surrogates are escaped.
+pub fn utf16_to_literal(units: &[u16]) -> String {
+    let mut out = String::from("\"");
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok('"') => out.push_str("\\\""),
+            Ok('\\') => out.push_str("\\\\"),
+            Ok('\n') => out.push_str("\\n"),
+            Ok('\r') => out.push_str("\\r"),
+            Ok('\t') => out.push_str("\\t"),
+            Ok(c) if !c.is_control() => out.push(c),
+            Ok(c) => out.push_str(&format!("\\u{:04x}", c as u32)),
+            Err(e) => out.push_str(&format!("\\u{:04x}", e.unpaired_surrogate())),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_table() {
+        let buffer = [0x02, b'h', b'i', 0x00, 0x05, b'x', 0x00];
+        let diagnostics = Diagnostics::new(false);
+        let table = StringTable::new(&buffer, vec![Some(4), Some(0), None], &diagnostics);
+        assert_eq!(table.len(), 3);
+        // The length of the first string does not match its data, so it reads as a placeholder
+        assert_eq!(table.get(0).unwrap(), "");
+        assert_eq!(table.get(0).unwrap(), "");
+        assert_eq!(diagnostics.entries().len(), 1);
+        assert_eq!(diagnostics.entries()[0].offset, Some(4));
+        assert!(table.validate().is_err());
+        assert_eq!(table.get(1).unwrap(), "hi");
+        assert!(table.get(2).is_none());
+        assert!(table.get(3).is_none());
+    }
+
+    #[test]
+    fn test_ascii_is_borrowed() {
+        let buffer = [0x02, b'h', b'i', 0x00];
+        let s = read_string_from_bytes(&buffer, 0).unwrap();
+        assert!(matches!(s, Cow::Borrowed("hi")));
+    }
+
+    #[test]
+    fn test_encoded_nul_and_supplementary() {
+        // "a\0" followed by U+1F600 as a surrogate pair of two 3-byte sequences
+        let buffer = [
+            0x04, b'a', 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, 0x00,
+        ];
+        assert_eq!(
+            read_utf16_from_bytes(&buffer, 0).unwrap(),
+            [0x61, 0x00, 0xD83D, 0xDE00]
+        );
+        assert_eq!(read_string_from_bytes(&buffer, 0).unwrap(), "a\0\u{1F600}");
+    }
+
+    #[test]
+    fn test_unpaired_surrogate() {
+        let buffer = [0x01, 0xED, 0xA0, 0x80, 0x00];
+        assert_eq!(read_utf16_from_bytes(&buffer, 0).unwrap(), [0xD800]);
+        assert_eq!(read_string_from_bytes(&buffer, 0).unwrap(), "\u{FFFD}");
+    }
+
+    #[test]
+    fn test_literal_escapes() {
+        let units: Vec<u16> = "a\"\\\n\0".encode_utf16().chain([0xDC00]).collect();
+        assert_eq!(utf16_to_literal(&units), "\"a\\\"\\\\\\n\\u0000\\udc00\"");
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let buffer = [0x03, b'h', b'i', 0x00];
+        assert!(matches!(
+            read_utf16_from_bytes(&buffer, 0),
+            Err(StringDataError::LengthMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_four_byte_sequence_is_invalid() {
+        let buffer = [0x02, 0xF0, 0x9F, 0x98, 0x80, 0x00];
+        assert!(matches!(
+            read_utf16_from_bytes(&buffer, 0),
+            Err(StringDataError::InvalidByte {
+                offset: 1,
+                byte: 0xF0
+            })
+        ));
+    }
 }
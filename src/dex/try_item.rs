@@ -0,0 +1,156 @@
+use crate::{
+    errors::Error,
+    traits::parse::TryParseFromBytes,
+    utils::{read_u16_le, read_u32_le, Reader},
+};
+
+/// https://source.android.com/docs/core/runtime/dex-format#type-item
+#[derive(Debug)]
+pub struct TryItem {
+    /// start address of the block of code covered by this entry. The address is a count of 16-bit code units to the start of the first covered instruction.
+    pub start_addr: u32,
+    /// number of 16-bit code units covered by this entry. The last code unit covered (inclusive) is `start_addr + insn_count - 1`.
+    pub insn_count: u16,
+    /// offset in bytes from the start of the associated `encoded_catch_handler_list` to the `encoded_catch_handler` for this entry. This must be an offset to the start of an `encoded_catch_handler`.
+    pub handler_off: u16,
+}
+
+impl TryItem {
+    /// Address of the first code unit past the end of the covered block, or `None` if the block runs past the largest address.
+    pub fn end_addr(&self) -> Option<u32> {
+        self.start_addr.checked_add(self.insn_count as u32)
+    }
+}
+
+impl TryParseFromBytes for TryItem {
+    const NAME: &'static str = "try_item";
+    const SIZE: usize = 8;
+
+    fn parse_from_bytes(buffer: &[u8]) -> Self {
+        let start_addr = read_u32_le(buffer, 0);
+        let insn_count = read_u16_le(buffer, 4);
+        let handler_off = read_u16_le(buffer, 6);
+        Self {
+            start_addr,
+            insn_count,
+            handler_off,
+        }
+    }
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#encoded-type-addr-pair
+#[derive(Debug)]
+pub struct TypeAddrPair {
+    /// index into the `type_ids` list for the type of the exception to catch
+    pub type_idx: u32,
+    /// bytecode address of the associated exception handler
+    pub addr: u32,
+}
+
+/// https://source.android.com/docs/core/runtime/dex-format#encoded-catch-handler
+#[derive(Debug)]
+pub struct CatchHandler {
+    /// offset in bytes from the start of the `encoded_catch_handler_list`, as referenced by `TryItem::handler_off`
+    pub offset: u16,
+    /// the typed handlers, in the order in which the types should be tested
+    pub handlers: Vec<TypeAddrPair>,
+    /// bytecode address of the catch-all handler, or `None` if this handler has no catch-all
+    pub catch_all_addr: Option<u32>,
+}
+
+impl CatchHandler {
+    pub fn try_parse_from_reader(reader: &mut Reader) -> Result<Self, Error> {
+        let handler_offset = reader.offset as u16;
+        let size = reader.sleb128("encoded_catch_handler size")?;
+
+        let mut handlers = Vec::new();
+        for _ in 0..size.unsigned_abs() {
+            let type_idx = reader.uleb128("catch handler type index")? as u32;
+            let addr = reader.uleb128("catch handler address")? as u32;
+            handlers.push(TypeAddrPair { type_idx, addr });
+        }
+
+        // A non-positive size means the typed handlers are followed by a catch-all
+        let catch_all_addr = if size <= 0 {
+            Some(reader.uleb128("catch-all handler address")? as u32)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            offset: handler_offset,
+            handlers,
+            catch_all_addr,
+        })
+    }
+}
+
+/// Parses an `encoded_catch_handler_list`, keeping the offset of each handler so `TryItem::handler_off` can be resolved.
+pub fn try_parse_catch_handler_list(buffer: &[u8]) -> Result<Vec<CatchHandler>, Error> {
+    let mut reader = Reader::new(buffer, "encoded_catch_handler_list");
+    let size = reader.uleb128("encoded_catch_handler_list size")?;
+
+    let mut handlers = Vec::new();
+    for _ in 0..size {
+        handlers.push(CatchHandler::try_parse_from_reader(&mut reader)?);
+    }
+    Ok(handlers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorKind;
+
+    #[test]
+    fn test_end_addr() {
+        let try_item = TryItem::parse_from_bytes(&[0x10, 0, 0, 0, 0x04, 0, 0, 0]);
+        assert_eq!(try_item.end_addr(), Some(0x14));
+
+        let try_item = TryItem::parse_from_bytes(&[0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0, 0, 0]);
+        assert_eq!(try_item.end_addr(), None);
+    }
+
+    #[test]
+    fn test_catch_handler_list() {
+        #[rustfmt::skip]
+        let buffer = [
+            0x03, // size
+            0x02, 0x05, 0x10, 0x06, 0x20, // two typed handlers
+            0x00, 0x30, // a catch-all alone
+            0x7F, 0x05, 0x10, 0x40, // a typed handler followed by a catch-all
+        ];
+        let handlers = try_parse_catch_handler_list(&buffer).unwrap();
+        let summary: Vec<_> = handlers
+            .iter()
+            .map(|handler| {
+                let pairs: Vec<_> = handler
+                    .handlers
+                    .iter()
+                    .map(|pair| (pair.type_idx, pair.addr))
+                    .collect();
+                (handler.offset, pairs, handler.catch_all_addr)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, vec![(5, 0x10), (6, 0x20)], None),
+                (6, vec![], Some(0x30)),
+                (8, vec![(5, 0x10)], Some(0x40)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncated_catch_handler() {
+        // the catch-all address of the second handler is missing
+        let e = try_parse_catch_handler_list(&[0x02, 0x00, 0x30, 0x00]).unwrap_err();
+        assert_eq!(e.structure, "encoded_catch_handler_list");
+        assert_eq!(e.offset, 4);
+        assert!(matches!(
+            e.kind,
+            ErrorKind::Leb128("catch-all handler address")
+        ));
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{
+    errors::Error,
+    utils::{read_u16_le, Reader},
+};
+
+/// https://source.android.com/docs/core/runtime/dex-format#type-list
+#[derive(Debug, Default)]
+pub struct TypeList {
+    /// elements of the list, each an index into the `type_ids` list
+    pub list: Vec<u16>,
+}
+
+impl TypeList {
+    pub fn try_parse_from_bytes_unsized(buffer: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(buffer, "type_list");
+        let size = reader.u32()? as usize;
+        let items = reader.bytes(size.saturating_mul(2))?;
+        let list = items
+            .chunks_exact(2)
+            .map(|item| read_u16_le(item, 0))
+            .collect();
+
+        Ok(Self { list })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorKind;
+
+    #[test]
+    fn test_type_list() {
+        let buffer = [0x02, 0x00, 0x00, 0x00, 0x05, 0x00, 0x01, 0x01];
+        let type_list = TypeList::try_parse_from_bytes_unsized(&buffer).unwrap();
+        assert_eq!(type_list.list, [5, 0x101]);
+    }
+
+    #[test]
+    fn test_truncated_type_list() {
+        let buffer = [0x02, 0x00, 0x00, 0x00, 0x05, 0x00, 0x01];
+        let e = TypeList::try_parse_from_bytes_unsized(&buffer).unwrap_err();
+        assert_eq!((e.structure, e.offset), ("type_list", 4));
+        assert!(matches!(
+            e.kind,
+            ErrorKind::UnexpectedEof {
+                needed: 4,
+                available: 3
+            }
+        ));
+    }
+
+    #[test]
+    fn test_huge_size_does_not_allocate() {
+        let buffer = [0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(TypeList::try_parse_from_bytes_unsized(&buffer).is_err());
+    }
+}
@@ -0,0 +1,859 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use crate::{
+    errors::{Error, StructureError},
+    traits::parse::TryParseFromBytes,
+    utils::Reader,
+};
+
+use super::{
+    class_data_item::ClassDataItem,
+    debug_info_item::DebugInfoItem,
+    encoded_value::{EncodedAnnotation, EncodedArray},
+    instruction::verify::TableSizes,
+    map_list::{MapItem, MapItemType},
+    try_item::{CatchHandler, TryItem},
+    Dex, NO_INDEX,
+};
+
+/// Offset of `file_size` in `header_item`
+const FILE_SIZE_OFFSET: usize = 0x20;
+/// Offset of `map_off` in `header_item`
+const MAP_OFF_OFFSET: usize = 0x34;
+
+impl Dex<'_> {
+    /// Checks the structural constraints of the dex format specification that parsing alone does not enforce, returning every violation found along with its offset.
+    ///
+    /// This covers a sane `file_size`, the alignment, order and extent of the sections listed in the `map_list` (which must not overlap, with the end of a section of variable-size items found by walking its items), the sort order of the id sections, the indices held by `field_ids`, `method_ids` and `class_defs`, the order of `class_defs`, and the `code_off` of every method.
+    pub fn verify_structure(&self) -> Vec<Error> {
+        let mut violations = Vec::new();
+        self.verify_file_size(&mut violations);
+        self.verify_sections(&mut violations);
+        self.verify_id_order(&mut violations);
+        self.verify_indices(&mut violations);
+        self.verify_class_order(&mut violations);
+        self.verify_code_offsets(&mut violations);
+        violations
+    }
+
+    /// Runs the bytecode checks of [`CodeItem::verify`](super::code_item::CodeItem::verify) on every method with code, returning every violation along with the method it was found in.
+    ///
+    /// Class data that fails to parse is skipped, as [`Self::verify_structure`] already reports it.
+    pub fn verify_methods(&self) -> Vec<Error> {
+        let tables = TableSizes::of(self);
+        let mut violations = Vec::new();
+        for class_def in &self.class_defs {
+            let Ok(class_data) = self.class_data(class_def.class_data_off) else {
+                continue;
+            };
+            let methods = class_data
+                .direct_methods
+                .iter()
+                .chain(&class_data.virtual_methods);
+            for method in methods.filter(|method| method.code_off != 0) {
+                let context = self
+                    .method_ids
+                    .get(method.method_idx as usize)
+                    .and_then(|method_id| method_id.to_human_readable(self).ok())
+                    .unwrap_or_else(|| format!("method {}", method.method_idx));
+                match self.code_item(method.code_off) {
+                    Ok(code_item) => violations.extend(
+                        code_item
+                            .verify(&tables)
+                            .into_iter()
+                            .map(|e| e.with_context(&context)),
+                    ),
+                    Err(e) => violations.push(e.with_context(&context)),
+                }
+            }
+        }
+        violations
+    }
+
+    /// Returns the offset from the start of the buffer to the end of this dex file.
+    fn file_end(&self) -> usize {
+        self.header_item.header_offset as usize + self.header_item.file_size as usize
+    }
+
+    /// Returns the bounds of the data section. Version 041 no longer declares one, so the whole file is used instead.
+    fn data_section(&self) -> (usize, usize) {
+        let header = &self.header_item;
+        if header.is_container() {
+            (header.header_offset as usize, self.file_end())
+        } else {
+            let data_off = header.data_off as usize;
+            (data_off, data_off + header.data_size as usize)
+        }
+    }
+
+    fn verify_file_size(&self, violations: &mut Vec<Error>) {
+        let header = &self.header_item;
+        let header_offset = header.header_offset as usize;
+        let available = self.raw.len().saturating_sub(header_offset);
+        let file_size = header.file_size as usize;
+        // Up to version 040 the file must be exactly as long as declared. From 041 it may be followed by the next dex of the container.
+        let fits = if header.is_container() {
+            file_size <= available
+        } else {
+            file_size == available
+        };
+        if !fits || file_size < header.header_size as usize {
+            violations.push(Error::new(
+                "header_item",
+                header_offset + FILE_SIZE_OFFSET,
+                StructureError::FileSize {
+                    declared: header.file_size,
+                    available,
+                },
+            ));
+        }
+    }
+
+    /// Returns the offset from the start of the buffer to the end of the section described by `item`, or `None` for a section of unknown type.
+    ///
+    /// Sections of variable-size items are walked item by item, with each item aligned as the spec requires. An item that cannot be read is returned as the error.
+    fn section_end(&self, item: &MapItem) -> Result<Option<usize>, Error> {
+        let offset = item.offset as usize;
+        let size = match item.type_ {
+            MapItemType::HeaderItem => self.header_item.header_size as usize,
+            MapItemType::MapList => 4 + self.map_list.list.len() * MapItem::SIZE,
+            MapItemType::Unknown(_) => return Ok(None),
+            type_ => match type_.item_size() {
+                Some(size) => size.saturating_mul(item.size as usize),
+                None => {
+                    let mut end = offset;
+                    for i in 0..item.size {
+                        // A misaligned first item is reported on its own, so it is read where it is
+                        let start = if i == 0 {
+                            end
+                        } else {
+                            end.next_multiple_of(type_.alignment())
+                        };
+                        end = self.item_end(type_, start)?;
+                    }
+                    return Ok(Some(end));
+                }
+            },
+        };
+        Ok(Some(offset.saturating_add(size)))
+    }
+
+    /// Returns the offset from the start of the buffer just past the variable-size item of type `type_` at `offset`.
+    fn item_end(&self, type_: MapItemType, offset: usize) -> Result<usize, Error> {
+        let mut reader = Reader::at(self.raw, offset, type_.name());
+        match type_ {
+            MapItemType::TypeList => {
+                let size = reader.u32()? as usize;
+                reader.bytes(size.saturating_mul(2))?;
+            }
+            MapItemType::AnnotationSetRefList | MapItemType::AnnotationSetItem => {
+                let size = reader.u32()? as usize;
+                reader.bytes(size.saturating_mul(4))?;
+            }
+            MapItemType::ClassDataItem => {
+                ClassDataItem::try_parse_from_reader(&mut reader)?;
+            }
+            MapItemType::CodeItem => {
+                reader.bytes(6)?; // registers_size, ins_size, outs_size
+                let tries_size = reader.u16()? as usize;
+                reader.u32()?; // debug_info_off
+                let insns_size = reader.u32()? as usize;
+                reader.bytes(insns_size.saturating_mul(2))?;
+                if tries_size != 0 {
+                    if insns_size % 2 == 1 {
+                        reader.u16()?;
+                    }
+                    reader.bytes(tries_size * TryItem::SIZE)?;
+                    let handlers_size = reader.uleb128("encoded_catch_handler_list size")?;
+                    for _ in 0..handlers_size {
+                        CatchHandler::try_parse_from_reader(&mut reader)?;
+                    }
+                }
+            }
+            MapItemType::StringDataItem => {
+                reader.uleb128("utf16_size")?;
+                // The MUTF-8 data never holds a zero byte before its terminator
+                while reader.u8()? != 0 {}
+            }
+            MapItemType::DebugInfoItem => {
+                DebugInfoItem::try_parse_from_reader(&mut reader)?;
+            }
+            MapItemType::AnnotationItem => {
+                reader.u8()?; // visibility
+                EncodedAnnotation::try_parse_from_reader(&mut reader)?;
+            }
+            MapItemType::EncodedArrayItem => {
+                EncodedArray::try_parse_from_reader(&mut reader)?;
+            }
+            MapItemType::AnnotationsDirectoryItem => {
+                reader.u32()?; // class_annotations_off
+                let mut entries = 0usize;
+                for _ in 0..3 {
+                    entries = entries.saturating_add(reader.u32()? as usize);
+                }
+                reader.bytes(entries.saturating_mul(8))?;
+            }
+            MapItemType::HiddenapiClassDataItem => {
+                // The item starts with its own size in bytes
+                let size = reader.u32()? as usize;
+                reader.bytes(size.saturating_sub(4))?;
+            }
+            // Fixed-size items are measured by `section_end` and never walked
+            _ => {}
+        }
+        Ok(reader.offset)
+    }
+
+    fn verify_sections(&self, violations: &mut Vec<Error>) {
+        let header = &self.header_item;
+        let header_offset = header.header_offset as usize;
+        let map_off = header.map_off as usize;
+        let file_end = self.file_end();
+
+        let mut seen = HashSet::new();
+        // The previous section, and the section reaching furthest into the file along with where it ends
+        let mut previous: Option<&MapItem> = None;
+        let mut furthest: Option<(&MapItem, usize)> = None;
+        for (i, item) in self.map_list.list.iter().enumerate() {
+            let item_offset = map_off + 4 + i * MapItem::SIZE;
+            let section = item.type_.name();
+            let end = match self.section_end(item) {
+                Ok(end) => end.unwrap_or(item.offset as usize),
+                Err(e) => {
+                    // The rest of the section cannot be located, so later sections are not checked against it
+                    violations.push(e);
+                    item.offset as usize
+                }
+            };
+            let mut report = |e: StructureError| {
+                violations.push(Error::new(MapItem::NAME, item_offset, e));
+            };
+
+            if !seen.insert(item.type_) {
+                report(StructureError::DuplicateSection { section });
+            }
+            let alignment = item.type_.alignment();
+            if !(item.offset as usize).is_multiple_of(alignment) {
+                report(StructureError::Misaligned { section, alignment });
+            }
+            if let Some(previous) = previous {
+                if item.offset < previous.offset {
+                    report(StructureError::UnsortedSection {
+                        section,
+                        offset: item.offset,
+                        previous: previous.offset,
+                    });
+                }
+            }
+            if let Some((other, other_end)) = furthest {
+                if item.offset >= other.offset && (item.offset as usize) < other_end {
+                    report(StructureError::Overlap {
+                        section,
+                        offset: item.offset,
+                        other: other.type_.name(),
+                        other_end,
+                    });
+                }
+            }
+            if end > file_end {
+                report(StructureError::PastEnd {
+                    section,
+                    offset: item.offset,
+                    end,
+                    file_end,
+                });
+            }
+            previous = Some(item);
+            if furthest.is_none_or(|(_, furthest_end)| end > furthest_end) {
+                furthest = Some((item, end));
+            }
+        }
+
+        // The header repeats the offset and size of the id sections and the map_list, and both copies must agree. Each entry holds the section, the offset of its fields in the header, and their values.
+        let header_sections = [
+            (MapItemType::MapList, MAP_OFF_OFFSET, 1, header.map_off),
+            (
+                MapItemType::StringIdItem,
+                0x38,
+                header.string_ids_size,
+                header.string_ids_off,
+            ),
+            (
+                MapItemType::TypeIdItem,
+                0x40,
+                header.type_ids_size,
+                header.type_ids_off,
+            ),
+            (
+                MapItemType::ProtoIdItem,
+                0x48,
+                header.proto_ids_size,
+                header.proto_ids_off,
+            ),
+            (
+                MapItemType::FieldIdItem,
+                0x50,
+                header.field_ids_size,
+                header.field_ids_off,
+            ),
+            (
+                MapItemType::MethodIdItem,
+                0x58,
+                header.method_ids_size,
+                header.method_ids_off,
+            ),
+            (
+                MapItemType::ClassDefItem,
+                0x60,
+                header.class_defs_size,
+                header.class_defs_off,
+            ),
+        ];
+        for (type_, field_offset, size, offset) in header_sections {
+            let (map_size, map_offset) = self
+                .map_list
+                .get(type_)
+                .map_or((0, 0), |item| (item.size, item.offset));
+            if size != map_size || (size != 0 && offset != map_offset) {
+                violations.push(Error::new(
+                    "header_item",
+                    header_offset + field_offset,
+                    StructureError::HeaderMismatch {
+                        section: type_.name(),
+                        offset,
+                        size,
+                        map_offset,
+                        map_size,
+                    },
+                ));
+            }
+        }
+    }
+
+    fn verify_id_order(&self, violations: &mut Vec<Error>) {
+        let header = &self.header_item;
+
+        // Strings are sorted by their UTF-16 code units. Strings that fail to decode have already been reported and are skipped.
+        let strings = (0..self.strings.len()).map(|idx| self.string_utf16(idx).ok());
+        check_sorted(
+            strings,
+            "string_id_item",
+            header.string_ids_off,
+            4,
+            violations,
+        );
+
+        if let Ok(descriptor_idxs) = Self::read_u32_section(
+            self.raw,
+            header.type_ids_off,
+            header.type_ids_size,
+            "type_id_item",
+        ) {
+            for (i, descriptor_idx) in descriptor_idxs.iter().enumerate() {
+                check_index(
+                    "descriptor_idx",
+                    *descriptor_idx,
+                    self.strings.len(),
+                    "type_id_item",
+                    header.type_ids_off as usize + i * 4,
+                    violations,
+                );
+            }
+            check_sorted(
+                descriptor_idxs.into_iter().map(Some),
+                "type_id_item",
+                header.type_ids_off,
+                4,
+                violations,
+            );
+        }
+
+        let protos = self.proto_ids.iter().map(|proto| {
+            let parameters = self.type_list(proto.parameters_off).ok()?;
+            Some((proto.return_type_idx, parameters.list))
+        });
+        check_sorted(
+            protos,
+            "proto_id_item",
+            header.proto_ids_off,
+            12,
+            violations,
+        );
+
+        let fields = self
+            .field_ids
+            .iter()
+            .map(|field| Some((field.class_idx, field.name_idx, field.type_idx)));
+        check_sorted(fields, "field_id_item", header.field_ids_off, 8, violations);
+
+        let methods = self
+            .method_ids
+            .iter()
+            .map(|method| Some((method.class_idx, method.name_idx, method.proto_idx)));
+        check_sorted(
+            methods,
+            "method_id_item",
+            header.method_ids_off,
+            8,
+            violations,
+        );
+    }
+
+    fn verify_indices(&self, violations: &mut Vec<Error>) {
+        let header = &self.header_item;
+        let strings = self.strings.len();
+        let types = self.types.len();
+
+        for (i, field) in self.field_ids.iter().enumerate() {
+            let offset = header.field_ids_off as usize + i * 8;
+            let structure = "field_id_item";
+            check_index(
+                "class_idx",
+                field.class_idx.into(),
+                types,
+                structure,
+                offset,
+                violations,
+            );
+            check_index(
+                "type_idx",
+                field.type_idx.into(),
+                types,
+                structure,
+                offset + 2,
+                violations,
+            );
+            check_index(
+                "name_idx",
+                field.name_idx,
+                strings,
+                structure,
+                offset + 4,
+                violations,
+            );
+        }
+
+        for (i, method) in self.method_ids.iter().enumerate() {
+            let offset = header.method_ids_off as usize + i * 8;
+            let structure = "method_id_item";
+            check_index(
+                "class_idx",
+                method.class_idx.into(),
+                types,
+                structure,
+                offset,
+                violations,
+            );
+            let protos = self.proto_ids.len();
+            check_index(
+                "proto_idx",
+                method.proto_idx.into(),
+                protos,
+                structure,
+                offset + 2,
+                violations,
+            );
+            check_index(
+                "name_idx",
+                method.name_idx,
+                strings,
+                structure,
+                offset + 4,
+                violations,
+            );
+        }
+
+        for (i, class_def) in self.class_defs.iter().enumerate() {
+            let offset = header.class_defs_off as usize + i * 32;
+            let structure = "class_def_item";
+            check_index(
+                "class_idx",
+                class_def.class_idx,
+                types,
+                structure,
+                offset,
+                violations,
+            );
+            if class_def.superclass_idx != NO_INDEX {
+                check_index(
+                    "superclass_idx",
+                    class_def.superclass_idx,
+                    types,
+                    structure,
+                    offset + 8,
+                    violations,
+                );
+            }
+            if class_def.source_file_idx != NO_INDEX {
+                check_index(
+                    "source_file_idx",
+                    class_def.source_file_idx,
+                    strings,
+                    structure,
+                    offset + 16,
+                    violations,
+                );
+            }
+            match self.type_list(class_def.interfaces_off) {
+                Ok(interfaces) => {
+                    for interface in interfaces.list {
+                        check_index(
+                            "interface type_idx",
+                            interface.into(),
+                            types,
+                            structure,
+                            offset + 12,
+                            violations,
+                        );
+                    }
+                }
+                Err(e) => violations.push(e),
+            }
+        }
+    }
+
+    /// Checks that no class is defined twice, and that each class comes after its superclass and interfaces when those are defined in the same file.
+    fn verify_class_order(&self, violations: &mut Vec<Error>) {
+        let mut defined = HashMap::new();
+        for (i, class_def) in self.class_defs.iter().enumerate() {
+            let offset = self.header_item.class_defs_off as usize + i * 32;
+            match defined.entry(class_def.class_idx) {
+                Entry::Vacant(entry) => {
+                    entry.insert(i);
+                }
+                Entry::Occupied(entry) => violations.push(Error::new(
+                    "class_def_item",
+                    offset,
+                    StructureError::DuplicateClass {
+                        first: *entry.get(),
+                    },
+                )),
+            }
+        }
+
+        for (i, class_def) in self.class_defs.iter().enumerate() {
+            let offset = self.header_item.class_defs_off as usize + i * 32;
+            let interfaces = self
+                .type_list(class_def.interfaces_off)
+                .map(|interfaces| interfaces.list)
+                .unwrap_or_default();
+            let supertypes = (class_def.superclass_idx != NO_INDEX)
+                .then_some(class_def.superclass_idx)
+                .into_iter()
+                .chain(interfaces.into_iter().map(u32::from));
+            for supertype in supertypes {
+                if defined.get(&supertype).is_some_and(|j| *j >= i) {
+                    let supertype = self
+                        .types
+                        .get(supertype as usize)
+                        .map_or_else(|| supertype.to_string(), |name| name.to_string());
+                    violations.push(Error::new(
+                        "class_def_item",
+                        offset,
+                        StructureError::DefinedBeforeSupertype { supertype },
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Checks that every method with code points into the data section at a 4-byte aligned `code_item`.
+    fn verify_code_offsets(&self, violations: &mut Vec<Error>) {
+        let (data_start, data_end) = self.data_section();
+        for class_def in &self.class_defs {
+            let class_data = match self.class_data(class_def.class_data_off) {
+                Ok(class_data) => class_data,
+                Err(e) => {
+                    violations.push(e);
+                    continue;
+                }
+            };
+            let methods = class_data
+                .direct_methods
+                .iter()
+                .chain(&class_data.virtual_methods);
+            for method in methods.filter(|method| method.code_off != 0) {
+                let code_off = method.code_off as usize;
+                let violation = if code_off < data_start || code_off >= data_end {
+                    StructureError::OutsideSection {
+                        field: "code_off",
+                        offset: method.code_off,
+                        section: "data",
+                    }
+                } else if !code_off.is_multiple_of(MapItemType::CodeItem.alignment()) {
+                    StructureError::Misaligned {
+                        section: "code_item",
+                        alignment: MapItemType::CodeItem.alignment(),
+                    }
+                } else {
+                    continue;
+                };
+                let mut e = Error::new(
+                    "class_data_item",
+                    class_def.class_data_off as usize,
+                    violation,
+                );
+                let method_id = self.method_ids.get(method.method_idx as usize);
+                if let Some(Ok(method)) =
+                    method_id.map(|method_id| method_id.to_human_readable(self))
+                {
+                    e = e.with_context(method);
+                }
+                violations.push(e);
+            }
+        }
+    }
+}
+
+/// Reports every key of an id section that is not strictly greater than the one before it. `None` keys could not be read and are skipped along with their neighbours.
+fn check_sorted<K: Ord>(
+    keys: impl Iterator<Item = Option<K>>,
+    structure: &'static str,
+    section_off: u32,
+    item_size: usize,
+    violations: &mut Vec<Error>,
+) {
+    let mut previous = None;
+    for (idx, key) in keys.enumerate() {
+        if let (Some(previous), Some(key)) = (&previous, &key) {
+            if key <= previous {
+                violations.push(Error::new(
+                    structure,
+                    section_off as usize + idx * item_size,
+                    StructureError::Unsorted { idx },
+                ));
+            }
+        }
+        previous = key;
+    }
+}
+
+/// Reports `idx` if it does not index a table of `size` entries.
+fn check_index(
+    field: &'static str,
+    idx: u32,
+    size: usize,
+    structure: &'static str,
+    offset: usize,
+    violations: &mut Vec<Error>,
+) {
+    if idx as usize >= size {
+        violations.push(Error::new(
+            structure,
+            offset,
+            StructureError::IndexOutOfRange { field, idx, size },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dex::fixture::{seal, DexBuilder},
+        diagnostics::Diagnostics,
+        errors::ErrorKind,
+        utils::read_u32_le,
+    };
+
+    /// A class `LFoo;` with one method holding a `return-void`, its `class_data_item` and `code_item` declared in the `map_list`.
+    fn builder() -> DexBuilder {
+        let mut builder = DexBuilder::new();
+        let class_idx = builder.type_("LFoo;");
+        let superclass_idx = builder.type_("Ljava/lang/Object;");
+        let proto_idx = builder.proto("V", "V", &[]);
+        let method_idx = builder.method("LFoo;", proto_idx, "run");
+        #[rustfmt::skip]
+        let code_off = builder.data(&[
+            1, 0, 1, 0, 0, 0, 0, 0, // registers_size, ins_size, outs_size, tries_size
+            0, 0, 0, 0, 1, 0, 0, 0, // debug_info_off, insns_size
+            0x0e, 0x00, // return-void
+        ]);
+        builder.map_item(0x2001, 1, code_off);
+        // one public direct method at code_off 0x400
+        assert_eq!(code_off, 0x400);
+        let class_data_off = builder.data(&[0, 0, 1, 0, method_idx as u8, 0x01, 0x80, 0x08]);
+        builder.map_item(0x2000, 1, class_data_off);
+        builder.class_def([
+            class_idx,
+            0x0001,
+            superclass_idx,
+            0,
+            NO_INDEX,
+            0,
+            class_data_off,
+            0,
+        ]);
+        builder
+    }
+
+    fn verify(file: &[u8]) -> Vec<Error> {
+        let diagnostics = Diagnostics::new(true);
+        let dex = Dex::try_parse_from_bytes(file, &diagnostics).unwrap();
+        dex.verify_structure()
+    }
+
+    #[test]
+    fn test_clean_file() {
+        let violations = verify(&builder().build());
+        assert!(violations.is_empty(), "{violations:?}");
+    }
+
+    #[test]
+    fn test_overlapping_sections() {
+        let mut builder = builder();
+        // a type_list of three entries, so ten bytes long, and an encoded_array_item declared in its last two bytes
+        let type_list_off = builder.data(&[3, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        builder.map_item(0x1001, 1, type_list_off);
+        builder.map_item(0x2005, 1, type_list_off + 8);
+
+        let violations = verify(&builder.build());
+        assert_eq!(violations.len(), 1, "{violations:?}");
+        assert_eq!(violations[0].structure, "map_item");
+        assert!(matches!(
+            violations[0].kind,
+            ErrorKind::Structure(StructureError::Overlap {
+                section: "encoded_array_item",
+                other: "type_list",
+                ..
+            })
+        ));
+        assert_eq!(
+            violations[0].kind.to_string(),
+            format!(
+                "encoded_array_item at {:#x} overlaps type_list, which ends at {:#x}",
+                type_list_off + 8,
+                type_list_off + 10
+            )
+        );
+    }
+
+    #[test]
+    fn test_items_are_walked_with_alignment() {
+        let mut builder = builder();
+        // two type_lists of one entry, the second aligned to 4 bytes, so the section ends 14 bytes in
+        let type_list_off = builder.data(&[1, 0, 0, 0, 0, 0]);
+        builder.data(&[1, 0, 0, 0, 0, 0]);
+        builder.map_item(0x1001, 2, type_list_off);
+        builder.map_item(0x2005, 1, type_list_off + 13);
+
+        let violations = verify(&builder.build());
+        assert_eq!(violations.len(), 1, "{violations:?}");
+        assert!(matches!(
+            violations[0].kind,
+            ErrorKind::Structure(StructureError::Overlap { other_end, .. })
+                if other_end == type_list_off as usize + 14
+        ));
+    }
+
+    #[test]
+    fn test_misaligned_section() {
+        let mut builder = builder();
+        let offset = builder.data(&[0; 6]);
+        builder.map_item(0x1003, 1, offset + 2);
+
+        let violations = verify(&builder.build());
+        assert_eq!(violations.len(), 1, "{violations:?}");
+        assert!(matches!(
+            violations[0].kind,
+            ErrorKind::Structure(StructureError::Misaligned {
+                section: "annotation_set_item",
+                alignment: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn test_unsorted_ids() {
+        let mut builder = builder();
+        builder.string("x");
+        builder.string("w");
+        let file = builder.build();
+        let string_ids_off = read_u32_le(&file, 0x3C) as usize;
+
+        let violations = verify(&file);
+        assert_eq!(violations.len(), 1, "{violations:?}");
+        assert_eq!(violations[0].structure, "string_id_item");
+        // `w` is the sixth string
+        assert_eq!(violations[0].offset, string_ids_off + 5 * 4);
+        assert!(matches!(
+            violations[0].kind,
+            ErrorKind::Structure(StructureError::Unsorted { idx: 5 })
+        ));
+    }
+
+    #[test]
+    fn test_file_size_mismatch() {
+        let mut file = builder().build();
+        let file_size = file.len();
+        file.extend([0; 4]);
+        seal(&mut file);
+
+        let violations = verify(&file);
+        assert_eq!(violations.len(), 1, "{violations:?}");
+        assert_eq!(violations[0].offset, FILE_SIZE_OFFSET);
+        assert!(matches!(
+            violations[0].kind,
+            ErrorKind::Structure(StructureError::FileSize { declared, available })
+                if declared as usize == file_size && available == file_size + 4
+        ));
+    }
+
+    #[test]
+    fn test_indices_out_of_range() {
+        let mut file = builder().build();
+        let method_ids_off = read_u32_le(&file, 0x5C) as usize;
+        let class_defs_off = read_u32_le(&file, 0x64) as usize;
+        // the name of the method and the superclass of the class
+        file[method_ids_off + 4..method_ids_off + 8].copy_from_slice(&40u32.to_le_bytes());
+        file[class_defs_off + 8..class_defs_off + 12].copy_from_slice(&9u32.to_le_bytes());
+        seal(&mut file);
+
+        let violations = verify(&file);
+        let found: Vec<_> = violations
+            .iter()
+            .map(|e| (e.structure, e.offset, e.kind.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "method_id_item",
+                    method_ids_off + 4,
+                    String::from("name_idx 40 is out of range, there are 4 entries")
+                ),
+                (
+                    "class_def_item",
+                    class_defs_off + 8,
+                    String::from("superclass_idx 9 is out of range, there are 3 entries")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_sorted() {
+        let mut violations = Vec::new();
+        let keys = [Some(1), Some(3), Some(3), None, Some(2), Some(0)];
+        check_sorted(keys.into_iter(), "type_id_item", 0x40, 4, &mut violations);
+        // The duplicate 3 is reported, 2 is skipped as it follows an unreadable entry, and 0 is out of order
+        let offsets: Vec<_> = violations.iter().map(|e| e.offset).collect();
+        assert_eq!(offsets, [0x48, 0x54]);
+        assert_eq!(
+            violations[1].kind.to_string(),
+            "Entry 5 is not sorted strictly after the previous entry"
+        );
+    }
+
+    #[test]
+    fn test_check_index() {
+        let mut violations = Vec::new();
+        check_index("name_idx", 3, 4, "field_id_item", 0x10, &mut violations);
+        check_index("name_idx", 4, 4, "field_id_item", 0x18, &mut violations);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].to_string(),
+            "Failed to parse field_id_item at offset 0x18: name_idx 4 is out of range, there are 4 entries"
+        );
+    }
+}
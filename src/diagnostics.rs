@@ -0,0 +1,218 @@
+use std::{fmt, sync::Mutex};
+
+use crate::errors::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// the output is usable but may be incomplete, e.g. an unresolvable index was left out
+    Warning,
+    /// a structure could not be parsed and was replaced by a placeholder
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => f.write_str("warning"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A single problem found while reading or rendering a dex file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// name of the structure the problem was found in, if it came from a parser
+    pub structure: Option<&'static str>,
+    /// offset from the start of the file, if known
+    pub offset: Option<usize>,
+    /// the class or method being read, if known
+    pub context: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = [
+            self.structure.map(String::from),
+            self.offset.map(|offset| format!("at offset {offset:#x}")),
+            self.context.as_ref().map(|context| format!("in {context}")),
+        ];
+        let location: Vec<String> = location.into_iter().flatten().collect();
+        write!(f, "{}: ", self.severity)?;
+        if !location.is_empty() {
+            write!(f, "{}: ", location.join(" "))?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl From<Error> for Diagnostic {
+    fn from(error: Error) -> Self {
+        Self {
+            severity: Severity::Error,
+            structure: Some(error.structure),
+            offset: Some(error.offset),
+            context: error.context,
+            message: error.kind.to_string(),
+        }
+    }
+}
+
+/// Collects the problems found while reading a dex file instead of printing them as they happen.
+///
+/// In lenient mode a structure that fails to parse is recorded and replaced by a placeholder, so that tables keep their indices and the rest of the file can still be read. In strict mode the first error is returned instead.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    strict: bool,
+    entries: Mutex<Vec<Diagnostic>>,
+}
+
+impl Diagnostics {
+    pub fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            entries: Mutex::default(),
+        }
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    fn push(&self, diagnostic: Diagnostic) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(diagnostic);
+    }
+
+    /// Records `error` regardless of the mode, for problems that have already been worked around.
+    pub fn record(&self, error: Error) {
+        self.push(Diagnostic::from(error));
+    }
+
+    /// Records a problem that does not stop the structure from being used.
+    pub fn warning(&self, context: Option<&str>, message: impl Into<String>) {
+        self.push(Diagnostic {
+            severity: Severity::Warning,
+            structure: None,
+            offset: None,
+            context: context.map(String::from),
+            message: message.into(),
+        });
+    }
+
+    /// Passes `result` through in strict mode. In lenient mode an error is recorded and replaced by `fallback()`.
+    pub fn recover<T>(
+        &self,
+        result: Result<T, Error>,
+        fallback: impl FnOnce() -> T,
+    ) -> Result<T, Error> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(e) if self.strict => Err(e),
+            Err(e) => {
+                self.record(e);
+                Ok(fallback())
+            }
+        }
+    }
+
+    /// Returns everything recorded so far, in the order it was found.
+    pub fn entries(&self) -> Vec<Diagnostic> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Renders every diagnostic as one JSON object per line, with the keys `severity`, `structure`, `offset`, `context` and `message`. Unknown values are `null`.
+    pub fn to_json_lines(&self) -> String {
+        let mut out = String::new();
+        for diagnostic in self.entries() {
+            out.push_str(&format!(
+                "{{\"severity\":\"{}\",\"structure\":{},\"offset\":{},\"context\":{},\"message\":{}}}\n",
+                diagnostic.severity,
+                json_string(diagnostic.structure),
+                diagnostic
+                    .offset
+                    .map_or(String::from("null"), |offset| offset.to_string()),
+                json_string(diagnostic.context.as_deref()),
+                json_string(Some(&diagnostic.message)),
+            ));
+        }
+        out
+    }
+}
+
+/// Renders `value` as a JSON string literal, or `null`.
+fn json_string(value: Option<&str>) -> String {
+    let Some(value) = value else {
+        return String::from("null");
+    };
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorKind;
+
+    fn truncated() -> Result<u32, Error> {
+        Err(Error::new(
+            "type_list",
+            0x40,
+            ErrorKind::UnexpectedEof {
+                needed: 4,
+                available: 1,
+            },
+        ))
+    }
+
+    #[test]
+    fn test_lenient_recovers() {
+        let diagnostics = Diagnostics::new(false);
+        assert_eq!(diagnostics.recover(truncated(), || 7).unwrap(), 7);
+        diagnostics.warning(Some("LFoo;"), "Invalid \"type\"");
+        let entries = diagnostics.entries();
+        assert_eq!(
+            entries[0].to_string(),
+            "error: type_list at offset 0x40: Unexpected end of data, needed 4 bytes but only 1 are left"
+        );
+        assert_eq!(
+            entries[1].to_string(),
+            "warning: in LFoo;: Invalid \"type\""
+        );
+        assert_eq!(
+            diagnostics.to_json_lines(),
+            concat!(
+                "{\"severity\":\"error\",\"structure\":\"type_list\",\"offset\":64,\"context\":null,",
+                "\"message\":\"Unexpected end of data, needed 4 bytes but only 1 are left\"}\n",
+                "{\"severity\":\"warning\",\"structure\":null,\"offset\":null,\"context\":\"LFoo;\",",
+                "\"message\":\"Invalid \\\"type\\\"\"}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_strict_fails_fast() {
+        let diagnostics = Diagnostics::new(true);
+        assert!(diagnostics.recover(truncated(), || 7).is_err());
+        assert!(diagnostics.entries().is_empty());
+    }
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::utils::hex_literal;
+
 #[derive(Debug, Error)]
 pub enum InstructionError {
     #[error("Unknown opcode: {0:x}")]
@@ -12,6 +14,22 @@ pub enum InstructionError {
         expected: usize,
         actual: usize,
     },
+    /// Raised by `Instruction::encode`, which is only built for tests
+    #[cfg(test)]
+    #[error("Operand {value} of {opcode} does not fit in {bits} bits")]
+    OperandRange {
+        opcode: &'static str,
+        value: i64,
+        bits: u32,
+    },
+    /// Raised by `Instruction::encode`, which is only built for tests
+    #[cfg(test)]
+    #[error("Invalid {payload} length, expected {expected}, got {actual}")]
+    PayloadLength {
+        payload: &'static str,
+        expected: usize,
+        actual: usize,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -22,6 +40,12 @@ pub enum DexParseError {
         expected: usize,
         actual: usize,
     },
+    #[error("Invalid magic \"{}\", expected \"dex\\n0NN\\0\" with a known version", magic.escape_ascii())]
+    InvalidMagic { magic: [u8; 8] },
+    #[error("Reverse-endian dex files are not supported")]
+    ReverseEndian,
+    #[error("Invalid endian tag: {0:#010x}")]
+    InvalidEndianTag(u32),
 }
 
 #[derive(Debug, Error)]
@@ -40,4 +64,256 @@ pub enum TableIdxError {
     CallSite(usize),
     #[error("Invalid method handle idx: {0}")]
     MethodHandle(usize),
+    #[error(transparent)]
+    TypeList(Box<Error>),
+}
+
+#[derive(Debug, Error)]
+pub enum StringDataError {
+    #[error("Invalid string idx: {0}")]
+    Index(usize),
+    #[error("Failed to decode ULEB128 for string length at offset {0}")]
+    Length(usize),
+    #[error("String data at offset {0} is not NUL-terminated")]
+    Unterminated(usize),
+    #[error("Invalid MUTF-8 byte {byte:#04x} at offset {offset}")]
+    InvalidByte { offset: usize, byte: u8 },
+    #[error("String length mismatch, expected {expected} UTF-16 code units, got {actual}")]
+    LengthMismatch { expected: u64, actual: u64 },
+}
+
+#[derive(Debug, Error)]
+pub enum IntegrityError {
+    #[error("File is truncated, header declares {expected} bytes but only {actual} are present")]
+    Truncated { expected: usize, actual: usize },
+    #[error(
+        "Checksum mismatch, header declares {expected:#010x} but contents hash to {actual:#010x}"
+    )]
+    Checksum { expected: u32, actual: u32 },
+    #[error(
+        "Signature mismatch, header declares {} but contents hash to {}",
+        hex(expected),
+        hex(actual)
+    )]
+    Signature {
+        expected: [u8; 20],
+        actual: [u8; 20],
+    },
+}
+
+/// A violation of the structural constraints of the dex format, as found by `Dex::verify_structure`.
+#[derive(Debug, Error)]
+pub enum StructureError {
+    #[error("Declared file_size {declared:#x} does not fit the {available:#x} bytes available")]
+    FileSize { declared: u32, available: usize },
+    #[error("{section} is not aligned to {alignment} bytes")]
+    Misaligned {
+        section: &'static str,
+        alignment: usize,
+    },
+    #[error("{section} appears more than once in the map_list")]
+    DuplicateSection { section: &'static str },
+    #[error("{section} at {offset:#x} starts before the previous section at {previous:#x}")]
+    UnsortedSection {
+        section: &'static str,
+        offset: u32,
+        previous: u32,
+    },
+    #[error("{section} at {offset:#x} overlaps {other}, which ends at {other_end:#x}")]
+    Overlap {
+        section: &'static str,
+        offset: u32,
+        other: &'static str,
+        other_end: usize,
+    },
+    #[error(
+        "{section} at {offset:#x} ends at {end:#x}, past the end of the file at {file_end:#x}"
+    )]
+    PastEnd {
+        section: &'static str,
+        offset: u32,
+        end: usize,
+        file_end: usize,
+    },
+    #[error("Header declares {size} {section} at {offset:#x}, but the map_list declares {map_size} at {map_offset:#x}")]
+    HeaderMismatch {
+        section: &'static str,
+        offset: u32,
+        size: u32,
+        map_offset: u32,
+        map_size: u32,
+    },
+    #[error("Entry {idx} is not sorted strictly after the previous entry")]
+    Unsorted { idx: usize },
+    #[error("{field} {idx} is out of range, there are {size} entries")]
+    IndexOutOfRange {
+        field: &'static str,
+        idx: u32,
+        size: usize,
+    },
+    #[error("Class is already defined by class_def {first}")]
+    DuplicateClass { first: usize },
+    #[error("Class is defined before its supertype {supertype}")]
+    DefinedBeforeSupertype { supertype: String },
+    #[error("{field} {offset:#x} is outside the {section} section")]
+    OutsideSection {
+        field: &'static str,
+        offset: u32,
+        section: &'static str,
+    },
+}
+
+/// A violation of the constraints on a method's bytecode, as found by `verify_instructions`.
+#[derive(Debug, Error)]
+pub enum BytecodeError {
+    #[error("Register v{register} is out of range, the method has {registers_size} registers")]
+    RegisterOutOfRange { register: u32, registers_size: u16 },
+    #[error("Wide register pair v{register}/v{} is out of range, the method has {registers_size} registers", register + 1)]
+    WidePairOutOfRange { register: u32, registers_size: u16 },
+    #[error("Branch offset of zero")]
+    ZeroBranchOffset,
+    #[error("Branch target {} is not the start of an instruction", hex_literal(*target, ""))]
+    BranchTarget { target: i64 },
+    #[error("Payload target {} is not a {expected}", hex_literal(*target, ""))]
+    PayloadTarget { target: i64, expected: &'static str },
+    #[error("Payload at {} is not 4-byte aligned", hex_literal(*target, ""))]
+    MisalignedPayload { target: i64 },
+    #[error("Execution can continue past the end of the code")]
+    FallsOffEnd,
+    #[error("Execution can continue into the payload at {address:#x}")]
+    FallsIntoPayload { address: u32 },
+    #[error("{opcode} does not immediately follow an invoke or filled-new-array")]
+    MisplacedMoveResult { opcode: &'static str },
+    #[error("{kind} index {idx} is out of range, there are {size} entries")]
+    IndexOutOfRange {
+        kind: &'static str,
+        idx: u32,
+        size: usize,
+    },
+}
+
+/// What went wrong while reading a structure, independent of where it happened.
+#[derive(Debug, Error)]
+pub enum ErrorKind {
+    #[error("Unexpected end of data, needed {needed} bytes but only {available} are left")]
+    UnexpectedEof { needed: usize, available: usize },
+    #[error("Failed to decode LEB128 for {0}")]
+    Leb128(&'static str),
+    #[error("{0}")]
+    Malformed(String),
+    #[error(transparent)]
+    Parse(#[from] DexParseError),
+    #[error(transparent)]
+    TableIdx(#[from] TableIdxError),
+    #[error(transparent)]
+    Instruction(#[from] InstructionError),
+    #[error(transparent)]
+    StringData(#[from] StringDataError),
+    #[error(transparent)]
+    Integrity(#[from] IntegrityError),
+    #[error(transparent)]
+    Structure(#[from] StructureError),
+    #[error(transparent)]
+    Bytecode(#[from] BytecodeError),
+}
+
+/// The error returned by every parser: the structure that failed, its absolute offset in the file, the class or method being read if known, and the cause.
+#[derive(Debug, Error)]
+#[error(
+    "Failed to parse {structure} at offset {offset:#x}{}: {kind}",
+    context_suffix(context)
+)]
+pub struct Error {
+    /// name of the structure as used by the dex format specification, e.g. `code_item`
+    pub structure: &'static str,
+    /// offset from the start of the file
+    pub offset: usize,
+    /// the class or method the structure belongs to
+    pub context: Option<String>,
+    #[source]
+    pub kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new(structure: &'static str, offset: usize, kind: impl Into<ErrorKind>) -> Self {
+        Self {
+            structure,
+            offset,
+            context: None,
+            kind: kind.into(),
+        }
+    }
+
+    /// Moves the error by `base` bytes, for parsers that only see the part of the file starting at `base`.
+    pub fn offset_by(mut self, base: usize) -> Self {
+        self.offset += base;
+        self
+    }
+
+    /// Records the class or method being read, unless a more specific one is already known.
+    pub fn with_context(mut self, context: impl Into<String>) -> Self {
+        self.context.get_or_insert_with(|| context.into());
+        self
+    }
+
+    /// Records what encloses the current context, e.g. the dex file of a container around a method, keeping the context already known.
+    pub fn within(mut self, outer: impl Into<String>) -> Self {
+        let outer = outer.into();
+        self.context = Some(match self.context.take() {
+            Some(context) => format!("{context} in {outer}"),
+            None => outer,
+        });
+        self
+    }
+}
+
+fn context_suffix(context: &Option<String>) -> String {
+    context
+        .as_ref()
+        .map(|context| format!(" in {context}"))
+        .unwrap_or_default()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context() {
+        let e = || Error::new("code_item", 0x40, ErrorKind::Malformed(String::from("bad")));
+
+        let method = e().with_context("LFoo;->bar()V").with_context("LFoo;");
+        assert_eq!(method.context.as_deref(), Some("LFoo;->bar()V"));
+
+        let in_dex = e().with_context("LFoo;->bar()V").within("dex 1");
+        assert_eq!(
+            in_dex.to_string(),
+            "Failed to parse code_item at offset 0x40 in LFoo;->bar()V in dex 1: bad"
+        );
+        assert_eq!(e().within("dex 1").context.as_deref(), Some("dex 1"));
+    }
+
+    #[test]
+    fn test_bytecode_targets_are_signed() {
+        assert_eq!(
+            BytecodeError::BranchTarget { target: -2 }.to_string(),
+            "Branch target -0x2 is not the start of an instruction"
+        );
+        let e = BytecodeError::PayloadTarget {
+            target: 0x10,
+            expected: "packed-switch-payload",
+        };
+        assert_eq!(
+            e.to_string(),
+            "Payload target 0x10 is not a packed-switch-payload"
+        );
+        assert_eq!(
+            BytecodeError::MisalignedPayload { target: -3 }.to_string(),
+            "Payload at -0x3 is not 4-byte aligned"
+        );
+    }
 }
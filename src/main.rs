@@ -1,34 +1,346 @@
-use std::{fs::File, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    path::Path,
+};
 use rayon::prelude::*;
-use dex::{class_data_item::ClassDataItem, code_item::CodeItem, Dex};
+use dex::{
+    access_flags::{AccessFlags, AccessFlagsContext},
+    annotation_item::AnnotationItem,
+    class_data_item::ClassDataItem,
+    class_def_item::ClassDefItem,
+    code_item::CodeItem,
+    container::DexContainer,
+    Dex, NO_INDEX,
+};
+use diagnostics::{Diagnostics, Severity};
+use errors::{Error, ErrorKind, IntegrityError};
 
 mod dex;
+mod diagnostics;
 mod errors;
 mod traits;
 mod utils;
 
+/// Hands a parse error to the diagnostics of `dex`, substituting `fallback()` in lenient mode. In strict mode the error is returned and stops the class.
+fn recover<T>(
+    dex: &Dex,
+    result: Result<T, Error>,
+    context: &str,
+    fallback: impl FnOnce() -> T,
+) -> Result<T, std::io::Error> {
+    dex.diagnostics
+        .recover(result.map_err(|e| e.with_context(context)), fallback)
+        .map_err(std::io::Error::other)
+}
+
+/// Renders `access_flags` followed by a space, or nothing if no flag is set, so it can prefix the item name.
+fn access_flags_prefix(
+    dex: &Dex,
+    access_flags: AccessFlags,
+    context: AccessFlagsContext,
+    name: &str,
+) -> String {
+    let unknown_bits = access_flags.unknown_bits(context);
+    if unknown_bits != 0 {
+        dex.diagnostics.warning(
+            Some(name),
+            format!("Unknown access flags {unknown_bits:#x}"),
+        );
+    }
+
+    let flags = access_flags.to_human_readable(context);
+    if flags.is_empty() {
+        flags
+    } else {
+        format!("{flags} ")
+    }
+}
+
+fn write_annotations<W: std::io::Write>(
+    writer: &mut W,
+    dex: &Dex,
+    annotations: &[AnnotationItem],
+    indent: &str,
+    context: &str,
+) -> Result<(), std::io::Error> {
+    for annotation in annotations {
+        match annotation.to_human_readable(dex) {
+            Ok(lines) => {
+                for line in lines {
+                    writeln!(writer, "{indent}{line}")?;
+                }
+            }
+            Err(e) => dex
+                .diagnostics
+                .warning(Some(context), format!("Failed to write annotation: {e}")),
+        }
+    }
+    Ok(())
+}
+
+fn write_lines<W: std::io::Write>(
+    writer: &mut W,
+    lines: Option<&Vec<String>>,
+) -> Result<(), std::io::Error> {
+    for line in lines.into_iter().flatten() {
+        writeln!(writer, "    {line}")?;
+    }
+    Ok(())
+}
+
+/// Builds the try/catch labels and `.catch` directives of a method, keyed by the address they precede.
+///
+/// The first map holds the `:try_end_N` labels along with their `.catch` directives, which belong before any other directive at the same address. The second holds the `:try_start_N`, `:catch_N` and `:catchall_N` labels.
+fn try_catch_directives(
+    dex: &Dex,
+    code_item: &CodeItem,
+    method_context: &str,
+) -> (BTreeMap<u32, Vec<String>>, BTreeMap<u32, Vec<String>>) {
+    let mut try_ends: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    let mut labels: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+
+    // Handler labels are numbered by address, as a handler may be shared by several try blocks
+    let mut catch_addrs = BTreeSet::new();
+    let mut catch_all_addrs = BTreeSet::new();
+    for handler in &code_item.handlers {
+        catch_addrs.extend(handler.handlers.iter().map(|pair| pair.addr));
+        catch_all_addrs.extend(handler.catch_all_addr);
+    }
+    let catch_label = |addr: u32| {
+        let idx = catch_addrs
+            .iter()
+            .position(|a| *a == addr)
+            .unwrap_or_default();
+        format!(":catch_{idx}")
+    };
+    let catch_all_label = |addr: u32| {
+        let idx = catch_all_addrs
+            .iter()
+            .position(|a| *a == addr)
+            .unwrap_or_default();
+        format!(":catchall_{idx}")
+    };
+    for addr in &catch_addrs {
+        labels.entry(*addr).or_default().push(catch_label(*addr));
+    }
+    for addr in &catch_all_addrs {
+        labels
+            .entry(*addr)
+            .or_default()
+            .push(catch_all_label(*addr));
+    }
+
+    for (i, try_item) in code_item.tries.iter().enumerate() {
+        let Some(end_addr) = try_item.end_addr() else {
+            let e = Error::new(
+                "try_item",
+                code_item.try_item_offset(i),
+                ErrorKind::Malformed(format!(
+                    "Try block {i} starting at {:#x} with {} code units ends past the largest address",
+                    try_item.start_addr, try_item.insn_count
+                )),
+            );
+            dex.diagnostics.record(e.with_context(method_context));
+            continue;
+        };
+        labels
+            .entry(try_item.start_addr)
+            .or_default()
+            .push(format!(":try_start_{i}"));
+
+        let range = format!("{{:try_start_{i} .. :try_end_{i}}}");
+        let lines = try_ends.entry(end_addr).or_default();
+        lines.push(format!(":try_end_{i}"));
+
+        let Some(handler) = code_item.catch_handler(try_item) else {
+            dex.diagnostics.warning(
+                Some(method_context),
+                format!(
+                    "No catch handler at offset {} for try block {}",
+                    try_item.handler_off, i
+                ),
+            );
+            continue;
+        };
+        for pair in &handler.handlers {
+            match dex.types.get(pair.type_idx as usize) {
+                Some(exception_type) => lines.push(format!(
+                    ".catch {exception_type} {range} {}",
+                    catch_label(pair.addr)
+                )),
+                None => dex.diagnostics.warning(
+                    Some(method_context),
+                    format!("Invalid catch type index {}", pair.type_idx),
+                ),
+            }
+        }
+        if let Some(addr) = handler.catch_all_addr {
+            lines.push(format!(".catchall {range} {}", catch_all_label(addr)));
+        }
+    }
+
+    (try_ends, labels)
+}
+
+/// How method bodies are rendered, chosen on the command line.
+#[derive(Debug, Clone, Copy)]
+struct SmaliOptions {
+    /// print `.locals` (the registers not holding arguments) instead of `.registers`
+    locals: bool,
+    /// name the argument registers `p0..pN` instead of `vN`
+    parameter_registers: bool,
+}
+
 fn write_class<W: std::io::Write>(
     writer: &mut W,
     dex: &Dex,
+    class_def: &ClassDefItem,
     class_name: &str,
-    superclass_name: &str,
     class_data_item: &ClassDataItem,
+    options: SmaliOptions,
 ) -> Result<(), std::io::Error> {
-    writeln!(writer, ".class {}", class_name)?;
-    writeln!(writer, ".super {}", superclass_name)?;
+    let flags = access_flags_prefix(
+        dex,
+        class_def.access_flags,
+        AccessFlagsContext::Class,
+        class_name,
+    );
+    writeln!(writer, ".class {flags}{class_name}")?;
 
+    // Root classes such as `Ljava/lang/Object;` have no superclass
+    if class_def.superclass_idx != NO_INDEX {
+        match dex.types.get(class_def.superclass_idx as usize) {
+            Some(superclass_name) => writeln!(writer, ".super {superclass_name}")?,
+            None => dex.diagnostics.warning(
+                Some(class_name),
+                format!("Invalid superclass index {}", class_def.superclass_idx),
+            ),
+        }
+    }
+
+    if class_def.source_file_idx != NO_INDEX {
+        match dex.string_literal(class_def.source_file_idx as usize) {
+            // Like baksmali, an empty source file name is left out
+            Ok(source_file) if source_file == "\"\"" => {}
+            Ok(source_file) => writeln!(writer, ".source {source_file}")?,
+            Err(e) => dex.diagnostics.warning(
+                Some(class_name),
+                format!("Failed to resolve source file: {e}"),
+            ),
+        }
+    }
+
+    let interfaces = recover(
+        dex,
+        dex.type_list(class_def.interfaces_off),
+        class_name,
+        Default::default,
+    )?
+    .list;
+    if !interfaces.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "# interfaces")?;
+        for type_idx in interfaces {
+            match dex.types.get(type_idx as usize) {
+                Some(interface) => writeln!(writer, ".implements {interface}")?,
+                None => dex.diagnostics.warning(
+                    Some(class_name),
+                    format!("Invalid interface index {type_idx}"),
+                ),
+            }
+        }
+    }
+
+    let annotations_directory = recover(
+        dex,
+        dex.annotations_directory(class_def.annotations_off),
+        class_name,
+        Default::default,
+    )?;
+    let annotation_set =
+        |offset: u32| recover(dex, dex.annotation_set(offset), class_name, Vec::new);
+
+    let class_annotations = annotation_set(annotations_directory.class_annotations_off)?;
+    if !class_annotations.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "# annotations")?;
+        write_annotations(writer, dex, &class_annotations, "", class_name)?;
+    }
+
+    let static_values = recover(
+        dex,
+        dex.encoded_array(class_def.static_values_off),
+        class_name,
+        Default::default,
+    )?
+    .values;
+
+    // Static values are paired with the static fields in declaration order; fields past the end of the array keep their default value
     let fields = class_data_item
         .static_fields
         .iter()
-        .chain(class_data_item.instance_fields.iter());
+        .enumerate()
+        .map(|(i, field)| (field, static_values.get(i)))
+        .chain(
+            class_data_item
+                .instance_fields
+                .iter()
+                .map(|field| (field, None)),
+        );
 
-    for field in fields {
-        let field_id = &dex.field_ids[field.field_idx as usize];
-        let field_type = &dex.types[field_id.type_idx as usize];
-        let field_name = &dex.strings[field_id.name_idx as usize];
+    for (field, value) in fields {
+        let Some(field_id) = dex.field_ids.get(field.field_idx as usize) else {
+            dex.diagnostics.warning(
+                Some(class_name),
+                format!("Invalid field index {}", field.field_idx),
+            );
+            continue;
+        };
+        let (Some(field_type), Some(field_name)) = (
+            dex.types.get(field_id.type_idx as usize),
+            dex.strings.get(field_id.name_idx as usize),
+        ) else {
+            dex.diagnostics.warning(
+                Some(class_name),
+                format!("Invalid field id {}", field.field_idx),
+            );
+            continue;
+        };
+
+        let flags = access_flags_prefix(
+            dex,
+            field.access_flags,
+            AccessFlagsContext::Field,
+            field_name,
+        );
 
         writeln!(writer)?;
-        writeln!(writer, ".field {field_name}:{field_type}")?;
+        match value.map(|value| value.to_human_readable(dex)) {
+            Some(Ok(value)) => {
+                writeln!(writer, ".field {flags}{field_name}:{field_type} = {value}")?
+            }
+            Some(Err(e)) => {
+                dex.diagnostics.warning(
+                    Some(class_name),
+                    format!("Failed to resolve static value for {field_name}: {e}"),
+                );
+                writeln!(writer, ".field {flags}{field_name}:{field_type}")?;
+            }
+            None => writeln!(writer, ".field {flags}{field_name}:{field_type}")?,
+        }
+
+        let field_annotations = annotations_directory
+            .field_annotations
+            .iter()
+            .find(|annotation| annotation.field_idx as u64 == field.field_idx)
+            .map(|annotation| annotation_set(annotation.annotations_off))
+            .transpose()?
+            .unwrap_or_default();
+        if !field_annotations.is_empty() {
+            write_annotations(writer, dex, &field_annotations, "    ", class_name)?;
+            writeln!(writer, ".end field")?;
+        }
     }
 
     let methods = class_data_item
@@ -37,33 +349,199 @@ fn write_class<W: std::io::Write>(
         .chain(class_data_item.virtual_methods.iter());
 
     for method in methods {
-        let method_id = &dex.method_ids[method.method_idx as usize];
-        let proto_id = &dex.proto_ids[method_id.proto_idx as usize];
+        let Some(method_id) = dex.method_ids.get(method.method_idx as usize) else {
+            dex.diagnostics.warning(
+                Some(class_name),
+                format!("Invalid method index {}", method.method_idx),
+            );
+            continue;
+        };
+        let Some(method_name) = dex.strings.get(method_id.name_idx as usize) else {
+            dex.diagnostics.warning(
+                Some(class_name),
+                format!("Invalid method id {}", method.method_idx),
+            );
+            continue;
+        };
+        let descriptor = match dex.proto_descriptor(method_id.proto_idx as usize) {
+            Ok(descriptor) => descriptor,
+            Err(e) => {
+                dex.diagnostics.warning(
+                    Some(class_name),
+                    format!("Failed to resolve prototype for {method_name}: {e}"),
+                );
+                continue;
+            }
+        };
+        let method_context = format!("{class_name}->{method_name}{descriptor}");
 
-        let return_type = &dex.types[proto_id.return_type_idx as usize];
-        let method_name = &dex.strings[method_id.name_idx as usize];
+        let flags = access_flags_prefix(
+            dex,
+            method.access_flags,
+            AccessFlagsContext::Method,
+            &method_context,
+        );
 
         writeln!(writer)?;
-        writeln!(writer, ".method {method_name}(){return_type}")?;
+        writeln!(writer, ".method {flags}{method_name}{descriptor}")?;
 
-        let code_item =
-            match CodeItem::try_parse_from_bytes_unsized(&dex.raw[method.code_off as usize..]) {
-                Ok(code_item) => code_item,
-                Err(e) => {
-                    eprintln!("Failed to parse CodeItem for {}: {}", method_name, e);
+        // Abstract and native methods have no code
+        let code_item = if method.code_off != 0 {
+            let code_item = dex.code_item(method.code_off).map(Some);
+            recover(dex, code_item, &method_context, || None)?
+        } else {
+            None
+        };
+        let debug_info = match &code_item {
+            Some(code_item) => recover(
+                dex,
+                dex.debug_info(code_item.debug_info_off),
+                &method_context,
+                Default::default,
+            )?,
+            None => Default::default(),
+        };
+
+        if let Some(code_item) = &code_item {
+            if options.locals {
+                let locals = code_item.registers_size.saturating_sub(code_item.ins_size);
+                writeln!(writer, "    .locals {locals}")?;
+            } else {
+                writeln!(writer, "    .registers {}", code_item.registers_size)?;
+            }
+        }
+
+        let parameter_annotations = annotations_directory
+            .parameter_annotations
+            .iter()
+            .find(|annotation| annotation.method_idx as u64 == method.method_idx)
+            .map(|annotation| {
+                recover(
+                    dex,
+                    dex.annotation_set_ref_list(annotation.annotations_off),
+                    &method_context,
+                    Vec::new,
+                )
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let parameters_count = parameter_annotations
+            .len()
+            .max(debug_info.parameter_names.len());
+        if parameters_count > 0 {
+            let is_static = method.access_flags.contains(AccessFlags::STATIC);
+            let registers = dex
+                .parameter_registers(method_id.proto_idx as usize, is_static)
+                .unwrap_or_default();
+            for i in 0..parameters_count {
+                let annotations = parameter_annotations
+                    .get(i)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let name_idx = debug_info.parameter_names.get(i).copied().flatten();
+                if annotations.is_empty() && name_idx.is_none() {
+                    continue;
+                }
+                let Some(register) = registers.get(i) else {
+                    dex.diagnostics.warning(
+                        Some(&method_context),
+                        format!("Parameter {i} has debug info but no register"),
+                    );
                     continue;
+                };
+                match name_idx.map(|idx| dex.string_literal(idx as usize)) {
+                    Some(Ok(name)) => writeln!(writer, "    .param p{register}, {name}")?,
+                    Some(Err(_)) => {
+                        dex.diagnostics.warning(
+                            Some(&method_context),
+                            format!("Invalid name for parameter {i}"),
+                        );
+                        writeln!(writer, "    .param p{register}")?;
+                    }
+                    None => writeln!(writer, "    .param p{register}")?,
                 }
-            };
+                if !annotations.is_empty() {
+                    write_annotations(writer, dex, annotations, "        ", &method_context)?;
+                    writeln!(writer, "    .end param")?;
+                }
+            }
+        }
 
-        for insns in &code_item.insns {
-            match insns.to_human_readable(dex) {
-                Ok(repr) => writeln!(writer, "    {repr}")?,
+        let method_annotations = annotations_directory
+            .method_annotations
+            .iter()
+            .find(|annotation| annotation.method_idx as u64 == method.method_idx)
+            .map(|annotation| annotation_set(annotation.annotations_off))
+            .transpose()?
+            .unwrap_or_default();
+        write_annotations(writer, dex, &method_annotations, "    ", &method_context)?;
+
+        let Some(code_item) = code_item else {
+            writeln!(writer, ".end method")?;
+            continue;
+        };
+
+        let (try_ends, labels) = try_catch_directives(dex, &code_item, &method_context);
+        let context = code_item.render_context(options.parameter_registers);
+
+        // Directives are emitted in address order right before the instruction they refer to
+        let mut events = debug_info.events.iter().peekable();
+        let mut end = 0;
+        for (address, insn) in code_item.instructions() {
+            write_lines(writer, try_ends.get(&address))?;
+            while let Some(event) = events.next_if(|event| event.address <= address) {
+                match event.kind.to_human_readable(dex, &context) {
+                    Ok(repr) => writeln!(writer, "    {repr}")?,
+                    Err(e) => dex.diagnostics.warning(
+                        Some(&method_context),
+                        format!("Failed to write debug info: {e}"),
+                    ),
+                }
+            }
+            // baksmali prints all labels of an address sorted by name
+            let mut address_labels: Vec<&str> = labels
+                .get(&address)
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .chain(context.labels_at(address))
+                .collect();
+            address_labels.sort_unstable();
+            for label in address_labels {
+                writeln!(writer, "    {label}")?;
+            }
+
+            end = address + insn.size_bytes() as u32 / 2;
+            match insn.to_human_readable(dex, address, &context) {
+                Ok(repr) => {
+                    for line in repr.lines() {
+                        writeln!(writer, "    {line}")?;
+                    }
+                }
                 Err(e) => {
-                    eprintln!("Failed to write instruction: {e}");
+                    dex.diagnostics.warning(
+                        Some(&method_context),
+                        format!(
+                            "Failed to write instruction at {:#x}: {e}",
+                            code_item.file_offset(address).unwrap_or_default()
+                        ),
+                    );
                     continue;
                 }
             }
         }
+        for lines in try_ends.range(end..).map(|(_, lines)| lines) {
+            write_lines(writer, Some(lines))?;
+        }
+        for event in events {
+            match event.kind.to_human_readable(dex, &context) {
+                Ok(repr) => writeln!(writer, "    {repr}")?,
+                Err(e) => dex.diagnostics.warning(
+                    Some(&method_context),
+                    format!("Failed to write debug info: {e}"),
+                ),
+            }
+        }
 
         writeln!(writer, ".end method")?;
     }
@@ -71,54 +549,375 @@ fn write_class<W: std::io::Write>(
     Ok(())
 }
 
-fn main() {
-    let path = std::env::args().nth(1).expect("Please provide a file path");
-    let buffer = std::fs::read(&path).expect("Failed to read file");
-    let dex = Dex::try_parse_from_bytes(&buffer).expect("Failed to parse DEX file");
-
-    let out_path = Path::new("out-smali");
-    if let Err(e) = std::fs::remove_dir_all(out_path) {
-        eprint!("Failed to remove directory: {e}");
+fn write_sections<W: std::io::Write>(writer: &mut W, dex: &Dex) -> Result<(), std::io::Error> {
+    writeln!(
+        writer,
+        "{:<28} {:<6} {:<10} {:>10}",
+        "type", "code", "offset", "size"
+    )?;
+    for item in &dex.map_list.list {
+        writeln!(
+            writer,
+            "{:<28} {:#06x} {:#010x} {:>10}",
+            item.type_.to_string(),
+            u16::from(item.type_),
+            item.offset,
+            item.size
+        )?;
     }
-    std::fs::create_dir(out_path).unwrap();
-
-    let start_time = std::time::Instant::now();
+    Ok(())
+}
 
-    dex.class_defs.par_iter().for_each(|class| {
-        let class_name = &dex.types[class.class_idx as usize];
-        let superclass_name = &dex.types[class.superclass_idx as usize];
-        let class_data_offset = class.class_data_off as usize;
-        let class_data_item =
-            match ClassDataItem::try_parse_from_bytes_unsized(&buffer[class_data_offset..]) {
-                Ok(class_data_item) => class_data_item,
-                Err(e) => {
-                    eprintln!("Failed to parse ClassDataItem for {}: {}", class_name, e);
-                    return; // Skip this class if parsing fails
-                }
-            };
+/// Writes one smali file per class of `dex` into `out_path`. In strict mode the first class that fails stops the run.
+fn disassemble(dex: &Dex, out_path: &Path, options: SmaliOptions) -> Result<(), std::io::Error> {
+    dex.class_defs.par_iter().try_for_each(|class| {
+        let Some(class_name) = dex.types.get(class.class_idx as usize) else {
+            dex.diagnostics
+                .warning(None, format!("Invalid class index {}", class.class_idx));
+            return Ok(());
+        };
+        // Skip this class if parsing fails
+        let class_data = dex.class_data(class.class_data_off).map(Some);
+        let Some(class_data_item) = recover(dex, class_data, class_name, || None)? else {
+            return Ok(());
+        };
 
-        let class_name_stripped = &class_name[1..class_name.len() - 1]; // Remove 'L' and ';'
+        let class_name_stripped = class_name
+            .strip_prefix('L')
+            .and_then(|name| name.strip_suffix(';'))
+            .unwrap_or(class_name);
 
         let class_out_path =
             out_path.join(format!("{}.smali", class_name_stripped.replace('/', "_")));
         
-        let mut class_out_file = File::create(&class_out_path)
-            .unwrap_or_else(|_| panic!("Failed to create file: {}", class_out_path.display()));
+        let mut class_out_file = File::create(&class_out_path).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("Failed to create file {}: {e}", class_out_path.display()),
+            )
+        })?;
 
-        if let Err(e) = write_class(
+        let result = write_class(
             &mut class_out_file,
-            &dex,
-            class_name_stripped,
-            superclass_name,
+            dex,
+            class,
+            class_name,
             &class_data_item,
-        ) {
-            eprintln!("Failed to write class {}: {}", class_name, e);
+            options,
+        );
+        match result {
+            Err(e) if !dex.diagnostics.is_strict() => {
+                dex.diagnostics
+                    .warning(Some(class_name), format!("Failed to write class: {e}"));
+                Ok(())
+            }
+            result => result,
+        }
+    })
+}
+
+/// Returns where the header field checked by `error` lives, relative to the header.
+fn integrity_offset(error: &IntegrityError) -> usize {
+    match error {
+        IntegrityError::Truncated { .. } => 0x20, // file_size
+        IntegrityError::Checksum { .. } => 0x08,
+        IntegrityError::Signature { .. } => 0x0C,
+    }
+}
+
+/// Prints everything recorded in `diagnostics` followed by a count per severity, and writes them as JSON Lines to `path` if given.
+fn report(diagnostics: &Diagnostics, path: Option<&str>) {
+    let entries = diagnostics.entries();
+    for diagnostic in &entries {
+        eprintln!("{diagnostic}");
+    }
+    let count = |severity| {
+        entries
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    if !entries.is_empty() {
+        eprintln!(
+            "{} errors, {} warnings",
+            count(Severity::Error),
+            count(Severity::Warning)
+        );
+    }
+
+    if let Some(path) = path {
+        if let Err(e) = std::fs::write(path, diagnostics.to_json_lines()) {
+            eprintln!("Failed to write diagnostics to {path}: {e}");
+        }
+    }
+}
+
+enum Command {
+    /// Write one smali file per class into `out-smali`
+    Disassemble,
+    /// Print the section table from the map_list
+    Sections,
+    /// Check the structural constraints of the format and the bytecode of every method, and report every violation
+    Verify,
+}
+
+fn main() {
+    // `--strict` stops at the first structure that fails to parse, and refuses files whose checksum or signature does not match their contents
+    // `--locals` prints `.locals` instead of `.registers`
+    // `--no-parameter-registers` names every register `vN`, including the arguments
+    // `--diagnostics=<path>` writes every problem found as JSON Lines to `path`
+    let (flags, args): (Vec<_>, Vec<_>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    for flag in &flags {
+        if !["--strict", "--locals", "--no-parameter-registers"].contains(&flag.as_str())
+            && !flag.starts_with("--diagnostics=")
+        {
+            eprintln!("Ignoring unknown option {flag}");
+        }
+    }
+    let has_flag = |name: &str| flags.iter().any(|flag| flag == name);
+    let diagnostics_path = flags
+        .iter()
+        .find_map(|flag| flag.strip_prefix("--diagnostics="));
+    let diagnostics = Diagnostics::new(has_flag("--strict"));
+    let options = SmaliOptions {
+        locals: has_flag("--locals"),
+        parameter_registers: !has_flag("--no-parameter-registers"),
+    };
+    let mut args = args.into_iter();
+    let first = args.next().expect("Please provide a file path");
+    let command = match first.as_str() {
+        "sections" => Some(Command::Sections),
+        "verify" => Some(Command::Verify),
+        _ => None,
+    };
+    let (command, path) = match command {
+        Some(command) => (command, args.next().expect("Please provide a file path")),
+        None => (Command::Disassemble, first),
+    };
+
+    let buffer = match std::fs::read(&path) {
+        Ok(buffer) => buffer,
+        Err(e) => {
+            eprintln!("Failed to read {path}: {e}");
+            std::process::exit(1);
+        }
+    };
+    let dexes = match DexContainer::new(&buffer, &diagnostics).collect::<Result<Vec<_>, _>>() {
+        Ok(dexes) => dexes,
+        Err(e) => {
+            report(&diagnostics, diagnostics_path);
+            eprintln!("Failed to parse DEX file: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    // Parsing does not look at the checksum or signature, so every mismatch is recorded here before strict mode refuses the file
+    let mut corrupted = false;
+    for (i, dex) in dexes.iter().enumerate() {
+        for e in dex.verify_integrity() {
+            let header_offset = dex.header_item.header_offset as usize;
+            let e = Error::new("header_item", header_offset + integrity_offset(&e), e)
+                .within(format!("dex {i}"));
+            diagnostics.record(e);
+            corrupted = true;
+        }
+    }
+    if corrupted && diagnostics.is_strict() {
+        report(&diagnostics, diagnostics_path);
+        eprintln!("Refusing to process a corrupted file in strict mode");
+        std::process::exit(1);
+    }
+
+    if let Command::Verify = command {
+        for (i, dex) in dexes.iter().enumerate() {
+            for e in dex
+                .verify_structure()
+                .into_iter()
+                .chain(dex.verify_methods())
+            {
+                diagnostics.record(e.within(format!("dex {i}")));
+            }
         }
-    });
+        report(&diagnostics, diagnostics_path);
+        let failed = diagnostics
+            .entries()
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error);
+        std::process::exit(if failed { 1 } else { 0 });
+    }
+
+    if let Command::Sections = command {
+        let mut stdout = std::io::stdout().lock();
+        for (i, dex) in dexes.iter().enumerate() {
+            if dexes.len() > 1 {
+                if i > 0 {
+                    println!();
+                }
+                println!("# dex {i} at offset {:#x}", dex.header_item.header_offset);
+            }
+            if let Err(e) = write_sections(&mut stdout, dex) {
+                eprintln!("Failed to write section table: {e}");
+            }
+        }
+        report(&diagnostics, diagnostics_path);
+        return;
+    }
+
+    let out_path = Path::new("out-smali");
+    if let Err(e) = std::fs::remove_dir_all(out_path) {
+        eprint!("Failed to remove directory: {e}");
+    }
+    if let Err(e) = std::fs::create_dir(out_path) {
+        eprintln!("Failed to create directory {}: {e}", out_path.display());
+        std::process::exit(1);
+    }
+
+    let start_time = std::time::Instant::now();
+
+    // The dex files of a container hold disjoint sets of classes, so they share the output directory
+    for dex in &dexes {
+        if let Err(e) = disassemble(dex, out_path, options) {
+            report(&diagnostics, diagnostics_path);
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
 
     let elapsed_time = start_time.elapsed();
     println!(
         "Elapsed time: {} seconds",
         elapsed_time.as_secs_f32()
     );
+    report(&diagnostics, diagnostics_path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dex::fixture::DexBuilder;
+
+    const OPTIONS: SmaliOptions = SmaliOptions {
+        locals: false,
+        parameter_registers: true,
+    };
+
+    /// Renders the first class of `file` without class data.
+    fn render_class(file: &[u8], diagnostics: &Diagnostics) -> String {
+        let dex = Dex::try_parse_from_bytes(file, diagnostics).unwrap();
+        let class_def = &dex.class_defs[0];
+        let class_name = dex.types.get(class_def.class_idx as usize).unwrap();
+        let mut out = Vec::new();
+        write_class(
+            &mut out,
+            &dex,
+            class_def,
+            class_name,
+            &ClassDataItem::default(),
+            OPTIONS,
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_class_header() {
+        let mut builder = DexBuilder::new();
+        let class_idx = builder.type_("LFoo;");
+        let superclass_idx = builder.type_("Ljava/lang/Object;");
+        let interface_idx = builder.type_("LBar;");
+        let source_file_idx = builder.string("Foo.java");
+        let mut interfaces = 1u32.to_le_bytes().to_vec();
+        interfaces.extend((interface_idx as u16).to_le_bytes());
+        let interfaces_off = builder.data(&interfaces);
+        builder.class_def([
+            class_idx,
+            0x0001,
+            superclass_idx,
+            interfaces_off,
+            source_file_idx,
+            0,
+            0,
+            0,
+        ]);
+        let diagnostics = Diagnostics::new(true);
+
+        assert_eq!(
+            render_class(&builder.build(), &diagnostics),
+            ".class public LFoo;\n\
+             .super Ljava/lang/Object;\n\
+             .source \"Foo.java\"\n\
+             \n\
+             # interfaces\n\
+             .implements LBar;\n"
+        );
+        assert!(diagnostics.entries().is_empty());
+    }
+
+    #[test]
+    fn test_root_class_header() {
+        let mut builder = DexBuilder::new();
+        let class_idx = builder.type_("Ljava/lang/Object;");
+        builder.class_def([class_idx, 0x0001, NO_INDEX, 0, NO_INDEX, 0, 0, 0]);
+        let diagnostics = Diagnostics::new(true);
+
+        assert_eq!(
+            render_class(&builder.build(), &diagnostics),
+            ".class public Ljava/lang/Object;\n"
+        );
+        assert!(diagnostics.entries().is_empty());
+    }
+
+    #[test]
+    fn test_empty_source_file() {
+        let mut builder = DexBuilder::new();
+        let class_idx = builder.type_("Ljava/lang/Object;");
+        let source_file_idx = builder.string("");
+        builder.class_def([class_idx, 0x0001, NO_INDEX, 0, source_file_idx, 0, 0, 0]);
+        let diagnostics = Diagnostics::new(true);
+
+        assert_eq!(
+            render_class(&builder.build(), &diagnostics),
+            ".class public Ljava/lang/Object;\n"
+        );
+        assert!(diagnostics.entries().is_empty());
+    }
+
+    #[test]
+    fn test_unwritable_output_path() {
+        let mut builder = DexBuilder::new();
+        let class_idx = builder.type_("LFoo;");
+        builder.class_def([class_idx, 0x0001, NO_INDEX, 0, NO_INDEX, 0, 0, 0]);
+        let file = builder.build();
+        let diagnostics = Diagnostics::new(false);
+        let dex = Dex::try_parse_from_bytes(&file, &diagnostics).unwrap();
+
+        let out_path = std::env::temp_dir().join("dex2smali-missing-dir/nested");
+        let e = disassemble(&dex, &out_path, OPTIONS).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+        assert!(e.to_string().contains("Foo.smali"));
+    }
+
+    #[test]
+    fn test_invalid_class_header() {
+        let mut builder = DexBuilder::new();
+        let class_idx = builder.type_("LFoo;");
+        // a superclass and source file past the end of their tables, and interfaces past the end of the file
+        builder.class_def([class_idx, 0x0000, 7, 0xFFFF_0000, 9, 0, 0, 0]);
+        let diagnostics = Diagnostics::new(false);
+
+        assert_eq!(
+            render_class(&builder.build(), &diagnostics),
+            ".class LFoo;\n"
+        );
+        let entries = diagnostics.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].message, "Invalid superclass index 7");
+        assert!(entries[1].message.starts_with("Failed to resolve source file"));
+        // The interfaces are reported where the type_list parser stopped
+        assert_eq!(entries[2].structure, Some("type_list"));
+        assert_eq!(entries[2].offset, Some(0xFFFF_0000));
+        assert_eq!(entries[2].context.as_deref(), Some("LFoo;"));
+    }
 }
@@ -1,8 +1,106 @@
+use crate::{
+    errors::{Error, ErrorKind},
+    traits::parse::TryParseFromBytes,
+};
+
+/// A bounds-checked little-endian cursor over a structure. Every read that runs past the end of the buffer or hits a malformed LEB128 value fails with an [`Error`] naming the structure and the offset of the read.
+///
+/// Offsets are relative to the start of `buffer`; callers that only hand out part of the file rebase errors with [`Error::offset_by`].
+pub struct Reader<'a> {
+    buffer: &'a [u8],
+    /// position of the next read
+    pub offset: usize,
+    structure: &'static str,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buffer: &'a [u8], structure: &'static str) -> Self {
+        Self::at(buffer, 0, structure)
+    }
+
+    pub fn at(buffer: &'a [u8], offset: usize, structure: &'static str) -> Self {
+        Self {
+            buffer,
+            offset,
+            structure,
+        }
+    }
+
+    /// Number of bytes left after the current offset. Use it to cap preallocation by untrusted sizes.
+    pub fn remaining(&self) -> usize {
+        self.buffer.len().saturating_sub(self.offset)
+    }
+
+    /// Builds an error for the structure being read at the current offset.
+    pub fn error(&self, kind: impl Into<ErrorKind>) -> Error {
+        Error::new(self.structure, self.offset, kind)
+    }
+
+    /// Returns the next `len` bytes.
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.buffer.get(self.offset..end))
+            .ok_or_else(|| {
+                self.error(ErrorKind::UnexpectedEof {
+                    needed: len,
+                    available: self.remaining(),
+                })
+            })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, Error> {
+        Ok(read_u16_le(self.bytes(2)?, 0))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Error> {
+        Ok(read_u32_le(self.bytes(4)?, 0))
+    }
+
+    /// Reads a ULEB128 value; `what` names the field in the error.
+    pub fn uleb128(&mut self, what: &'static str) -> Result<u64, Error> {
+        let (value, bytes_used) =
+            decode_uleb128(self.buffer.get(self.offset..).unwrap_or_default())
+                .ok_or_else(|| self.error(ErrorKind::Leb128(what)))?;
+        self.offset += bytes_used;
+        Ok(value)
+    }
+
+    /// Reads a `uleb128p1`, mapping the encoded `NO_INDEX` to `None`.
+    pub fn uleb128p1(&mut self, what: &'static str) -> Result<Option<u32>, Error> {
+        Ok(self.uleb128(what)?.checked_sub(1).map(|value| value as u32))
+    }
+
+    /// Reads a SLEB128 value; `what` names the field in the error.
+    pub fn sleb128(&mut self, what: &'static str) -> Result<i64, Error> {
+        let (value, bytes_used) =
+            decode_sleb128(self.buffer.get(self.offset..).unwrap_or_default())
+                .ok_or_else(|| self.error(ErrorKind::Leb128(what)))?;
+        self.offset += bytes_used;
+        Ok(value)
+    }
+
+    /// Parses a fixed-size structure and advances past it.
+    pub fn parse<T: TryParseFromBytes>(&mut self) -> Result<T, Error> {
+        let item = T::try_parse_from_bytes(self.buffer.get(self.offset..).unwrap_or_default())
+            .map_err(|e| Error::new(T::NAME, self.offset, e))?;
+        self.offset += T::SIZE;
+        Ok(item)
+    }
+}
+
 /// Reads a 64-bit unsigned integer from the given byte slice at `offset` in little-endian order.
 ///
 /// # Panics
 ///
-/// Panics if the slice is not long enough to read 8 bytes.
+/// Panics if the slice is not long enough to read 8 bytes. Use [`Reader`] for input whose length has not been checked.
 pub fn read_u64_le(data: &[u8], offset: usize) -> u64 {
     u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
 }
@@ -11,7 +109,7 @@ pub fn read_u64_le(data: &[u8], offset: usize) -> u64 {
 ///
 /// # Panics
 ///
-/// Panics if the slice is not long enough to read 4 bytes.
+/// Panics if the slice is not long enough to read 4 bytes. Use [`Reader`] for input whose length has not been checked.
 pub fn read_u32_le(data: &[u8], offset: usize) -> u32 {
     u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
 }
@@ -20,7 +118,7 @@ pub fn read_u32_le(data: &[u8], offset: usize) -> u32 {
 ///
 /// # Panics
 ///
-/// Panics if the slice is not long enough to read 2 bytes.
+/// Panics if the slice is not long enough to read 2 bytes. Use [`Reader`] for input whose length has not been checked.
 pub fn read_u16_le(data: &[u8], offset: usize) -> u16 {
     u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
 }
@@ -57,7 +155,83 @@ pub fn decode_uleb128(input: &[u8]) -> Option<(u64, usize)> {
     None
 }
 
+/// Decodes a SLEB128-encoded integer from the given byte slice.
+/// Returns the decoded value and the number of bytes read, or `None` if the encoding is malformed (i.e., exceeds 10 bytes or is truncated).
+pub fn decode_sleb128(input: &[u8]) -> Option<(i64, usize)> {
+    let mut result: i64 = 0;
+    let mut shift = 0;
+    let mut count = 0;
+
+    for byte in input {
+        result |= ((byte & 0x7F) as i64) << shift;
+        shift += 7;
+        count += 1;
+
+        if (byte & 0x80) == 0 {
+            if shift < 64 && (byte & 0x40) != 0 {
+                // Sign-extend from the last byte read
+                result |= -1i64 << shift;
+            }
+            return Some((result, count));
+        }
+
+        if shift >= 64 {
+            break;
+        }
+    }
+
+    None
+}
+
+/// Computes the Adler-32 checksum of the given byte slice, as used by the dex header `checksum`.
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    // The largest number of bytes that can be summed before `b` may overflow a u32
+    const NMAX: usize = 5552;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(NMAX) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
 /// Converts a byte to a tuple of two nibbles (4-bit values): (lo, hi).
 pub const fn to_nibbles(byte: u8) -> (u8, u8) {
     (byte & 0x0F, byte >> 4)
 }
+
+/// Renders an integer the way smali writes literals, in hex with the sign in front and `suffix` after, e.g. `-0x1t`.
+pub fn hex_literal(value: i64, suffix: &str) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    format!("{sign}{:#x}{suffix}", value.unsigned_abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 0x00000001);
+        assert_eq!(adler32(b"a"), 0x00620062);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    }
+
+    #[test]
+    fn test_adler32_reduces_long_input() {
+        // Longer than NMAX, so the sums are reduced between chunks
+        let data = vec![0xFF; 100_000];
+        let (mut a, mut b) = (1u64, 0u64);
+        for byte in &data {
+            a = (a + *byte as u64) % 65521;
+            b = (b + a) % 65521;
+        }
+        assert_eq!(adler32(&data), ((b << 16) | a) as u32);
+    }
+}